@@ -0,0 +1,147 @@
+package com.example.app;
+
+import android.app.NativeActivity;
+import android.content.BroadcastReceiver;
+import android.content.Context;
+import android.content.Intent;
+import android.content.IntentFilter;
+import android.content.pm.ResolveInfo;
+import android.graphics.Rect;
+import android.os.Bundle;
+import android.view.ActionMode;
+import android.view.Menu;
+import android.view.MenuItem;
+import android.view.View;
+import androidx.core.content.ContextCompat;
+
+/**
+ * An activity with the Java glue some features of egui_android need, which a
+ * plain NativeActivity can't provide. Copy the parts you need into your own
+ * activity, and name it in AndroidManifest.xml instead of NativeActivity.
+ */
+public class MainActivity extends NativeActivity {
+    // Share results, for share_with_result. The receiver is registered here
+    // rather than in AndroidManifest.xml, but ContextCompat needs the
+    // androidx.core library, which the README's FileProvider also needs.
+
+    private final BroadcastReceiver shareTargetReceiver = new BroadcastReceiver() {
+        @Override
+        public void onReceive(Context context, Intent intent) {
+            onShareTargetChosen();
+        }
+    };
+
+    @Override
+    protected void onCreate(Bundle savedInstanceState) {
+        super.onCreate(savedInstanceState);
+        ContextCompat.registerReceiver(this, shareTargetReceiver,
+                new IntentFilter(getPackageName() + ".SHARE_TARGET_CHOSEN"),
+                ContextCompat.RECEIVER_NOT_EXPORTED);
+    }
+
+    @Override
+    protected void onDestroy() {
+        unregisterReceiver(shareTargetReceiver);
+        super.onDestroy();
+    }
+
+    // Implemented by egui_android.
+    private native void onShareTargetChosen();
+
+    // Text selection menu, for TextSelectionMenu::System.
+
+    private ActionMode textActionMode;
+    private final Rect textActionRect = new Rect();
+    private int textActions;
+    private String selectedText;
+
+    // Called by egui_android, from any thread. The rectangle is in pixels,
+    // and `actions` is a bit set of cut (1), copy (2), paste (4) and select
+    // all (8). The text is null for passwords.
+    public void showTextActionMode(
+            int left, int top, int right, int bottom, int actions, String text) {
+        runOnUiThread(() -> {
+            textActionRect.set(left, top, right, bottom);
+            textActions = actions;
+            selectedText = text;
+            if (textActionMode != null) {
+                textActionMode.invalidate();
+                textActionMode.invalidateContentRect();
+                return;
+            }
+            textActionMode = getWindow().getDecorView().startActionMode(
+                    new ActionMode.Callback2() {
+                @Override
+                public boolean onCreateActionMode(ActionMode mode, Menu menu) {
+                    return true;
+                }
+
+                @Override
+                public boolean onPrepareActionMode(ActionMode mode, Menu menu) {
+                    menu.clear();
+                    if ((textActions & 1) != 0)
+                        menu.add(0, android.R.id.cut, 0, android.R.string.cut);
+                    if ((textActions & 2) != 0)
+                        menu.add(0, android.R.id.copy, 1, android.R.string.copy);
+                    if ((textActions & 4) != 0)
+                        menu.add(0, android.R.id.paste, 2, android.R.string.paste);
+                    if ((textActions & 8) != 0)
+                        menu.add(0, android.R.id.selectAll, 3, android.R.string.selectAll);
+                    if (selectedText != null) {
+                        Intent share = new Intent(Intent.ACTION_SEND)
+                                .setType("text/plain")
+                                .putExtra(Intent.EXTRA_TEXT, selectedText);
+                        menu.add(0, 0, 4, "Share")
+                                .setIntent(Intent.createChooser(share, null));
+                        Intent process = new Intent(Intent.ACTION_PROCESS_TEXT)
+                                .setType("text/plain");
+                        for (ResolveInfo info : getPackageManager()
+                                .queryIntentActivities(process, 0)) {
+                            menu.add(0, 0, 5, info.loadLabel(getPackageManager()))
+                                    .setIntent(new Intent(process)
+                                            .setClassName(info.activityInfo.packageName,
+                                                    info.activityInfo.name)
+                                            .putExtra(Intent.EXTRA_PROCESS_TEXT, selectedText)
+                                            .putExtra(Intent.EXTRA_PROCESS_TEXT_READONLY, true));
+                        }
+                    }
+                    return true;
+                }
+
+                @Override
+                public boolean onActionItemClicked(ActionMode mode, MenuItem item) {
+                    // Items with an intent are started by the system.
+                    if (item.getIntent() != null) return false;
+                    onTextAction(item.getItemId());
+                    return true;
+                }
+
+                @Override
+                public void onDestroyActionMode(ActionMode mode) {
+                    if (textActionMode == mode) {
+                        textActionMode = null;
+                        onTextAction(0);
+                    }
+                }
+
+                @Override
+                public void onGetContentRect(ActionMode mode, View view, Rect outRect) {
+                    outRect.set(textActionRect);
+                }
+            }, ActionMode.TYPE_FLOATING);
+        });
+    }
+
+    // Called by egui_android, from any thread.
+    public void hideTextActionMode() {
+        runOnUiThread(() -> {
+            ActionMode mode = textActionMode;
+            textActionMode = null;
+            if (mode != null) mode.finish();
+        });
+    }
+
+    // Implemented by egui_android, with the ID of the menu item picked, or 0
+    // once the menu is closed.
+    private native void onTextAction(int itemId);
+}
@@ -1,5 +1,9 @@
-use egui::{Context, Rect};
-use std::time::Duration;
+use crate::{
+    GraphicsError, GraphicsOptions, OpenUrlError, SoftInputAdjust, Storage, TextSelectionMenu,
+};
+use egui::{ColorImage, Context, Rect};
+use egui_glow::glow;
+use std::{sync::Arc, time::Duration};
 
 /// Core trait for implementing the root of an egui Android application.
 ///
@@ -18,12 +22,190 @@ pub trait Activity {
     /// second.
     fn update(&mut self, ctx: &Context);
 
+    /// Called once after the activity is created, to load any data previously
+    /// written by [`Activity::save`].
+    fn load(&mut self, _storage: &dyn Storage) {
+        // By default, do nothing.
+    }
+
+    /// Called periodically and when the activity is paused, stopped or
+    /// destroyed, to persist data that should survive the app being closed.
+    ///
+    /// Unlike [`Activity::save_state`], data written here is kept until the
+    /// app is uninstalled or its data is cleared.
+    fn save(&mut self, _storage: &mut dyn Storage) {
+        // By default, do nothing.
+    }
+
+    /// Time between automatic calls to [`Activity::save`].
+    fn auto_save_interval(&self) -> Duration {
+        Duration::from_secs(30)
+    }
+
+    /// Called when the activity becomes visible to the user.
+    fn on_start(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when the activity starts interacting with the user, after any
+    /// previously saved state has been restored.
+    fn on_resume(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when the activity is no longer in the foreground. This is a good
+    /// place to stop animations, audio, or anything else that consumes CPU.
+    fn on_pause(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when the activity is no longer visible to the user. The process
+    /// may be killed at any time after this without further notice, so any
+    /// unsaved data should be flushed here.
+    fn on_stop(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when a window has been created and the UI is about to start
+    /// being drawn into it.
+    fn on_window_created(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when the window has been destroyed. `update` will not be called
+    /// again until a new window is created.
+    fn on_window_destroyed(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when the activity's window gains or loses input focus.
+    fn on_focus_changed(&mut self, _focused: bool) {
+        // By default, do nothing.
+    }
+
+    /// Called when the activity is being destroyed, just before the event
+    /// loop exits.
+    fn on_destroy(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Called when Android asks the activity to save its state, which happens
+    /// before the activity is stopped. The returned bytes will be passed to
+    /// [`Activity::restore_state`] if the activity is later resumed, even if
+    /// the process was killed in the meantime.
+    ///
+    /// Android limits the size of saved state, so this should be kept small.
+    /// If `None` is returned, only egui's own memory is saved.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Called when the activity is resumed with state previously returned by
+    /// [`Activity::save_state`]. This happens before the first call to
+    /// `update` after resuming.
+    fn restore_state(&mut self, _bytes: &[u8]) {
+        // By default, do nothing.
+    }
+
     /// Called when the rectangle in the window in which content should be
     /// placed has changed.
     fn on_content_rect_changed(&mut self, _new_rect: Rect) {
         // By default, do nothing.
     }
 
+    /// Options for the framebuffer and OpenGL ES context. This is called once,
+    /// right after the activity is created.
+    fn graphics_options(&self) -> GraphicsOptions {
+        GraphicsOptions::default()
+    }
+
+    /// Called when a new OpenGL context has been created, before the first
+    /// frame is drawn with it.
+    ///
+    /// The context can be used to create GL resources for custom painting with
+    /// [`egui_glow::CallbackFn`]. This is called again if the context is
+    /// recreated, such as after it was lost while the app was in the
+    /// background, in which case any resources created with the previous
+    /// context are no longer valid and must be created again.
+    fn on_gl_context_created(&mut self, _gl: &Arc<glow::Context>) {
+        // By default, do nothing.
+    }
+
+    /// Called when the wgpu device and renderer have been created, before the
+    /// first frame is drawn with them. Only used with the `wgpu` feature.
+    ///
+    /// The render state can be used to register resources for custom painting
+    /// with [`egui_wgpu::Callback`]. Like
+    /// [`on_gl_context_created`](Self::on_gl_context_created), this is called
+    /// again if the device has to be recreated.
+    #[cfg(feature = "wgpu")]
+    fn on_wgpu_render_state_created(&mut self, _render_state: &egui_wgpu::RenderState) {
+        // By default, do nothing.
+    }
+
+    /// Called when the graphics backend fails, for example because of a driver
+    /// bug. Nothing is drawn until the backend recovers, which is attempted
+    /// again the next time a window is created.
+    fn on_graphics_error(&mut self, _error: &GraphicsError) {
+        // By default, do nothing.
+    }
+
+    /// How the UI should make room for the soft keyboard.
+    fn soft_input_adjust(&self) -> SoftInputAdjust {
+        SoftInputAdjust::default()
+    }
+
+    /// Whether to show an enlarged view of the text under the finger while a
+    /// text selection handle is dragged.
+    fn show_text_magnifier(&self) -> bool {
+        true
+    }
+
+    /// Which menu of clipboard actions to show for selected text.
+    fn text_selection_menu(&self) -> TextSelectionMenu {
+        TextSelectionMenu::default()
+    }
+
+    /// Called when an image on the clipboard is pasted, as egui can only
+    /// paste text. The image can be shown with
+    /// [`Context::load_texture`](egui::Context::load_texture).
+    fn on_paste_image(&mut self, _image: Arc<ColorImage>) {
+        // By default, do nothing.
+    }
+
+    /// Called when something new is copied to the clipboard, by this app or
+    /// by another app while this one was in the background, which is only
+    /// noticed once the app gains focus again. Needs Android 8 or later.
+    fn on_clipboard_changed(&mut self) {
+        // By default, do nothing.
+    }
+
+    /// Whether web links opened with [`OpenUrl::same_tab`](egui::OpenUrl::same_tab)
+    /// are shown in a [Custom Tab](https://developer.chrome.com/docs/android/custom-tabs)
+    /// on top of the app, with a toolbar colored like egui's panels, instead
+    /// of switching to the browser. Links opened with
+    /// [`OpenUrl::new_tab`](egui::OpenUrl::new_tab) always open the browser.
+    fn open_links_in_custom_tabs(&self) -> bool {
+        false
+    }
+
+    /// Called when a URL opened with [`Context::open_url`] could not be
+    /// opened, such as when no installed app can handle it.
+    fn on_open_url_error(&mut self, _url: &str, _error: &OpenUrlError) {
+        // By default, do nothing.
+    }
+
+    /// Whether the D-pad of a TV remote or game controller moves keyboard focus
+    /// between widgets even when no widget has focus yet, and the A button of
+    /// a game controller clicks the focused widget like the center of the
+    /// D-pad does. Enable this to make the app usable on Android TV.
+    ///
+    /// Game controllers can be read with [`gamepads`](crate::gamepads)
+    /// either way.
+    fn focus_navigation(&self) -> bool {
+        false
+    }
+
     /// Called by Android when the system is running low on memory.
     fn on_low_memory(&mut self) {
         // By default, do nothing.
@@ -38,3 +220,81 @@ pub trait Activity {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::headless::{LifecycleEvent, MotionAction};
+    use crate::internal::testing::*;
+    use egui::{Color32, ColorImage, ViewportCommand};
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn pasted_image_goes_to_activity() {
+        let paste = Rc::new(Cell::new(false));
+        let pasted = Log::default();
+
+        let mut runner = launch(
+            TestActivity::default()
+                .with_update(shared(&paste, |paste, ctx| {
+                    if paste.take() {
+                        ctx.send_viewport_cmd(ViewportCommand::RequestPaste);
+                    }
+                }))
+                .with_pasted_images(record(&pasted)),
+        );
+
+        // egui text fields can only take text, so the activity gets the image.
+        runner.set_clipboard_image(ColorImage::new([1, 1], vec![Color32::GREEN]));
+        paste.set(true);
+        runner.push_event(LifecycleEvent::RedrawNeeded);
+        runner.run_once();
+        let pasted = pasted.borrow();
+        assert_eq!(pasted.len(), 1);
+        assert_eq!(pasted[0].pixels, [Color32::GREEN]);
+    }
+
+    #[test]
+    fn clipboard_changes_are_reported() {
+        let button = Rc::<CopyButton>::default();
+        let callbacks = Log::default();
+        let mut runner = launch(
+            TestActivity::default()
+                .with_update(shared(&button, CopyButton::show))
+                .with_callbacks(record(&callbacks)),
+        );
+        let changes = || {
+            callbacks
+                .borrow()
+                .iter()
+                .filter(|&&name| name == "clipboard_changed")
+                .count()
+        };
+        assert_eq!(changes(), 0);
+
+        // Copying in the app is noticed straight away.
+        let pos = button.center();
+        runner.push_input(touch(MotionAction::Down, pos.x, pos.y));
+        runner.run_once();
+        runner.push_input(touch(MotionAction::Up, pos.x, pos.y));
+        runner.run_once();
+        assert_eq!(runner.clipboard_text(), "copied");
+        assert_eq!(changes(), 1);
+
+        // Copying in another app is only noticed once the app is focused again.
+        runner.push_event(LifecycleEvent::LostFocus);
+        runner.run_once();
+        runner.set_clipboard_text("elsewhere");
+        runner.run_once();
+        assert_eq!(changes(), 1);
+
+        runner.push_event(LifecycleEvent::GainedFocus);
+        runner.run_once();
+        assert_eq!(changes(), 2);
+
+        // Regaining focus with nothing new copied is not a change.
+        runner.push_event(LifecycleEvent::LostFocus);
+        runner.push_event(LifecycleEvent::GainedFocus);
+        runner.run_once();
+        assert_eq!(changes(), 2);
+    }
+}
@@ -3,28 +3,38 @@
 
 #![doc(hidden)]
 
+#[cfg(target_os = "android")]
 use crate::{
-    internal::{bindings::application_info::ApplicationInfo, logging, runner::Runner},
+    internal::{
+        bindings::application_info::ApplicationInfo, logging, platform::android::AndroidPlatform,
+        runner::Runner,
+    },
     Activity,
 };
 
+#[cfg(target_os = "android")]
 pub use android_activity::AndroidApp;
 
 /// Our implementation of an Android main for `NativeActivity`.
+#[cfg(target_os = "android")]
 pub fn main<T: Activity>(android_app: AndroidApp) {
     let app_info = ApplicationInfo::for_android_app(&android_app).unwrap();
 
     logging::init(app_info.package_name().unwrap());
 
-    Runner::<T>::new(android_app).run_until_closed();
+    Runner::<T, _>::new(AndroidPlatform::new(android_app)).run_until_closed();
 
     log::debug!("app exited cleanly");
 }
 
 /// Define the entrypoint for an Android app.
+///
+/// Nothing is exported when compiling for other platforms, so that the app
+/// crate can still be built and tested on the host.
 #[macro_export]
 macro_rules! export {
     ($app:ty) => {
+        #[cfg(target_os = "android")]
         #[doc(hidden)]
         #[no_mangle]
         pub fn android_main(android_app: $crate::entrypoint::AndroidApp) {
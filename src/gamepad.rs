@@ -0,0 +1,169 @@
+use egui::{Context, Id, Vec2};
+use std::{collections::BTreeSet, sync::Arc};
+
+/// A button on a game controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+
+    /// The left shoulder button.
+    L1,
+
+    /// The right shoulder button.
+    R1,
+
+    /// The left trigger, on controllers that report it as a button.
+    L2,
+
+    /// The right trigger, on controllers that report it as a button.
+    R2,
+
+    /// Pressing down on the left stick.
+    LeftThumb,
+
+    /// Pressing down on the right stick.
+    RightThumb,
+
+    Start,
+    Select,
+
+    /// The button with the controller's or the system's logo.
+    Mode,
+
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+/// The state of a game controller, as of the current pass.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GamepadState {
+    /// Identifies the controller, to tell players apart.
+    pub device_id: i32,
+
+    /// The buttons that are held down.
+    pub down: BTreeSet<GamepadButton>,
+
+    /// The buttons that were pressed since the previous pass, even if they
+    /// have been released since.
+    pub pressed: BTreeSet<GamepadButton>,
+
+    /// Position of the left stick, from -1 to 1 on each axis. Positive `y` is
+    /// down, like in egui.
+    pub left_stick: Vec2,
+
+    /// Position of the right stick, from -1 to 1 on each axis.
+    pub right_stick: Vec2,
+
+    /// How far the left trigger is pulled, from 0 to 1.
+    pub left_trigger: f32,
+
+    /// How far the right trigger is pulled, from 0 to 1.
+    pub right_trigger: f32,
+}
+
+impl GamepadState {
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.down.contains(&button)
+    }
+
+    /// Whether the button was pressed since the previous pass.
+    pub fn was_pressed(&self, button: GamepadButton) -> bool {
+        self.pressed.contains(&button)
+    }
+}
+
+/// Get the state of the game controllers that have been used, ordered by
+/// device ID.
+///
+/// Controllers only show up once a button or stick on them has been used, and
+/// stay until the app is restarted.
+pub fn gamepads(ctx: &Context) -> Vec<GamepadState> {
+    ctx.data(|data| data.get_temp::<Gamepads>(Id::NULL))
+        .map(|gamepads| gamepads.0.to_vec())
+        .unwrap_or_default()
+}
+
+/// The state of the game controllers for a pass, kept in egui's temporary
+/// data.
+#[derive(Clone, Default)]
+pub(crate) struct Gamepads(Arc<[GamepadState]>);
+
+impl Gamepads {
+    /// Make the state available to [`gamepads`] during the next pass.
+    pub(crate) fn store(gamepads: Vec<GamepadState>, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, Self(gamepads.into())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{InputEvent, JoystickEvent, KeyAction, LifecycleEvent};
+    use crate::internal::testing::*;
+    use egui::vec2;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn gamepad_state_per_pass() {
+        use crate::GamepadButton::{DpadLeft, A};
+
+        let gamepads = Rc::new(RefCell::new(Vec::<GamepadState>::new()));
+        let mut runner = launch(
+            TestActivity::default().with_update(shared(&gamepads, |gamepads, ctx| {
+                *gamepads.borrow_mut() = crate::gamepads(ctx)
+            })),
+        );
+
+        runner.push_input(gamepad_button(KeyAction::Down, A));
+        runner.push_input(InputEvent::Joystick(JoystickEvent {
+            device_id: 2,
+            left_stick: vec2(0.5, -1.0),
+            right_trigger: 0.25,
+            hat: vec2(-1.0, 0.0),
+            ..Default::default()
+        }));
+        runner.run_once();
+
+        let gamepad = gamepads.borrow()[0].clone();
+        assert_eq!(gamepad.device_id, 2);
+        assert!(gamepad.is_down(A) && gamepad.was_pressed(A));
+        assert!(gamepad.is_down(DpadLeft));
+        assert_eq!(gamepad.left_stick, vec2(0.5, -1.0));
+        assert_eq!(gamepad.right_trigger, 0.25);
+
+        runner.push_input(gamepad_button(KeyAction::Up, A));
+        runner.push_event(LifecycleEvent::RedrawNeeded);
+        runner.run_once();
+
+        let gamepad = gamepads.borrow()[0].clone();
+        assert!(!gamepad.is_down(A) && !gamepad.was_pressed(A));
+        assert!(gamepad.is_down(DpadLeft));
+    }
+
+    #[test]
+    fn dpad_moves_focus_and_a_clicks() {
+        use crate::GamepadButton::{DpadDown, A};
+
+        let button = Rc::<CopyButton>::default();
+        let mut runner = launch(
+            TestActivity::default()
+                .with_update(shared(&button, CopyButton::show))
+                .with_focus_navigation(),
+        );
+
+        runner.push_input(gamepad_button(KeyAction::Down, DpadDown));
+        runner.push_input(gamepad_button(KeyAction::Up, DpadDown));
+        runner.run_once();
+        assert!(runner.context().memory(|memory| memory.focused().is_some()));
+
+        runner.push_input(gamepad_button(KeyAction::Down, A));
+        runner.push_input(gamepad_button(KeyAction::Up, A));
+        runner.run_once();
+        assert_eq!(button.clicks.get(), 1);
+    }
+}
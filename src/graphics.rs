@@ -0,0 +1,103 @@
+use std::{error::Error, fmt};
+
+type Source = Box<dyn Error + Send + Sync>;
+
+/// Options for the framebuffer and OpenGL ES context the UI is drawn with.
+///
+/// These are treated as preferences: if the device does not support an exact
+/// match, the closest available configuration is used instead, and the one
+/// actually chosen is logged.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GraphicsOptions {
+    /// Number of samples per pixel for multisample anti-aliasing, or 0 to
+    /// disable it.
+    pub multisampling: u8,
+
+    /// Bits in the depth buffer, or 0 for no depth buffer.
+    pub depth_bits: u8,
+
+    /// Bits in the stencil buffer, or 0 for no stencil buffer.
+    pub stencil_bits: u8,
+
+    /// Use an sRGB framebuffer.
+    ///
+    /// egui itself blends in gamma space and expects a linear framebuffer, so
+    /// this is only useful if custom painting requires it.
+    pub srgb: bool,
+
+    /// Give the window an alpha channel, so that whatever is behind it shows
+    /// through transparent parts of the UI.
+    ///
+    /// The activity must also use a translucent theme in the app manifest.
+    pub transparent: bool,
+
+    /// The OpenGL ES version to request, as `(major, minor)`. If `None`, or
+    /// if the version is not available, the driver's default is used.
+    pub gles_version: Option<(u8, u8)>,
+}
+
+/// An error reported by the graphics backend.
+///
+/// Graphics errors do not abort the app. They are passed to
+/// [`Activity::on_graphics_error`](crate::Activity::on_graphics_error), and
+/// nothing is drawn until the backend recovers, which is attempted again the
+/// next time Android provides a window.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GraphicsError {
+    /// Connecting to the EGL display failed.
+    Display(Source),
+
+    /// No EGL config supports the requested attributes.
+    NoMatchingConfig,
+
+    /// Creating the OpenGL context failed.
+    CreateContext(Source),
+
+    /// Creating a surface in the window failed.
+    CreateSurface(Source),
+
+    /// Making the OpenGL context current failed.
+    MakeCurrent(Source),
+
+    /// The window has a size that cannot be drawn into.
+    InvalidWindowSize { width: i32, height: i32 },
+
+    /// Creating the egui painter failed, usually because the driver does not
+    /// support a recent enough version of OpenGL ES.
+    CreatePainter(Source),
+
+    /// Presenting a frame failed, even after recreating the surface.
+    SwapBuffers(Source),
+}
+
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Display(e) => write!(f, "failed to connect to display: {e}"),
+            Self::NoMatchingConfig => write!(f, "no matching EGL config found"),
+            Self::CreateContext(e) => write!(f, "failed to create OpenGL context: {e}"),
+            Self::CreateSurface(e) => write!(f, "failed to create window surface: {e}"),
+            Self::MakeCurrent(e) => write!(f, "failed to make OpenGL context current: {e}"),
+            Self::InvalidWindowSize { width, height } => {
+                write!(f, "invalid window size: {width}x{height}")
+            }
+            Self::CreatePainter(e) => write!(f, "failed to create painter: {e}"),
+            Self::SwapBuffers(e) => write!(f, "failed to swap buffers: {e}"),
+        }
+    }
+}
+
+impl Error for GraphicsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Display(e)
+            | Self::CreateContext(e)
+            | Self::CreateSurface(e)
+            | Self::MakeCurrent(e)
+            | Self::CreatePainter(e)
+            | Self::SwapBuffers(e) => Some(e.as_ref()),
+            Self::NoMatchingConfig | Self::InvalidWindowSize { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,395 @@
+//! A runner for driving an [`Activity`] without a device.
+//!
+//! [`HeadlessRunner`] runs the same event loop that is used on Android, but
+//! lifecycle and input events are supplied by a script instead of the operating
+//! system, and frames are recorded instead of drawn. This makes it possible to
+//! test app logic with `cargo test` on any host.
+//!
+//! As nothing is drawn, screenshots are not supported: a
+//! [`ViewportCommand::Screenshot`](egui::ViewportCommand::Screenshot) is never
+//! answered.
+
+use crate::{
+    internal::{
+        platform::{headless::HeadlessPlatform, ClipboardContent},
+        runner::Runner,
+    },
+    open_url::UrlTarget,
+    Activity, ShareContent, SystemBars, TextInputOptions, WindowInsets,
+};
+use egui::{Color32, ColorImage, Context, FullOutput, Rect, Theme};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+pub use crate::internal::platform::input::{
+    InputEvent, JoystickEvent, KeyAction, KeyEvent, MotionAction, MotionEvent, Pointer, TextAction,
+    TextActionMenu, TextInputState, TextSpan,
+};
+pub use crate::ToolType;
+
+/// A scripted equivalent of an Android lifecycle event.
+#[derive(Clone, Debug)]
+pub enum LifecycleEvent {
+    Start,
+    Resume {
+        /// State previously produced by a [`LifecycleEvent::SaveState`].
+        saved_state: Option<Vec<u8>>,
+    },
+    /// Ask the app to save its state. The result is available from
+    /// [`HeadlessRunner::saved_state`].
+    SaveState,
+    Pause,
+    Stop,
+    Destroy,
+    InitWindow {
+        width: u32,
+        height: u32,
+    },
+    TerminateWindow,
+    WindowResized {
+        width: u32,
+        height: u32,
+    },
+    RedrawNeeded,
+    /// The rectangle in which content should be placed, in physical pixels.
+    ContentRectChanged(Rect),
+    /// The parts of the window covered by system UI, in physical pixels.
+    InsetsChanged(WindowInsets),
+    GainedFocus,
+    LostFocus,
+    ConfigChanged {
+        /// Screen density in dots per inch.
+        density: Option<u32>,
+        theme: Option<Theme>,
+    },
+    LowMemory,
+}
+
+/// Runs an [`Activity`] against a scripted platform.
+///
+/// Events are queued with [`push_event`](Self::push_event) and
+/// [`push_input`](Self::push_input), and are processed the next time
+/// [`run_once`](Self::run_once) is called.
+pub struct HeadlessRunner<T: Activity> {
+    platform: HeadlessPlatform,
+    runner: Runner<T, HeadlessPlatform>,
+}
+
+impl<T: Activity> HeadlessRunner<T> {
+    /// Create a new instance of the activity, without a window.
+    pub fn new() -> Self {
+        Self::with_platform(HeadlessPlatform::default())
+    }
+
+    /// Create a new instance of the activity, using the given directory as the
+    /// app's internal data directory for [`Storage`](crate::Storage).
+    pub fn with_data_dir(data_dir: impl Into<PathBuf>) -> Self {
+        let platform = HeadlessPlatform::default();
+        platform.with_state(|state| state.data_dir = Some(data_dir.into()));
+
+        Self::with_platform(platform)
+    }
+
+    fn with_platform(platform: HeadlessPlatform) -> Self {
+        Self {
+            runner: Runner::new(platform.clone()),
+            platform,
+        }
+    }
+
+    /// Queue a lifecycle event.
+    pub fn push_event(&mut self, event: LifecycleEvent) {
+        self.platform
+            .with_state(|state| state.events.push_back(event));
+    }
+
+    /// Queue an input event.
+    pub fn push_input(&mut self, event: InputEvent) {
+        self.platform
+            .with_state(|state| state.input_events.push_back(event));
+    }
+
+    /// Process all queued events, and then update and paint the UI if needed.
+    ///
+    /// If nothing is queued, the clock skips ahead to when the activity next
+    /// wants to repaint, instead of waiting.
+    pub fn run_once(&mut self) {
+        self.runner.run_once();
+    }
+
+    /// Move the clock forward, as if that much time passed without any events.
+    /// Nothing happens until [`run_once`](Self::run_once) is next called.
+    pub fn advance_time(&mut self, duration: Duration) {
+        self.platform.with_state(|state| state.now += duration);
+    }
+
+    /// Take the output of all frames that have been painted so far.
+    pub fn take_frames(&mut self) -> Vec<FullOutput> {
+        self.platform
+            .with_state(|state| std::mem::take(&mut state.frames))
+    }
+
+    pub fn activity(&self) -> &T {
+        self.runner.app_state().inner()
+    }
+
+    pub fn activity_mut(&mut self) -> &mut T {
+        self.runner.app_state_mut().inner_mut()
+    }
+
+    pub fn context(&self) -> &Context {
+        self.runner.app_state().context()
+    }
+
+    /// Whether the activity has asked to be closed.
+    pub fn is_closed(&self) -> bool {
+        self.runner.is_closed()
+    }
+
+    /// State saved during the most recent [`LifecycleEvent::SaveState`].
+    pub fn saved_state(&self) -> Option<Vec<u8>> {
+        self.platform.with_state(|state| state.saved_state.clone())
+    }
+
+    pub fn keyboard_visible(&self) -> bool {
+        self.platform.with_state(|state| state.keyboard_visible)
+    }
+
+    /// The text input state most recently given to the soft keyboard.
+    pub fn text_input_state(&self) -> Option<TextInputState> {
+        self.platform
+            .with_state(|state| state.text_input_state.clone())
+    }
+
+    /// The soft keyboard options most recently applied.
+    pub fn text_input_options(&self) -> TextInputOptions {
+        self.platform.with_state(|state| state.text_input_options)
+    }
+
+    /// The system text selection menu currently shown, if any.
+    pub fn text_action_menu(&self) -> Option<TextActionMenu> {
+        self.platform
+            .with_state(|state| state.text_action_menu.clone())
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.platform.with_state(|state| state.fullscreen)
+    }
+
+    /// Whether the mouse is captured, which the app asks for with
+    /// [`egui::ViewportCommand::CursorGrab`].
+    pub fn is_pointer_captured(&self) -> bool {
+        self.platform.with_state(|state| state.pointer_captured)
+    }
+
+    /// The system bar settings most recently applied, and the theme their
+    /// icons were made to suit.
+    pub fn system_bars(&self) -> Option<(SystemBars, Theme)> {
+        self.platform.with_state(|state| state.system_bars)
+    }
+
+    /// The text on the clipboard, or an empty string if it holds something
+    /// else.
+    pub fn clipboard_text(&self) -> String {
+        self.platform.with_state(|state| match &state.clipboard {
+            ClipboardContent::Text(text) => text.clone(),
+            _ => String::new(),
+        })
+    }
+
+    /// Copy text to the clipboard, as if from another app.
+    pub fn set_clipboard_text(&mut self, text: impl Into<String>) {
+        self.platform
+            .with_state(|state| state.copy(ClipboardContent::Text(text.into())));
+    }
+
+    /// The image on the clipboard, if it holds one.
+    pub fn clipboard_image(&self) -> Option<Arc<ColorImage>> {
+        self.platform.with_state(|state| match &state.clipboard {
+            ClipboardContent::Image(image) => Some(image.clone()),
+            _ => None,
+        })
+    }
+
+    /// Copy an image to the clipboard, as if from another app.
+    pub fn set_clipboard_image(&mut self, image: ColorImage) {
+        self.platform
+            .with_state(|state| state.copy(ClipboardContent::Image(Arc::new(image))));
+    }
+
+    /// All URLs the activity has asked to open.
+    pub fn opened_urls(&self) -> Vec<String> {
+        self.platform.with_state(|state| {
+            state
+                .opened_urls
+                .iter()
+                .map(|(url, _)| url.clone())
+                .collect()
+        })
+    }
+
+    /// The URLs the activity has opened in a Custom Tab, with the tab's toolbar
+    /// color.
+    pub fn custom_tab_urls(&self) -> Vec<(String, Color32)> {
+        self.platform.with_state(|state| {
+            state
+                .opened_urls
+                .iter()
+                .filter_map(|(url, target)| match target {
+                    UrlTarget::Web {
+                        custom_tab: Some(color),
+                    } => Some((url.clone(), *color)),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    /// Everything the activity has opened the share sheet for.
+    pub fn shared(&self) -> Vec<ShareContent> {
+        self.platform.with_state(|state| state.shared.clone())
+    }
+
+    /// Pick an app from the open share sheet. It is closed once the activity
+    /// gains focus again.
+    pub fn choose_share_target(&mut self) {
+        self.platform
+            .with_state(|state| state.share_target_chosen = Some(true));
+    }
+}
+
+impl<T: Activity> Default for HeadlessRunner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::testing::*;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn no_frames_without_window() {
+        let mut runner = HeadlessRunner::<TestActivity>::new();
+        runner.push_event(LifecycleEvent::Start);
+        runner.run_once();
+
+        assert!(runner.take_frames().is_empty());
+    }
+
+    #[test]
+    fn paints_after_window_created() {
+        let updates = Rc::new(Cell::new(0));
+        let mut runner = launch(
+            TestActivity::default().with_update(shared(&updates, |updates, _| {
+                updates.set(updates.get() + 1)
+            })),
+        );
+
+        assert_eq!(runner.take_frames().len(), 1);
+        assert_eq!(updates.get(), 1);
+    }
+
+    #[test]
+    fn stops_painting_after_window_terminated() {
+        let mut runner = launch(TestActivity::default());
+        runner.take_frames();
+
+        runner.push_event(LifecycleEvent::TerminateWindow);
+        runner.push_event(LifecycleEvent::RedrawNeeded);
+        runner.run_once();
+
+        assert!(runner.take_frames().is_empty());
+    }
+
+    #[test]
+    fn lifecycle_callbacks() {
+        let callbacks = Log::default();
+        let mut runner = launch(TestActivity::default().with_callbacks(record(&callbacks)));
+        runner.push_event(LifecycleEvent::LostFocus);
+        runner.push_event(LifecycleEvent::Pause);
+        runner.push_event(LifecycleEvent::TerminateWindow);
+        runner.push_event(LifecycleEvent::Stop);
+        runner.push_event(LifecycleEvent::Destroy);
+        runner.run_once();
+
+        assert_eq!(
+            *callbacks.borrow(),
+            [
+                "start",
+                "resume",
+                "window_created",
+                "focused",
+                "unfocused",
+                "pause",
+                "window_destroyed",
+                "stop",
+                "destroy",
+            ]
+        );
+        assert!(runner.is_closed());
+    }
+
+    #[test]
+    fn low_memory() {
+        let callbacks = Log::default();
+        let mut runner = launch(TestActivity::default().with_callbacks(record(&callbacks)));
+        runner.push_event(LifecycleEvent::LowMemory);
+        runner.run_once();
+
+        assert_eq!(callbacks.borrow().last(), Some(&"low_memory"));
+    }
+
+    #[test]
+    fn save_and_restore_state() {
+        let mut runner = HeadlessRunner::<Counter>::new();
+        runner.activity_mut().count = 3;
+        runner.push_event(LifecycleEvent::SaveState);
+        runner.run_once();
+
+        let saved_state = runner.saved_state();
+        assert!(saved_state.is_some());
+
+        let mut runner = HeadlessRunner::<Counter>::new();
+        runner.push_event(LifecycleEvent::Resume { saved_state });
+        runner.run_once();
+
+        assert_eq!(runner.activity().count, 3);
+        assert_eq!(runner.activity().updates, 0);
+    }
+
+    #[test]
+    fn restores_state_only_on_first_resume() {
+        let mut runner = HeadlessRunner::<Counter>::new();
+        runner.activity_mut().count = 3;
+        runner.push_event(LifecycleEvent::SaveState);
+        runner.run_once();
+        let saved_state = runner.saved_state();
+
+        let mut runner = HeadlessRunner::<Counter>::new();
+        runner.push_event(LifecycleEvent::Resume {
+            saved_state: saved_state.clone(),
+        });
+        runner.run_once();
+        assert_eq!(runner.activity().count, 3);
+
+        runner.activity_mut().count = 4;
+        runner.push_event(LifecycleEvent::Pause);
+        runner.push_event(LifecycleEvent::Resume { saved_state });
+        runner.run_once();
+
+        assert_eq!(runner.activity().count, 4);
+    }
+
+    #[test]
+    fn ignores_corrupt_saved_state() {
+        let mut runner = HeadlessRunner::<Counter>::new();
+        runner.push_event(LifecycleEvent::Resume {
+            saved_state: Some(vec![0xff; 4]),
+        });
+        runner.run_once();
+
+        assert_eq!(runner.activity().count, 0);
+    }
+}
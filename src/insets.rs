@@ -0,0 +1,184 @@
+use egui::{epaint::MarginF32, Context, Id, Rect};
+
+/// The parts of the window that are covered by system UI, in points.
+///
+/// The window always covers the whole screen, so apps are responsible for
+/// keeping content clear of the status bar, navigation bar, display cutouts and
+/// the soft keyboard. Most apps only need [`safe_area`], which combines these.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowInsets {
+    /// The status bar, navigation bar and caption bar.
+    pub system_bars: MarginF32,
+
+    /// Display cutouts, such as a camera notch.
+    pub display_cutout: MarginF32,
+
+    /// Areas where system gestures, such as swiping in from the edge to go
+    /// back, take precedence over touches sent to the app.
+    ///
+    /// Content may be drawn under these, but controls that need dragging
+    /// should avoid them.
+    pub system_gestures: MarginF32,
+
+    /// The soft keyboard, if it is visible.
+    pub ime: MarginF32,
+}
+
+impl WindowInsets {
+    /// The insets that content should stay clear of, which is the largest of
+    /// the system bar, display cutout and soft keyboard insets on each side.
+    ///
+    /// With [`SoftInputAdjust::Pan`], the runner leaves the keyboard out of
+    /// the [`safe_area`] it gives to egui.
+    pub fn safe_area_insets(&self) -> MarginF32 {
+        let max = |side: fn(&MarginF32) -> f32| {
+            side(&self.system_bars)
+                .max(side(&self.display_cutout))
+                .max(side(&self.ime))
+        };
+
+        MarginF32 {
+            left: max(|m| m.left),
+            right: max(|m| m.right),
+            top: max(|m| m.top),
+            bottom: max(|m| m.bottom),
+        }
+    }
+
+    /// Convert insets in physical pixels to points.
+    pub(crate) fn to_points(self, pixels_per_point: f32) -> Self {
+        Self {
+            system_bars: self.system_bars / pixels_per_point,
+            display_cutout: self.display_cutout / pixels_per_point,
+            system_gestures: self.system_gestures / pixels_per_point,
+            ime: self.ime / pixels_per_point,
+        }
+    }
+
+    /// Make the insets available to [`window_insets`].
+    pub(crate) fn store(self, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, self));
+    }
+}
+
+/// Get the parts of the window that are currently covered by system UI.
+pub fn window_insets(ctx: &Context) -> WindowInsets {
+    ctx.data(|data| data.get_temp(Id::NULL)).unwrap_or_default()
+}
+
+/// Get the height of the soft keyboard, in points, or 0 if it is hidden.
+pub fn keyboard_height(ctx: &Context) -> f32 {
+    window_insets(ctx).ime.bottom
+}
+
+/// Get the area of the window in which content is not covered by system UI.
+///
+/// This is the same as [`Context::content_rect`], which the runner keeps up to
+/// date with the safe area insets of [`window_insets`]. The soft keyboard is
+/// only excluded with [`SoftInputAdjust::Resize`].
+pub fn safe_area(ctx: &Context) -> Rect {
+    ctx.content_rect()
+}
+
+/// How the UI makes room for the soft keyboard, like Android's
+/// `windowSoftInputMode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SoftInputAdjust {
+    /// Keep the layout as it is, and pan the whole UI up when needed to keep
+    /// the text cursor of the focused text field above the keyboard.
+    #[default]
+    Pan,
+
+    /// Remove the area covered by the keyboard from the [`safe_area`], so that
+    /// panels are laid out above it. The app is responsible for scrolling the
+    /// focused text field into view.
+    Resize,
+}
+
+impl SoftInputAdjust {
+    pub(crate) fn store(self, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, self));
+    }
+}
+
+pub(crate) fn soft_input_adjust(ctx: &Context) -> SoftInputAdjust {
+    ctx.data(|data| data.get_temp(Id::NULL)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::LifecycleEvent;
+    use crate::internal::testing::*;
+    use egui::{epaint::MarginF32, pos2, Rect};
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn safe_area_excludes_system_ui() {
+        let safe_area = Rc::new(Cell::new(Rect::NOTHING));
+        let mut runner = launch(
+            TestActivity::default()
+                .with_update(shared(&safe_area, |safe_area, ctx| {
+                    safe_area.set(crate::safe_area(ctx))
+                }))
+                .with_soft_input_adjust(SoftInputAdjust::Resize),
+        );
+        runner.push_event(LifecycleEvent::ConfigChanged {
+            density: Some(320),
+            theme: None,
+        });
+        runner.push_event(LifecycleEvent::InsetsChanged(WindowInsets {
+            system_bars: MarginF32 {
+                top: 100.0,
+                bottom: 60.0,
+                ..MarginF32::ZERO
+            },
+            display_cutout: MarginF32 {
+                top: 120.0,
+                ..MarginF32::ZERO
+            },
+            ime: MarginF32 {
+                bottom: 400.0,
+                ..MarginF32::ZERO
+            },
+            ..WindowInsets::default()
+        }));
+        runner.run_once();
+
+        assert_eq!(
+            safe_area.get(),
+            Rect::from_min_max(pos2(0.0, 60.0), pos2(200.0, 200.0))
+        );
+    }
+
+    #[test]
+    fn pans_focused_text_field_above_keyboard() {
+        let field = Rc::new(TextField::new(""));
+        let safe_area = Rc::new(Cell::new(Rect::NOTHING));
+        let mut runner = launch(TestActivity::default().with_update({
+            let (field, safe_area) = (field.clone(), safe_area.clone());
+            move |ctx| {
+                safe_area.set(crate::safe_area(ctx));
+                field.show(ctx);
+            }
+        }));
+        runner.run_once();
+        assert!(runner.keyboard_visible());
+
+        runner.push_event(LifecycleEvent::InsetsChanged(WindowInsets {
+            ime: MarginF32 {
+                bottom: 400.0,
+                ..MarginF32::ZERO
+            },
+            ..WindowInsets::default()
+        }));
+        runner.run_once();
+        runner.run_once();
+
+        let frames = runner.take_frames();
+        let ime = frames.last().unwrap().platform_output.ime.unwrap();
+        assert!(ime.cursor_rect.bottom() <= 400.0);
+        assert!(ime.cursor_rect.bottom() > 350.0);
+        assert_eq!(safe_area.get().bottom(), 800.0);
+    }
+}
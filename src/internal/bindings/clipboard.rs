@@ -0,0 +1,324 @@
+//! Reading and writing the clipboard beyond plain text, through
+//! [`ClipboardManager`](https://developer.android.com/reference/android/content/ClipboardManager).
+
+use super::file_provider::{cache_file, content_uri};
+use crate::internal::platform::ClipboardContent;
+use android_activity::AndroidApp;
+use egui::{Color32, ColorImage};
+use jni::{
+    objects::{JIntArray, JObject, JString, JValue},
+    JNIEnv, JavaVM,
+};
+use std::sync::Arc;
+
+type Error = Box<dyn std::error::Error>;
+
+/// Directory in the app's cache directory that copied images are written to.
+/// The app's `FileProvider` must be configured to share it.
+const IMAGE_DIR: &str = "egui_android_clipboard";
+
+/// Get the first item on the clipboard. Images are decoded, and anything else
+/// is converted to plain text, including HTML.
+pub(crate) fn get_clipboard(app: &AndroidApp) -> Result<ClipboardContent, Error> {
+    with_env(app, |env, activity| {
+        let manager = clipboard_manager(env, activity)?;
+        let clip = env
+            .call_method(
+                &manager,
+                "getPrimaryClip",
+                "()Landroid/content/ClipData;",
+                &[],
+            )?
+            .l()?;
+        if clip.is_null() || env.call_method(&clip, "getItemCount", "()I", &[])?.i()? == 0 {
+            return Ok(ClipboardContent::Empty);
+        }
+
+        let item = env
+            .call_method(
+                &clip,
+                "getItemAt",
+                "(I)Landroid/content/ClipData$Item;",
+                &[0.into()],
+            )?
+            .l()?;
+
+        let uri = env
+            .call_method(&item, "getUri", "()Landroid/net/Uri;", &[])?
+            .l()?;
+        if !uri.is_null() {
+            if let Some(image) = read_image(env, activity, &uri)? {
+                return Ok(ClipboardContent::Image(Arc::new(image)));
+            }
+        }
+
+        // Styled text is made from HTML if there is no plain text, and is
+        // read from the content provider for URIs that are not images.
+        let text = env
+            .call_method(
+                &item,
+                "coerceToStyledText",
+                "(Landroid/content/Context;)Ljava/lang/CharSequence;",
+                &[JValue::Object(activity)],
+            )?
+            .l()?;
+        let text = JString::from(
+            env.call_method(&text, "toString", "()Ljava/lang/String;", &[])?
+                .l()?,
+        );
+        let text = String::from(env.get_string(&text)?);
+
+        Ok(if text.is_empty() {
+            ClipboardContent::Empty
+        } else {
+            ClipboardContent::Text(text)
+        })
+    })
+}
+
+/// Put an image on the clipboard, as a PNG file shared through the app's
+/// `FileProvider`.
+pub(crate) fn set_clipboard_image(app: &AndroidApp, image: &ColorImage) -> Result<(), Error> {
+    with_env(app, |env, activity| {
+        let [width, height] = image.size;
+        let pixels: Vec<i32> = image
+            .pixels
+            .iter()
+            .map(|color| {
+                let [r, g, b, a] = color.to_srgba_unmultiplied();
+                i32::from_be_bytes([a, r, g, b])
+            })
+            .collect();
+        let colors = env.new_int_array(pixels.len() as i32)?;
+        env.set_int_array_region(&colors, 0, &pixels)?;
+
+        let config = env
+            .get_static_field(
+                "android/graphics/Bitmap$Config",
+                "ARGB_8888",
+                "Landroid/graphics/Bitmap$Config;",
+            )?
+            .l()?;
+        let bitmap = env
+            .call_static_method(
+                "android/graphics/Bitmap",
+                "createBitmap",
+                "([IIILandroid/graphics/Bitmap$Config;)Landroid/graphics/Bitmap;",
+                &[
+                    JValue::Object(&colors),
+                    (width as i32).into(),
+                    (height as i32).into(),
+                    JValue::Object(&config),
+                ],
+            )?
+            .l()?;
+
+        // Overwrite the previous image, as only the latest clip can be pasted.
+        let file = cache_file(env, activity, IMAGE_DIR, "image.png")?;
+        let stream = env.new_object(
+            "java/io/FileOutputStream",
+            "(Ljava/io/File;)V",
+            &[(&file).into()],
+        )?;
+        let format = env
+            .get_static_field(
+                "android/graphics/Bitmap$CompressFormat",
+                "PNG",
+                "Landroid/graphics/Bitmap$CompressFormat;",
+            )?
+            .l()?;
+        let written = env
+            .call_method(
+                &bitmap,
+                "compress",
+                "(Landroid/graphics/Bitmap$CompressFormat;ILjava/io/OutputStream;)Z",
+                &[(&format).into(), 100.into(), (&stream).into()],
+            )?
+            .z()?;
+        env.call_method(&stream, "close", "()V", &[])?;
+        if !written {
+            return Err("failed to encode image".into());
+        }
+
+        let uri = content_uri(env, activity, &file)?;
+
+        let resolver = content_resolver(env, activity)?;
+        let label = env.new_string("image")?;
+        let clip = env
+            .call_static_method(
+                "android/content/ClipData",
+                "newUri",
+                "(Landroid/content/ContentResolver;Ljava/lang/CharSequence;Landroid/net/Uri;)Landroid/content/ClipData;",
+                &[(&resolver).into(), (&label).into(), (&uri).into()],
+            )?
+            .l()?;
+
+        let manager = clipboard_manager(env, activity)?;
+        env.call_method(
+            &manager,
+            "setPrimaryClip",
+            "(Landroid/content/ClipData;)V",
+            &[(&clip).into()],
+        )?;
+
+        Ok(())
+    })
+}
+
+/// Get the time at which the current clip was copied, which needs Android 8.
+pub(crate) fn clipboard_timestamp(app: &AndroidApp) -> Result<Option<i64>, Error> {
+    if app.config().sdk_version() < 26 {
+        return Ok(None);
+    }
+
+    with_env(app, |env, activity| {
+        let manager = clipboard_manager(env, activity)?;
+        let description = env
+            .call_method(
+                &manager,
+                "getPrimaryClipDescription",
+                "()Landroid/content/ClipDescription;",
+                &[],
+            )?
+            .l()?;
+        if description.is_null() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            env.call_method(&description, "getTimestamp", "()J", &[])?
+                .j()?,
+        ))
+    })
+}
+
+fn with_env<R>(
+    app: &AndroidApp,
+    f: impl FnOnce(&mut JNIEnv<'_>, &JObject<'_>) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    let result = f(&mut env, &activity);
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result
+}
+
+fn clipboard_manager<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject<'_>,
+) -> Result<JObject<'local>, Error> {
+    let name = env.new_string("clipboard")?;
+    let manager = env
+        .call_method(
+            activity,
+            "getSystemService",
+            "(Ljava/lang/String;)Ljava/lang/Object;",
+            &[(&name).into()],
+        )?
+        .l()?;
+
+    Ok(manager)
+}
+
+fn content_resolver<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject<'_>,
+) -> Result<JObject<'local>, Error> {
+    let resolver = env
+        .call_method(
+            activity,
+            "getContentResolver",
+            "()Landroid/content/ContentResolver;",
+            &[],
+        )?
+        .l()?;
+
+    Ok(resolver)
+}
+
+/// Decode the image at the given content URI, or return `None` if it is not
+/// an image.
+fn read_image(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    uri: &JObject<'_>,
+) -> Result<Option<ColorImage>, Error> {
+    let resolver = content_resolver(env, activity)?;
+    let mime_type = env
+        .call_method(
+            &resolver,
+            "getType",
+            "(Landroid/net/Uri;)Ljava/lang/String;",
+            &[uri.into()],
+        )?
+        .l()?;
+    if mime_type.is_null() {
+        return Ok(None);
+    }
+    let mime_type = JString::from(mime_type);
+    if !env.get_string(&mime_type)?.to_str()?.starts_with("image/") {
+        return Ok(None);
+    }
+
+    let stream = env
+        .call_method(
+            &resolver,
+            "openInputStream",
+            "(Landroid/net/Uri;)Ljava/io/InputStream;",
+            &[uri.into()],
+        )?
+        .l()?;
+    let bitmap = env
+        .call_static_method(
+            "android/graphics/BitmapFactory",
+            "decodeStream",
+            "(Ljava/io/InputStream;)Landroid/graphics/Bitmap;",
+            &[(&stream).into()],
+        )?
+        .l()?;
+    env.call_method(&stream, "close", "()V", &[])?;
+    if bitmap.is_null() {
+        return Ok(None);
+    }
+
+    let width = env.call_method(&bitmap, "getWidth", "()I", &[])?.i()?;
+    let height = env.call_method(&bitmap, "getHeight", "()I", &[])?.i()?;
+    let colors: JIntArray<'_> = env.new_int_array(width * height)?;
+    env.call_method(
+        &bitmap,
+        "getPixels",
+        "([IIIIIII)V",
+        &[
+            JValue::Object(&colors),
+            0.into(),
+            width.into(),
+            0.into(),
+            0.into(),
+            width.into(),
+            height.into(),
+        ],
+    )?;
+    let mut pixels = vec![0; (width * height) as usize];
+    env.get_int_array_region(&colors, 0, &mut pixels)?;
+
+    // Pixels are packed ARGB without premultiplied alpha.
+    let pixels = pixels
+        .into_iter()
+        .map(|pixel| {
+            let [a, r, g, b] = pixel.to_be_bytes();
+            Color32::from_rgba_unmultiplied(r, g, b, a)
+        })
+        .collect();
+
+    Ok(Some(ColorImage::new(
+        [width as usize, height as usize],
+        pixels,
+    )))
+}
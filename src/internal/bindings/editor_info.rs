@@ -0,0 +1,98 @@
+use crate::{ImeAction, InputType, TextInputOptions};
+use android_activity::AndroidApp;
+use jni::{objects::JObject, JavaVM};
+
+type Error = Box<dyn std::error::Error>;
+
+// Constants from `InputType` and `EditorInfo`.
+const TYPE_CLASS_TEXT: i32 = 0x1;
+const TYPE_CLASS_NUMBER: i32 = 0x2;
+const TYPE_CLASS_PHONE: i32 = 0x3;
+const TYPE_TEXT_VARIATION_URI: i32 = 0x10;
+const TYPE_TEXT_VARIATION_EMAIL_ADDRESS: i32 = 0x20;
+const TYPE_TEXT_VARIATION_PASSWORD: i32 = 0x80;
+const TYPE_TEXT_FLAG_AUTO_CORRECT: i32 = 0x8000;
+const TYPE_TEXT_FLAG_MULTI_LINE: i32 = 0x20000;
+const TYPE_TEXT_FLAG_NO_SUGGESTIONS: i32 = 0x80000;
+const TYPE_NUMBER_FLAG_DECIMAL: i32 = 0x2000;
+const TYPE_NUMBER_VARIATION_PASSWORD: i32 = 0x10;
+const IME_ACTION_UNSPECIFIED: i32 = 0;
+const IME_ACTION_NONE: i32 = 1;
+const IME_ACTION_GO: i32 = 2;
+const IME_ACTION_SEARCH: i32 = 3;
+const IME_ACTION_SEND: i32 = 4;
+const IME_ACTION_NEXT: i32 = 5;
+const IME_ACTION_DONE: i32 = 6;
+const IME_ACTION_PREVIOUS: i32 = 7;
+const IME_FLAG_NO_EXTRACT_UI: i32 = 0x10000000;
+const IME_FLAG_NO_ENTER_ACTION: i32 = 0x40000000;
+
+/// Set the
+/// [`EditorInfo`](https://developer.android.com/reference/android/view/inputmethod/EditorInfo)
+/// the soft keyboard is opened with.
+///
+/// This needs a `GameActivity`, which has a `setImeEditorInfoFields` method for
+/// this. A `NativeActivity` always opens the same keyboard.
+pub(crate) fn set_editor_info(app: &AndroidApp, options: TextInputOptions) -> Result<(), Error> {
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    let action = ime_action(options.ime_action);
+    let mut ime_options = action | IME_FLAG_NO_EXTRACT_UI;
+    if action == IME_ACTION_UNSPECIFIED && options.input_type == InputType::Multiline {
+        ime_options |= IME_FLAG_NO_ENTER_ACTION;
+    }
+
+    let result = env.call_method(
+        &activity,
+        "setImeEditorInfoFields",
+        "(III)V",
+        &[
+            input_type(options).into(),
+            action.into(),
+            ime_options.into(),
+        ],
+    );
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result?;
+    Ok(())
+}
+
+fn input_type(options: TextInputOptions) -> i32 {
+    let suggestions = if options.autocorrect {
+        TYPE_TEXT_FLAG_AUTO_CORRECT
+    } else {
+        TYPE_TEXT_FLAG_NO_SUGGESTIONS
+    };
+
+    match options.input_type {
+        InputType::Text => TYPE_CLASS_TEXT | suggestions,
+        InputType::Multiline => TYPE_CLASS_TEXT | TYPE_TEXT_FLAG_MULTI_LINE | suggestions,
+        InputType::Number => TYPE_CLASS_NUMBER,
+        InputType::Decimal => TYPE_CLASS_NUMBER | TYPE_NUMBER_FLAG_DECIMAL,
+        InputType::Phone => TYPE_CLASS_PHONE,
+        InputType::Email => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_EMAIL_ADDRESS | suggestions,
+        InputType::Uri => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_URI | suggestions,
+        InputType::Password => TYPE_CLASS_TEXT | TYPE_TEXT_VARIATION_PASSWORD,
+        InputType::NumberPassword => TYPE_CLASS_NUMBER | TYPE_NUMBER_VARIATION_PASSWORD,
+    }
+}
+
+fn ime_action(action: ImeAction) -> i32 {
+    match action {
+        ImeAction::Unspecified => IME_ACTION_UNSPECIFIED,
+        ImeAction::None => IME_ACTION_NONE,
+        ImeAction::Go => IME_ACTION_GO,
+        ImeAction::Search => IME_ACTION_SEARCH,
+        ImeAction::Send => IME_ACTION_SEND,
+        ImeAction::Next => IME_ACTION_NEXT,
+        ImeAction::Previous => IME_ACTION_PREVIOUS,
+        ImeAction::Done => IME_ACTION_DONE,
+    }
+}
@@ -0,0 +1,89 @@
+//! Sharing files in the app's cache directory with other apps, through the
+//! app's
+//! [`FileProvider`](https://developer.android.com/reference/androidx/core/content/FileProvider).
+//!
+//! The provider has to be declared in the app's manifest with the authority
+//! `<package name>.fileprovider`, and be configured to share the directories
+//! used here.
+
+use jni::{
+    objects::{JClass, JObject, JString},
+    JNIEnv,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Get a file with the given name in a directory of the app's cache, creating
+/// the directory if needed.
+pub(crate) fn cache_file<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject<'_>,
+    dir: &str,
+    name: &str,
+) -> Result<JObject<'local>, Error> {
+    let cache_dir = env
+        .call_method(activity, "getCacheDir", "()Ljava/io/File;", &[])?
+        .l()?;
+    let dir_name = env.new_string(dir)?;
+    let dir = env.new_object(
+        "java/io/File",
+        "(Ljava/io/File;Ljava/lang/String;)V",
+        &[(&cache_dir).into(), (&dir_name).into()],
+    )?;
+    env.call_method(&dir, "mkdirs", "()Z", &[])?;
+
+    let name = env.new_string(name)?;
+    let file = env.new_object(
+        "java/io/File",
+        "(Ljava/io/File;Ljava/lang/String;)V",
+        &[(&dir).into(), (&name).into()],
+    )?;
+
+    Ok(file)
+}
+
+/// Get a `content://` URI through which other apps can read the given file.
+pub(crate) fn content_uri<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject<'_>,
+    file: &JObject<'_>,
+) -> Result<JObject<'local>, Error> {
+    // The provider is part of the app, so it can't be found through the class
+    // loader `FindClass` uses on native threads.
+    let class_loader = env
+        .call_method(activity, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])?
+        .l()?;
+    let class_name = env.new_string("androidx.core.content.FileProvider")?;
+    let file_provider = JClass::from(
+        env.call_method(
+            &class_loader,
+            "loadClass",
+            "(Ljava/lang/String;)Ljava/lang/Class;",
+            &[(&class_name).into()],
+        )?
+        .l()?,
+    );
+
+    let authority = format!("{}.fileprovider", package_name(env, activity)?);
+    let authority = env.new_string(authority)?;
+    let uri = env
+        .call_static_method(
+            &file_provider,
+            "getUriForFile",
+            "(Landroid/content/Context;Ljava/lang/String;Ljava/io/File;)Landroid/net/Uri;",
+            &[activity.into(), (&authority).into(), file.into()],
+        )?
+        .l()?;
+
+    Ok(uri)
+}
+
+pub(crate) fn package_name(env: &mut JNIEnv<'_>, activity: &JObject<'_>) -> Result<String, Error> {
+    let package_name = JString::from(
+        env.call_method(activity, "getPackageName", "()Ljava/lang/String;", &[])?
+            .l()?,
+    );
+    let package_name = env.get_string(&package_name)?.into();
+
+    Ok(package_name)
+}
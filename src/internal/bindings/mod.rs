@@ -2,3 +2,13 @@
 //! through the NDK.
 
 pub(crate) mod application_info;
+pub(crate) mod clipboard;
+#[cfg(feature = "game-activity")]
+pub(crate) mod editor_info;
+pub(crate) mod file_provider;
+pub(crate) mod open_url;
+pub(crate) mod pointer_capture;
+pub(crate) mod share;
+pub(crate) mod system_bars;
+pub(crate) mod text_action_mode;
+pub(crate) mod window_insets;
@@ -0,0 +1,255 @@
+//! Opening URLs by starting an activity with an intent, instead of going
+//! through a browser-specific API.
+
+use crate::{
+    open_url::{url_target, UrlTarget},
+    OpenUrlError,
+};
+use android_activity::AndroidApp;
+use egui::Color32;
+use jni::{
+    objects::{JObject, JString},
+    JNIEnv, JavaVM,
+};
+
+// Constants from `Intent` and the Custom Tabs protocol, which browsers
+// implement without the app needing the `androidx.browser` library.
+const ACTION_VIEW: &str = "android.intent.action.VIEW";
+const ACTION_SENDTO: &str = "android.intent.action.SENDTO";
+const ACTION_DIAL: &str = "android.intent.action.DIAL";
+const CATEGORY_BROWSABLE: &str = "android.intent.category.BROWSABLE";
+const URI_INTENT_SCHEME: i32 = 0x1;
+const EXTRA_BROWSER_FALLBACK_URL: &str = "browser_fallback_url";
+const EXTRA_SESSION: &str = "android.support.customtabs.extra.SESSION";
+const EXTRA_TOOLBAR_COLOR: &str = "android.support.customtabs.extra.TOOLBAR_COLOR";
+const EXTRA_TITLE_VISIBILITY: &str = "android.support.customtabs.extra.TITLE_VISIBILITY";
+const SHOW_PAGE_TITLE: i32 = 1;
+
+/// Open a URL in the app that handles it.
+///
+/// Web links are opened in a Custom Tab with the given toolbar color if one
+/// is given, which falls back to the browser if it doesn't support them.
+/// `mailto:` links start writing an email, `tel:` links open the dialer, and
+/// `intent:` links start the intent they describe, or open their fallback URL
+/// if no app can handle it.
+pub(crate) fn open_url(
+    app: &AndroidApp,
+    url: &str,
+    custom_tab: Option<Color32>,
+) -> Result<(), OpenUrlError> {
+    let target = url_target(url, custom_tab)?;
+
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _) }
+        .map_err(|e| OpenUrlError::Launch(e.into()))?;
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm
+        .attach_current_thread()
+        .map_err(|e| OpenUrlError::Launch(e.into()))?;
+
+    let result = match target {
+        UrlTarget::Intent => start_intent_url(&mut env, &activity, url),
+        UrlTarget::Email => start(&mut env, &activity, ACTION_SENDTO, url, None),
+        UrlTarget::Dialer => start(&mut env, &activity, ACTION_DIAL, url, None),
+        UrlTarget::Web { custom_tab } => start(&mut env, &activity, ACTION_VIEW, url, custom_tab),
+        UrlTarget::Other => start(&mut env, &activity, ACTION_VIEW, url, None),
+    };
+
+    result.map_err(|e| convert_error(&mut env, e))
+}
+
+/// Start an activity for a URL with the given intent action.
+fn start(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    action: &str,
+    url: &str,
+    custom_tab: Option<Color32>,
+) -> jni::errors::Result<()> {
+    let action = env.new_string(action)?;
+    let uri = parse_uri(env, url)?;
+    let intent = env.new_object(
+        "android/content/Intent",
+        "(Ljava/lang/String;Landroid/net/Uri;)V",
+        &[(&action).into(), (&uri).into()],
+    )?;
+
+    if let Some(toolbar_color) = custom_tab {
+        // Asking for a session, even without one, is what makes a browser
+        // open a Custom Tab.
+        let extras = env.new_object("android/os/Bundle", "()V", &[])?;
+        let name = env.new_string(EXTRA_SESSION)?;
+        env.call_method(
+            &extras,
+            "putBinder",
+            "(Ljava/lang/String;Landroid/os/IBinder;)V",
+            &[(&name).into(), (&JObject::null()).into()],
+        )?;
+        env.call_method(
+            &intent,
+            "putExtras",
+            "(Landroid/os/Bundle;)Landroid/content/Intent;",
+            &[(&extras).into()],
+        )?;
+
+        let [r, g, b, _] = toolbar_color.to_srgba_unmultiplied();
+        put_int_extra(
+            env,
+            &intent,
+            EXTRA_TOOLBAR_COLOR,
+            i32::from_be_bytes([0xff, r, g, b]),
+        )?;
+        put_int_extra(env, &intent, EXTRA_TITLE_VISIBILITY, SHOW_PAGE_TITLE)?;
+    }
+
+    start_activity(env, activity, &intent)
+}
+
+/// Start the intent described by an `intent:` URL, or open its fallback URL if
+/// no app can handle it.
+fn start_intent_url(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    url: &str,
+) -> jni::errors::Result<()> {
+    let url = env.new_string(url)?;
+    let intent = env
+        .call_static_method(
+            "android/content/Intent",
+            "parseUri",
+            "(Ljava/lang/String;I)Landroid/content/Intent;",
+            &[(&url).into(), URI_INTENT_SCHEME.into()],
+        )?
+        .l()?;
+
+    // Like a browser would, only allow the URL to start activities that can
+    // be opened from a link, and not pick a specific one.
+    let category = env.new_string(CATEGORY_BROWSABLE)?;
+    env.call_method(
+        &intent,
+        "addCategory",
+        "(Ljava/lang/String;)Landroid/content/Intent;",
+        &[(&category).into()],
+    )?;
+    env.call_method(
+        &intent,
+        "setComponent",
+        "(Landroid/content/ComponentName;)Landroid/content/Intent;",
+        &[(&JObject::null()).into()],
+    )?;
+    env.call_method(
+        &intent,
+        "setSelector",
+        "(Landroid/content/Intent;)V",
+        &[(&JObject::null()).into()],
+    )?;
+
+    let name = env.new_string(EXTRA_BROWSER_FALLBACK_URL)?;
+    let fallback_url = env
+        .call_method(
+            &intent,
+            "getStringExtra",
+            "(Ljava/lang/String;)Ljava/lang/String;",
+            &[(&name).into()],
+        )?
+        .l()?;
+
+    let result = start_activity(env, activity, &intent);
+    if fallback_url.is_null() || !is_exception(env, "android/content/ActivityNotFoundException")? {
+        return result;
+    }
+
+    env.exception_clear()?;
+    let fallback_url = String::from(env.get_string(&JString::from(fallback_url))?);
+    start(env, activity, ACTION_VIEW, &fallback_url, None)
+}
+
+fn parse_uri<'local>(env: &mut JNIEnv<'local>, url: &str) -> jni::errors::Result<JObject<'local>> {
+    let url = env.new_string(url)?;
+    env.call_static_method(
+        "android/net/Uri",
+        "parse",
+        "(Ljava/lang/String;)Landroid/net/Uri;",
+        &[(&url).into()],
+    )?
+    .l()
+}
+
+fn put_int_extra(
+    env: &mut JNIEnv<'_>,
+    intent: &JObject<'_>,
+    name: &str,
+    value: i32,
+) -> jni::errors::Result<()> {
+    let name = env.new_string(name)?;
+    env.call_method(
+        intent,
+        "putExtra",
+        "(Ljava/lang/String;I)Landroid/content/Intent;",
+        &[(&name).into(), value.into()],
+    )?;
+
+    Ok(())
+}
+
+fn start_activity(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    intent: &JObject<'_>,
+) -> jni::errors::Result<()> {
+    env.call_method(
+        activity,
+        "startActivity",
+        "(Landroid/content/Intent;)V",
+        &[intent.into()],
+    )?;
+
+    Ok(())
+}
+
+/// Whether the pending Java exception, if any, is of the given class.
+fn is_exception(env: &mut JNIEnv<'_>, class: &str) -> jni::errors::Result<bool> {
+    if !env.exception_check()? {
+        return Ok(false);
+    }
+
+    // Most JNI functions can't be called while an exception is pending, so
+    // it is rethrown afterwards.
+    let exception = env.exception_occurred()?;
+    env.exception_clear()?;
+    let matches = env.is_instance_of(&exception, class)?;
+    env.throw(exception)?;
+
+    Ok(matches)
+}
+
+/// Turn a failed JNI call into an error for the app, clearing any Java
+/// exception it threw.
+fn convert_error(env: &mut JNIEnv<'_>, error: jni::errors::Error) -> OpenUrlError {
+    let Ok(true) = env.exception_check() else {
+        return OpenUrlError::Launch(error.into());
+    };
+    let Ok(exception) = env.exception_occurred() else {
+        return OpenUrlError::Launch(error.into());
+    };
+    let _ = env.exception_clear();
+
+    let message = env
+        .call_method(&exception, "toString", "()Ljava/lang/String;", &[])
+        .and_then(|message| message.l())
+        .and_then(|message| Ok(String::from(env.get_string(&JString::from(message))?)))
+        .unwrap_or_else(|_| error.to_string());
+
+    if env
+        .is_instance_of(&exception, "android/content/ActivityNotFoundException")
+        .unwrap_or(false)
+    {
+        OpenUrlError::NoHandler
+    } else if env
+        .is_instance_of(&exception, "java/net/URISyntaxException")
+        .unwrap_or(false)
+    {
+        OpenUrlError::InvalidUrl(message.into())
+    } else {
+        OpenUrlError::Launch(message.into())
+    }
+}
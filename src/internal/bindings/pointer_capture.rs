@@ -0,0 +1,43 @@
+use android_activity::AndroidApp;
+use jni::{objects::JObject, JNIEnv, JavaVM};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Capture the mouse, so that its events report relative movement and the
+/// cursor is hidden, or release it. Needs Android 8.0.
+pub(crate) fn set_pointer_capture(app: &AndroidApp, captured: bool) -> Result<(), Error> {
+    if app.config().sdk_version() < 26 {
+        return Err("pointer capture needs Android 8.0".into());
+    }
+
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    let result = apply(&mut env, &activity, captured);
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result
+}
+
+fn apply(env: &mut JNIEnv<'_>, activity: &JObject<'_>, captured: bool) -> Result<(), Error> {
+    let window = env
+        .call_method(activity, "getWindow", "()Landroid/view/Window;", &[])?
+        .l()?;
+    let decor_view = env
+        .call_method(&window, "getDecorView", "()Landroid/view/View;", &[])?
+        .l()?;
+
+    let method = if captured {
+        "requestPointerCapture"
+    } else {
+        "releasePointerCapture"
+    };
+    env.call_method(&decor_view, method, "()V", &[])?;
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+//! Opening the share sheet with an `Intent.ACTION_SEND` intent.
+//!
+//! Whether an app was picked is reported to the activity's `BroadcastReceiver`,
+//! which calls the native `onShareTargetChosen` method implemented here. See
+//! `examples/java/MainActivity.java` for the Java side.
+
+use super::file_provider::{cache_file, content_uri, package_name};
+use crate::ShareContent;
+use android_activity::AndroidApp;
+use jni::{
+    objects::{JObject, JString},
+    JNIEnv, JavaVM, NativeMethod,
+};
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+type Error = Box<dyn std::error::Error>;
+
+/// Directory in the app's cache directory that shared files are written to.
+/// The app's `FileProvider` must be configured to share it.
+const FILE_DIR: &str = "egui_android_share";
+
+// Constants from `Intent` and `PendingIntent`.
+const FLAG_GRANT_READ_URI_PERMISSION: i32 = 0x1;
+const FLAG_MUTABLE: i32 = 0x02000000;
+const FLAG_UPDATE_CURRENT: i32 = 0x08000000;
+
+/// Whether the native method has been registered, which fails if the activity
+/// doesn't declare it. Set on the first share.
+static REPORTS_CHOSEN: OnceLock<bool> = OnceLock::new();
+
+/// Whether an app was picked from the share sheet most recently opened.
+static CHOSEN: AtomicBool = AtomicBool::new(false);
+
+/// Open the share sheet to share the given content.
+pub(crate) fn share(app: &AndroidApp, content: &ShareContent) -> Result<(), Error> {
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    REPORTS_CHOSEN.get_or_init(|| {
+        let result = register_native_methods(&mut env, &activity);
+        if result.is_err() {
+            let _ = env.exception_clear();
+        }
+        result.is_ok()
+    });
+    CHOSEN.store(false, Ordering::Relaxed);
+
+    let result = start_chooser(&mut env, &activity, content);
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result
+}
+
+/// Whether an app was picked from the share sheet most recently opened, if the
+/// activity reports it.
+pub(crate) fn share_target_chosen() -> Option<bool> {
+    REPORTS_CHOSEN
+        .get()
+        .copied()
+        .unwrap_or(false)
+        .then(|| CHOSEN.load(Ordering::Relaxed))
+}
+
+fn register_native_methods(env: &mut JNIEnv<'_>, activity: &JObject<'_>) -> Result<(), Error> {
+    let class = env.get_object_class(activity)?;
+    env.register_native_methods(
+        &class,
+        &[NativeMethod {
+            name: "onShareTargetChosen".into(),
+            sig: "()V".into(),
+            fn_ptr: on_share_target_chosen as *mut _,
+        }],
+    )?;
+
+    Ok(())
+}
+
+fn start_chooser(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    content: &ShareContent,
+) -> Result<(), Error> {
+    let action = env.new_string("android.intent.action.SEND")?;
+    let intent = env.new_object(
+        "android/content/Intent",
+        "(Ljava/lang/String;)V",
+        &[(&action).into()],
+    )?;
+
+    let mime_type = match content {
+        ShareContent::Text(text) | ShareContent::Url(text) => {
+            let name = env.new_string("android.intent.extra.TEXT")?;
+            let text = env.new_string(text)?;
+            env.call_method(
+                &intent,
+                "putExtra",
+                "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/Intent;",
+                &[(&name).into(), (&text).into()],
+            )?;
+            "text/plain"
+        }
+        ShareContent::File {
+            name,
+            mime_type,
+            bytes,
+        } => {
+            let uri = write_file(env, activity, name, bytes)?;
+            let name = env.new_string("android.intent.extra.STREAM")?;
+            env.call_method(
+                &intent,
+                "putExtra",
+                "(Ljava/lang/String;Landroid/os/Parcelable;)Landroid/content/Intent;",
+                &[(&name).into(), (&uri).into()],
+            )?;
+
+            // The URI also has to be in the clip data for the share sheet
+            // itself to be allowed to read it for its preview.
+            let label = env.new_string("")?;
+            let clip = env
+                .call_static_method(
+                    "android/content/ClipData",
+                    "newRawUri",
+                    "(Ljava/lang/CharSequence;Landroid/net/Uri;)Landroid/content/ClipData;",
+                    &[(&label).into(), (&uri).into()],
+                )?
+                .l()?;
+            env.call_method(
+                &intent,
+                "setClipData",
+                "(Landroid/content/ClipData;)V",
+                &[(&clip).into()],
+            )?;
+            env.call_method(
+                &intent,
+                "addFlags",
+                "(I)Landroid/content/Intent;",
+                &[FLAG_GRANT_READ_URI_PERMISSION.into()],
+            )?;
+            mime_type
+        }
+    };
+    let mime_type = env.new_string(mime_type)?;
+    env.call_method(
+        &intent,
+        "setType",
+        "(Ljava/lang/String;)Landroid/content/Intent;",
+        &[(&mime_type).into()],
+    )?;
+
+    let sender = chosen_sender(env, activity)?;
+    let chooser = env
+        .call_static_method(
+            "android/content/Intent",
+            "createChooser",
+            "(Landroid/content/Intent;Ljava/lang/CharSequence;Landroid/content/IntentSender;)Landroid/content/Intent;",
+            &[
+                (&intent).into(),
+                (&JObject::null()).into(),
+                (&sender).into(),
+            ],
+        )?
+        .l()?;
+    env.call_method(
+        activity,
+        "startActivity",
+        "(Landroid/content/Intent;)V",
+        &[(&chooser).into()],
+    )?;
+
+    Ok(())
+}
+
+/// Write a file to share to the cache, and get a URI other apps can read it
+/// through.
+fn write_file<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject<'_>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<JObject<'local>, Error> {
+    let name = Path::new(name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("file");
+    let file = cache_file(env, activity, FILE_DIR, name)?;
+
+    let path = JString::from(
+        env.call_method(&file, "getAbsolutePath", "()Ljava/lang/String;", &[])?
+            .l()?,
+    );
+    let path = String::from(env.get_string(&path)?);
+    std::fs::write(path, bytes)?;
+
+    content_uri(env, activity, &file)
+}
+
+/// Get the `IntentSender` the share sheet reports a picked app to, which is a
+/// broadcast to the activity's receiver, or null if the activity doesn't
+/// have one.
+fn chosen_sender<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject<'_>,
+) -> Result<JObject<'local>, Error> {
+    if REPORTS_CHOSEN.get() != Some(&true) {
+        return Ok(JObject::null());
+    }
+
+    let package_name = package_name(env, activity)?;
+    let action = env.new_string(format!("{package_name}.SHARE_TARGET_CHOSEN"))?;
+    let intent = env.new_object(
+        "android/content/Intent",
+        "(Ljava/lang/String;)V",
+        &[(&action).into()],
+    )?;
+    let package_name = env.new_string(package_name)?;
+    env.call_method(
+        &intent,
+        "setPackage",
+        "(Ljava/lang/String;)Landroid/content/Intent;",
+        &[(&package_name).into()],
+    )?;
+
+    // Mutable, as the system adds the picked app to the intent.
+    let pending_intent = env
+        .call_static_method(
+            "android/app/PendingIntent",
+            "getBroadcast",
+            "(Landroid/content/Context;ILandroid/content/Intent;I)Landroid/app/PendingIntent;",
+            &[
+                activity.into(),
+                0.into(),
+                (&intent).into(),
+                (FLAG_MUTABLE | FLAG_UPDATE_CURRENT).into(),
+            ],
+        )?
+        .l()?;
+    let sender = env
+        .call_method(
+            &pending_intent,
+            "getIntentSender",
+            "()Landroid/content/IntentSender;",
+            &[],
+        )?
+        .l()?;
+
+    Ok(sender)
+}
+
+extern "system" fn on_share_target_chosen(_env: JNIEnv<'_>, _activity: JObject<'_>) {
+    CHOSEN.store(true, Ordering::Relaxed);
+}
@@ -0,0 +1,155 @@
+use crate::SystemBars;
+use android_activity::AndroidApp;
+use egui::Theme;
+use jni::{objects::JObject, JNIEnv, JavaVM};
+
+type Error = Box<dyn std::error::Error>;
+
+// Constants from `View` and `WindowInsetsController`.
+const SYSTEM_UI_FLAG_HIDE_NAVIGATION: i32 = 0x2;
+const SYSTEM_UI_FLAG_FULLSCREEN: i32 = 0x4;
+const SYSTEM_UI_FLAG_LIGHT_NAVIGATION_BAR: i32 = 0x10;
+const SYSTEM_UI_FLAG_LAYOUT_STABLE: i32 = 0x100;
+const SYSTEM_UI_FLAG_LAYOUT_HIDE_NAVIGATION: i32 = 0x200;
+const SYSTEM_UI_FLAG_LAYOUT_FULLSCREEN: i32 = 0x400;
+const SYSTEM_UI_FLAG_IMMERSIVE_STICKY: i32 = 0x1000;
+const SYSTEM_UI_FLAG_LIGHT_STATUS_BAR: i32 = 0x2000;
+const APPEARANCE_LIGHT_STATUS_BARS: i32 = 0x8;
+const APPEARANCE_LIGHT_NAVIGATION_BARS: i32 = 0x10;
+const BEHAVIOR_SHOW_TRANSIENT_BARS_BY_SWIPE: i32 = 2;
+
+const TRANSPARENT: i32 = 0;
+const BLACK: i32 = 0xff000000_u32 as i32;
+
+/// Apply the given system bar settings to the activity's window.
+pub(crate) fn set_system_bars(
+    app: &AndroidApp,
+    system_bars: SystemBars,
+    theme: Theme,
+) -> Result<(), Error> {
+    let sdk_version = app.config().sdk_version();
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    let result = apply(&mut env, &activity, sdk_version, system_bars, theme);
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result
+}
+
+fn apply(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    sdk_version: i32,
+    system_bars: SystemBars,
+    theme: Theme,
+) -> Result<(), Error> {
+    let SystemBars {
+        edge_to_edge,
+        hidden,
+        ..
+    } = system_bars;
+    let light = theme == Theme::Light;
+
+    let window = env
+        .call_method(activity, "getWindow", "()Landroid/view/Window;", &[])?
+        .l()?;
+
+    let color = if edge_to_edge { TRANSPARENT } else { BLACK };
+    env.call_method(&window, "setStatusBarColor", "(I)V", &[color.into()])?;
+    env.call_method(&window, "setNavigationBarColor", "(I)V", &[color.into()])?;
+
+    // Otherwise a translucent scrim is drawn behind gesture navigation.
+    if sdk_version >= 29 {
+        env.call_method(
+            &window,
+            "setNavigationBarContrastEnforced",
+            "(Z)V",
+            &[(!edge_to_edge).into()],
+        )?;
+    }
+
+    // Before Android 11, all of this is controlled with flags on the decor
+    // view instead.
+    if sdk_version < 30 {
+        let mut flags = 0;
+
+        if edge_to_edge {
+            flags |= SYSTEM_UI_FLAG_LAYOUT_STABLE
+                | SYSTEM_UI_FLAG_LAYOUT_HIDE_NAVIGATION
+                | SYSTEM_UI_FLAG_LAYOUT_FULLSCREEN;
+        }
+        if hidden {
+            flags |= SYSTEM_UI_FLAG_HIDE_NAVIGATION
+                | SYSTEM_UI_FLAG_FULLSCREEN
+                | SYSTEM_UI_FLAG_IMMERSIVE_STICKY;
+        }
+        if light && sdk_version >= 23 {
+            flags |= SYSTEM_UI_FLAG_LIGHT_STATUS_BAR;
+        }
+        if light && sdk_version >= 26 {
+            flags |= SYSTEM_UI_FLAG_LIGHT_NAVIGATION_BAR;
+        }
+
+        let decor_view = env
+            .call_method(&window, "getDecorView", "()Landroid/view/View;", &[])?
+            .l()?;
+        env.call_method(
+            &decor_view,
+            "setSystemUiVisibility",
+            "(I)V",
+            &[flags.into()],
+        )?;
+
+        return Ok(());
+    }
+
+    env.call_method(
+        &window,
+        "setDecorFitsSystemWindows",
+        "(Z)V",
+        &[(!edge_to_edge).into()],
+    )?;
+
+    let controller = env
+        .call_method(
+            &window,
+            "getInsetsController",
+            "()Landroid/view/WindowInsetsController;",
+            &[],
+        )?
+        .l()?;
+
+    let appearance_mask = APPEARANCE_LIGHT_STATUS_BARS | APPEARANCE_LIGHT_NAVIGATION_BARS;
+    let appearance = if light { appearance_mask } else { 0 };
+    env.call_method(
+        &controller,
+        "setSystemBarsAppearance",
+        "(II)V",
+        &[appearance.into(), appearance_mask.into()],
+    )?;
+
+    let window_insets_types = env.find_class("android/view/WindowInsets$Type")?;
+    let system_bars_type = env
+        .call_static_method(&window_insets_types, "systemBars", "()I", &[])?
+        .i()?;
+
+    if hidden {
+        env.call_method(
+            &controller,
+            "setSystemBarsBehavior",
+            "(I)V",
+            &[BEHAVIOR_SHOW_TRANSIENT_BARS_BY_SWIPE.into()],
+        )?;
+        env.call_method(&controller, "hide", "(I)V", &[system_bars_type.into()])?;
+    } else {
+        env.call_method(&controller, "show", "(I)V", &[system_bars_type.into()])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! The system's floating text selection menu, an `ActionMode` of type
+//! `TYPE_FLOATING`.
+//!
+//! An `ActionMode` needs a Java callback and has to be started on the UI
+//! thread, so the activity provides `showTextActionMode` and
+//! `hideTextActionMode` methods to do that, and a native `onTextAction` method
+//! that is implemented here. See `examples/java/MainActivity.java` for the
+//! Java side.
+
+use crate::internal::platform::input::{TextAction, TextActionMenu};
+use android_activity::{AndroidApp, AndroidAppWaker};
+use jni::{
+    objects::{JObject, JValue},
+    sys::jint,
+    JNIEnv, JavaVM, NativeMethod,
+};
+use std::sync::{Mutex, OnceLock};
+
+type Error = Box<dyn std::error::Error>;
+
+// Bits of the `actions` argument of `showTextActionMode`.
+const ACTION_CUT: i32 = 0x1;
+const ACTION_COPY: i32 = 0x2;
+const ACTION_PASTE: i32 = 0x4;
+const ACTION_SELECT_ALL: i32 = 0x8;
+
+// Menu item IDs from `android.R.id`, and the ID reported when the menu closes.
+const ID_CLOSED: jint = 0;
+const ID_SELECT_ALL: jint = 0x0102001f;
+const ID_CUT: jint = 0x01020020;
+const ID_COPY: jint = 0x01020021;
+const ID_PASTE: jint = 0x01020022;
+
+/// Menu items picked since they were last taken, or `None` for the menu
+/// closing.
+static PENDING_ACTIONS: Mutex<Vec<Option<TextAction>>> = Mutex::new(Vec::new());
+
+/// Wakes up the event loop when a menu item is picked. Set once the native
+/// method has been registered.
+static WAKER: OnceLock<AndroidAppWaker> = OnceLock::new();
+
+/// Show the menu, or update it if it is already shown, or hide it if `None`.
+pub(crate) fn set_text_action_menu(
+    app: &AndroidApp,
+    menu: Option<&TextActionMenu>,
+) -> Result<(), Error> {
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    let result = match menu {
+        Some(menu) => show(&mut env, &activity, app, menu),
+        None => env
+            .call_method(&activity, "hideTextActionMode", "()V", &[])
+            .map(drop)
+            .map_err(Into::into),
+    };
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result
+}
+
+/// Take the menu items picked since this was last called.
+pub(crate) fn take_text_actions() -> Vec<Option<TextAction>> {
+    std::mem::take(&mut *PENDING_ACTIONS.lock().unwrap())
+}
+
+pub(crate) fn has_text_actions() -> bool {
+    !PENDING_ACTIONS.lock().unwrap().is_empty()
+}
+
+fn show(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+    app: &AndroidApp,
+    menu: &TextActionMenu,
+) -> Result<(), Error> {
+    if WAKER.get().is_none() {
+        let class = env.get_object_class(activity)?;
+        env.register_native_methods(
+            &class,
+            &[NativeMethod {
+                name: "onTextAction".into(),
+                sig: "(I)V".into(),
+                fn_ptr: on_text_action as *mut _,
+            }],
+        )?;
+        let _ = WAKER.set(app.create_waker());
+    }
+
+    let actions = menu.actions.iter().fold(0, |bits, action| {
+        bits | match action {
+            TextAction::Cut => ACTION_CUT,
+            TextAction::Copy => ACTION_COPY,
+            TextAction::Paste => ACTION_PASTE,
+            TextAction::SelectAll => ACTION_SELECT_ALL,
+        }
+    });
+    let selected_text = match &menu.selected_text {
+        Some(text) => JObject::from(env.new_string(text)?),
+        None => JObject::null(),
+    };
+
+    env.call_method(
+        activity,
+        "showTextActionMode",
+        "(IIIIILjava/lang/String;)V",
+        &[
+            (menu.rect.left().round() as i32).into(),
+            (menu.rect.top().round() as i32).into(),
+            (menu.rect.right().round() as i32).into(),
+            (menu.rect.bottom().round() as i32).into(),
+            actions.into(),
+            JValue::Object(&selected_text),
+        ],
+    )?;
+
+    Ok(())
+}
+
+extern "system" fn on_text_action(_env: JNIEnv<'_>, _activity: JObject<'_>, item_id: jint) {
+    let action = match item_id {
+        ID_CLOSED => None,
+        ID_CUT => Some(TextAction::Cut),
+        ID_COPY => Some(TextAction::Copy),
+        ID_PASTE => Some(TextAction::Paste),
+        ID_SELECT_ALL => Some(TextAction::SelectAll),
+        _ => {
+            log::warn!("unknown text action menu item: {item_id:#x}");
+            return;
+        }
+    };
+
+    PENDING_ACTIONS.lock().unwrap().push(action);
+
+    if let Some(waker) = WAKER.get() {
+        waker.wake();
+    }
+}
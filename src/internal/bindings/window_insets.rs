@@ -0,0 +1,98 @@
+use crate::WindowInsets;
+use android_activity::AndroidApp;
+use egui::epaint::MarginF32;
+use jni::{
+    objects::{JClass, JObject},
+    JNIEnv, JavaVM,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+/// `WindowInsets.getInsets(int)` was added in Android 11.
+const MIN_SDK_VERSION: i32 = 30;
+
+/// Get the current
+/// [`WindowInsets`](https://developer.android.com/reference/android/view/WindowInsets)
+/// of the activity's window, in physical pixels.
+///
+/// Returns `None` if the insets are not known, either because the window is
+/// not attached yet or because the device is too old to report them.
+pub(crate) fn window_insets(app: &AndroidApp) -> Result<Option<WindowInsets>, Error> {
+    if app.config().sdk_version() < MIN_SDK_VERSION {
+        return Ok(None);
+    }
+
+    let vm = unsafe { JavaVM::from_raw(app.vm_as_ptr() as _)? };
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr() as _) };
+    let mut env = vm.attach_current_thread()?;
+
+    let result = root_window_insets(&mut env, &activity);
+
+    // Don't leave an exception pending for the next JNI call to trip over.
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+
+    result
+}
+
+fn root_window_insets(
+    env: &mut JNIEnv<'_>,
+    activity: &JObject<'_>,
+) -> Result<Option<WindowInsets>, Error> {
+    let window = env
+        .call_method(activity, "getWindow", "()Landroid/view/Window;", &[])?
+        .l()?;
+    let decor_view = env
+        .call_method(&window, "getDecorView", "()Landroid/view/View;", &[])?
+        .l()?;
+    let insets = env
+        .call_method(
+            &decor_view,
+            "getRootWindowInsets",
+            "()Landroid/view/WindowInsets;",
+            &[],
+        )?
+        .l()?;
+
+    if insets.is_null() {
+        return Ok(None);
+    }
+
+    let types = env.find_class("android/view/WindowInsets$Type")?;
+
+    Ok(Some(WindowInsets {
+        system_bars: insets_of_type(env, &insets, &types, "systemBars")?,
+        display_cutout: insets_of_type(env, &insets, &types, "displayCutout")?,
+        system_gestures: insets_of_type(env, &insets, &types, "systemGestures")?,
+        ime: insets_of_type(env, &insets, &types, "ime")?,
+    }))
+}
+
+/// Call `insets.getInsets(WindowInsets.Type.<type_name>())`.
+fn insets_of_type(
+    env: &mut JNIEnv<'_>,
+    insets: &JObject<'_>,
+    types: &JClass<'_>,
+    type_name: &str,
+) -> Result<MarginF32, Error> {
+    let type_mask = env.call_static_method(types, type_name, "()I", &[])?.i()?;
+    let insets = env
+        .call_method(
+            insets,
+            "getInsets",
+            "(I)Landroid/graphics/Insets;",
+            &[type_mask.into()],
+        )?
+        .l()?;
+
+    let mut side =
+        |name| -> Result<f32, Error> { Ok(env.get_field(&insets, name, "I")?.i()? as f32) };
+
+    Ok(MarginF32 {
+        left: side("left")?,
+        right: side("right")?,
+        top: side("top")?,
+        bottom: side("bottom")?,
+    })
+}
@@ -2,8 +2,19 @@
 
 // https://github.com/emilk/egui/blob/master/crates/eframe/src/native/glow_integration.rs#L905
 
-use egui::FullOutput;
-use egui_glow::{glow, Painter};
+use super::{
+    platform::{self, Graphics},
+    textures::TextureCache,
+};
+use crate::{GraphicsError, GraphicsOptions};
+use egui::{
+    epaint::{PaintCallbackInfo, Primitive},
+    ClippedPrimitive, ColorImage, FullOutput, PaintCallback, TexturesDelta,
+};
+use egui_glow::{
+    glow::{self, HasContext},
+    CallbackFn, Painter,
+};
 use glutin::{
     api::egl::{
         config::Config,
@@ -11,46 +22,67 @@ use glutin::{
         display::Display,
         surface::Surface,
     },
-    config::{ConfigTemplate, ConfigTemplateBuilder},
-    context::{ContextApi, ContextAttributesBuilder, NotCurrentGlContext},
+    config::{Api, ConfigSurfaceTypes, ConfigTemplate, ConfigTemplateBuilder, GlConfig},
+    context::{ContextApi, ContextAttributesBuilder, NotCurrentGlContext, Version},
     display::GlDisplay,
+    error::ErrorKind,
     prelude::PossiblyCurrentGlContext,
     surface::{GlSurface, SurfaceAttributesBuilder, WindowSurface},
 };
-use ndk::native_window::NativeWindow;
+use ndk::{hardware_buffer_format::HardwareBufferFormat, native_window::NativeWindow};
 use raw_window_handle::{
     AndroidDisplayHandle, AndroidNdkWindowHandle, RawDisplayHandle, RawWindowHandle,
 };
-use std::{ffi::CString, mem::replace, num::NonZeroU32, sync::Arc};
+use std::{
+    borrow::Cow,
+    ffi::CString,
+    mem::{replace, take},
+    num::NonZeroU32,
+    sync::Arc,
+};
 
 /// Establishes a connection to Android's graphics API.
 pub(crate) struct GraphicsContext {
-    /// This is the primary EGL display connection.
-    display: Display,
+    /// Preferences for the EGL config and context.
+    options: GraphicsOptions,
 
-    /// This is the config that we use when creating surfaces.
-    egl_config: Config,
+    /// The EGL display connection. This is only established once the first
+    /// window is attached, so that errors can be reported to the activity.
+    egl: Option<Egl>,
 
     /// Sometimes we have a window surface, sometimes we don't. This holds
     /// objects specific to each of these possible states.
     state: State,
+
+    /// A copy of every texture egui has uploaded, in case the context is lost.
+    textures: TextureCache,
+
+    /// Set when a new painter has been created, and all textures need to be
+    /// uploaded to it.
+    replay_textures: bool,
+}
+
+struct Egl {
+    /// This is the primary EGL display connection.
+    display: Display,
+
+    /// This is the config that we use when creating surfaces.
+    config: Config,
+
+    /// Preferences for the EGL config and context.
+    options: GraphicsOptions,
 }
 
 #[derive(Default)]
 enum State {
-    /// We have not initialized OpenGL yet. We only do so lazily, since we do
-    /// need a window surface to do so.
+    /// We have not initialized OpenGL yet, or the context was lost. We only do
+    /// so lazily, since we do need a window surface to do so.
     #[default]
     Uninitialized,
 
     /// A window surface has been associated with the context and is currently
     /// active.
-    Active {
-        native_window: NativeWindow,
-        surface: Surface<WindowSurface>,
-        gl_context: PossiblyCurrentContext,
-        painter: Painter,
-    },
+    Active(Active),
 
     /// Android took away our window, but we still need to maintain our graphics
     /// handles until the window comes back.
@@ -60,112 +92,273 @@ enum State {
     },
 }
 
+struct Active {
+    native_window: NativeWindow,
+    surface: Surface<WindowSurface>,
+    gl_context: PossiblyCurrentContext,
+    painter: Painter,
+}
+
 impl GraphicsContext {
     /// Create a new instance. Only one instance should be created per process.
-    pub(crate) fn new() -> Self {
-        Self::with_config(
-            ConfigTemplateBuilder::new()
-                .with_depth_size(0)
-                .with_stencil_size(0)
-                .with_transparency(false)
-                .build(),
-        )
+    pub(crate) fn new(options: GraphicsOptions) -> Self {
+        GraphicsContext {
+            options,
+            egl: None,
+            state: State::default(),
+            textures: TextureCache::default(),
+            replay_textures: false,
+        }
     }
 
-    /// Create a new instance. Only one instance should be created per process.
-    pub(crate) fn with_config(config_template: ConfigTemplate) -> Self {
-        let display = get_default_display();
+    /// Attach a window to the context. A graphics surface will be initialized
+    /// within the window, and a renderer will become available for drawing to
+    /// the surface.
+    ///
+    /// If a window is already attached, its surface is torn down and replaced.
+    pub(crate) fn attach_window(
+        &mut self,
+        native_window: NativeWindow,
+    ) -> Result<(), GraphicsError> {
+        let egl = match self.egl.take() {
+            Some(egl) => egl,
+            None => Egl::new(&self.options)?,
+        };
+        let egl = self.egl.insert(egl);
+
+        self.state
+            .attach(egl, native_window, &mut self.replay_textures)
+    }
+}
 
-        let egl_config = unsafe { display.find_configs(config_template) }
-            .unwrap()
-            .next()
-            .unwrap();
+impl Graphics for GraphicsContext {
+    type Renderer<'a> = Renderer<'a>;
 
-        GraphicsContext {
-            display,
-            egl_config,
-            state: State::default(),
+    fn renderer(&mut self) -> Option<Renderer<'_>> {
+        if !matches!(self.state, State::Active(_)) {
+            return None;
         }
+
+        Some(Renderer {
+            egl: self.egl.as_ref()?,
+            state: &mut self.state,
+            textures: &mut self.textures,
+            replay_textures: &mut self.replay_textures,
+        })
     }
 
-    /// Get a renderer for the current window. If a window is not currently
-    /// associated with the context, then `None` is returned.
-    pub(crate) fn renderer(&mut self) -> Option<Renderer<'_>> {
-        match &mut self.state {
-            State::Active {
-                native_window,
-                surface,
-                gl_context,
-                painter,
-                ..
-            } => Some(Renderer {
-                native_window,
-                surface,
-                gl_context,
-                painter,
-            }),
-            _ => None,
+    fn detach_window(&mut self) {
+        self.state.detach(&mut self.replay_textures);
+    }
+
+    fn gl(&self) -> Option<Arc<glow::Context>> {
+        match &self.state {
+            State::Active(Active { painter, .. }) | State::Suspended { painter, .. } => {
+                Some(painter.gl().clone())
+            }
+            State::Uninitialized => None,
         }
     }
+}
 
-    /// Attach a window to the context. A graphics surface will be initialized
-    /// within the window, and a renderer will become available for drawing to
-    /// the surface.
-    pub(crate) fn attach_window(&mut self, native_window: NativeWindow) {
-        self.state = match self.state.take() {
-            // This is the first time a window has been created, initialize everything.
-            State::Uninitialized => {
-                let context_attributes = ContextAttributesBuilder::new()
-                    .with_context_api(ContextApi::Gles(None))
-                    .build(None);
+impl Egl {
+    fn new(options: &GraphicsOptions) -> Result<Self, GraphicsError> {
+        // There is only one way to get the default display on Android, and it
+        // is stateless.
+        let raw_display_handle = RawDisplayHandle::Android(AndroidDisplayHandle::new());
+        let display = unsafe { Display::new(raw_display_handle) }
+            .map_err(|e| GraphicsError::Display(e.into()))?;
+
+        let config = choose_config(&display, options)?;
+
+        log::info!(
+            "chose EGL config: api: {:?}, samples: {}, depth: {}, stencil: {}, alpha: {}, \
+             srgb: {}, hardware accelerated: {}",
+            config.api(),
+            config.num_samples(),
+            config.depth_size(),
+            config.stencil_size(),
+            config.alpha_size(),
+            config.srgb_capable(),
+            config.hardware_accelerated(),
+        );
 
-                let gl_context = unsafe {
-                    self.display
-                        .create_context(&self.egl_config, &context_attributes)
-                }
-                .unwrap();
+        Ok(Self {
+            display,
+            config,
+            options: options.clone(),
+        })
+    }
+}
 
-                let surface = self.create_window_surface(&native_window);
+/// Find the EGL config that best matches the given options.
+///
+/// Configs are first searched for with every option as a hard requirement.
+/// If nothing matches, requirements are dropped one at a time, starting with
+/// the least important, until something does.
+fn choose_config(display: &Display, options: &GraphicsOptions) -> Result<Config, GraphicsError> {
+    let mut requirements = options.clone();
+
+    // Requirements are dropped in this order, least important first.
+    let fallbacks: [fn(&mut GraphicsOptions); 5] = [
+        |o| o.multisampling = 0,
+        |o| o.srgb = false,
+        |o| o.stencil_bits = 0,
+        |o| o.depth_bits = 0,
+        |o| o.transparent = false,
+    ];
+    let mut fallbacks = fallbacks.into_iter();
+
+    loop {
+        let configs = unsafe { display.find_configs(config_template(&requirements)) }
+            .map_err(|e| GraphicsError::Display(e.into()))?;
+
+        let best = configs
+            .filter(|config| !requirements.srgb || config.srgb_capable())
+            .min_by_key(|config| rank_config(config, options));
+
+        if let Some(config) = best {
+            return Ok(config);
+        }
 
-                let gl_context = gl_context.make_current(&surface).unwrap();
-                let glow_context = Arc::new(self.create_glow_context(&gl_context));
+        let previous = requirements.clone();
 
-                State::Active {
-                    native_window,
-                    surface,
-                    gl_context,
-                    painter: Painter::new(glow_context, "", None, false).unwrap(),
-                }
+        while requirements == previous {
+            let relax = fallbacks.next().ok_or(GraphicsError::NoMatchingConfig)?;
+            relax(&mut requirements);
+        }
+
+        log::warn!("no EGL config matches {previous:?}, trying {requirements:?}");
+    }
+}
+
+fn config_template(requirements: &GraphicsOptions) -> ConfigTemplate {
+    let api = match requirements.gles_version {
+        Some((major, _)) if major >= 3 => Api::GLES3,
+        _ => Api::GLES2,
+    };
+
+    ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::WINDOW)
+        .with_api(api)
+        .with_multisampling(requirements.multisampling)
+        .with_depth_size(requirements.depth_bits)
+        .with_stencil_size(requirements.stencil_bits)
+        .with_alpha_size(if requirements.transparent { 8 } else { 0 })
+        .with_transparency(requirements.transparent)
+        .build()
+}
+
+/// Sort key for configs that meet the requirements, where lower is better.
+/// Configs closest to what was asked for are preferred, so that no memory is
+/// wasted on buffers that will not be used.
+fn rank_config(config: &Config, options: &GraphicsOptions) -> impl Ord {
+    (
+        !config.hardware_accelerated(),
+        options.srgb && !config.srgb_capable(),
+        config.num_samples().abs_diff(options.multisampling),
+        config.depth_size().abs_diff(options.depth_bits),
+        config.stencil_size().abs_diff(options.stencil_bits),
+        !options.transparent && config.alpha_size() > 0,
+    )
+}
+
+impl State {
+    fn take(&mut self) -> Self {
+        replace(self, Self::Uninitialized)
+    }
+
+    /// Attach a window, creating whichever graphics objects are missing. If
+    /// this fails, everything that could be kept is kept for the next attempt.
+    fn attach(
+        &mut self,
+        egl: &Egl,
+        native_window: NativeWindow,
+        replay_textures: &mut bool,
+    ) -> Result<(), GraphicsError> {
+        // Android sometimes gives us a new window without taking away the old
+        // one first, so make sure the old surface is released.
+        self.detach(replay_textures);
+
+        *self = match self.take() {
+            // This is the first time a window has been created, or the previous
+            // context was lost. Initialize everything.
+            State::Uninitialized => {
+                *replay_textures = true;
+                State::Active(Active::new(egl, native_window)?)
             }
 
             State::Suspended {
                 gl_context,
-                painter,
+                mut painter,
             } => {
-                let surface = self.create_window_surface(&native_window);
-                let gl_context = gl_context.make_current(&surface).unwrap();
-
-                State::Active {
-                    native_window,
-                    surface,
-                    gl_context,
-                    painter,
+                let surface = match create_window_surface(egl, &native_window) {
+                    Ok(surface) => surface,
+                    Err(e) => {
+                        *self = State::Suspended {
+                            gl_context,
+                            painter,
+                        };
+                        return Err(e);
+                    }
+                };
+
+                match gl_context.make_current(&surface) {
+                    Ok(gl_context) => State::Active(Active {
+                        native_window,
+                        surface,
+                        gl_context,
+                        painter,
+                    }),
+
+                    // The context was lost while we were in the background,
+                    // which many devices do to reclaim memory.
+                    Err(e) if e.error_kind() == ErrorKind::ContextLost => {
+                        log::warn!("EGL context lost while suspended, recreating it");
+
+                        // The painter's GL objects are already gone along with
+                        // the context, so this only marks it as destroyed.
+                        painter.destroy();
+                        drop(surface);
+
+                        *replay_textures = true;
+                        State::Active(Active::new(egl, native_window)?)
+                    }
+
+                    Err(e) => {
+                        painter.destroy();
+                        return Err(GraphicsError::MakeCurrent(e.into()));
+                    }
                 }
             }
 
-            State::Active { .. } => todo!(),
+            State::Active(_) => unreachable!("window was detached above"),
         };
+
+        Ok(())
     }
 
-    pub(crate) fn detach_window(&mut self) {
-        self.state = match self.state.take() {
-            State::Active {
+    /// Release the current window, if there is one, keeping the context and
+    /// painter for when a new window is attached.
+    fn detach(&mut self, replay_textures: &mut bool) {
+        *self = match self.take() {
+            State::Active(Active {
                 gl_context,
-                painter,
+                mut painter,
                 ..
-            } => State::Suspended {
-                painter,
-                gl_context: gl_context.make_not_current().unwrap(),
+            }) => match gl_context.make_not_current() {
+                Ok(gl_context) => State::Suspended {
+                    painter,
+                    gl_context,
+                },
+
+                // Start over from scratch the next time a window is attached.
+                Err(e) => {
+                    log::warn!("failed to release EGL context, it will be recreated: {e}");
+                    painter.destroy();
+                    *replay_textures = true;
+                    State::Uninitialized
+                }
             },
 
             // We have no window to remove, do nothing.
@@ -173,108 +366,313 @@ impl GraphicsContext {
         };
     }
 
-    fn create_glow_context(&self, _gl_context: &PossiblyCurrentContext) -> glow::Context {
-        unsafe {
-            glow::Context::from_loader_function(|s| {
-                let s = CString::new(s).unwrap();
-                self.display.get_proc_address(&s).cast()
-            })
-        }
-    }
-
-    fn create_window_surface(&self, native_window: &NativeWindow) -> Surface<WindowSurface> {
-        let raw_window_handle =
-            RawWindowHandle::from(AndroidNdkWindowHandle::new(native_window.ptr().cast()));
+    /// Destroy all graphics objects.
+    fn reset(&mut self) {
+        match self.take() {
+            State::Active(Active { mut painter, .. }) => {
+                painter.destroy();
+            }
 
-        let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
-            raw_window_handle,
-            NonZeroU32::new(native_window.width().try_into().unwrap()).unwrap(),
-            NonZeroU32::new(native_window.height().try_into().unwrap()).unwrap(),
-        );
+            State::Suspended { mut painter, .. } => {
+                painter.destroy();
+            }
 
-        unsafe {
-            self.display
-                .create_window_surface(&self.egl_config, &surface_attributes)
+            State::Uninitialized => {}
         }
-        .unwrap()
     }
 }
 
-impl State {
-    fn take(&mut self) -> Self {
-        replace(self, Self::Uninitialized)
+impl Active {
+    /// Create a new context and painter for drawing into the given window.
+    fn new(egl: &Egl, native_window: NativeWindow) -> Result<Self, GraphicsError> {
+        let gl_context = create_context(egl)?;
+
+        let surface = create_window_surface(egl, &native_window)?;
+
+        let gl_context = gl_context
+            .make_current(&surface)
+            .map_err(|e| GraphicsError::MakeCurrent(e.into()))?;
+        let glow_context = Arc::new(create_glow_context(&egl.display));
+
+        let painter = Painter::new(glow_context, "", None, false)
+            .map_err(|e| GraphicsError::CreatePainter(e.into()))?;
+
+        Ok(Active {
+            native_window,
+            surface,
+            gl_context,
+            painter,
+        })
     }
 }
 
 impl Drop for GraphicsContext {
     fn drop(&mut self) {
-        match self.state.take() {
-            State::Active {
-                gl_context,
-                mut painter,
-                ..
-            } => {
-                gl_context.make_not_current().unwrap();
-                painter.destroy();
-            }
-
-            State::Suspended { mut painter, .. } => {
-                painter.destroy();
-            }
-
-            State::Uninitialized => {}
-        }
+        self.state.reset();
     }
 }
 
 pub(crate) struct Renderer<'c> {
-    native_window: &'c NativeWindow,
-    surface: &'c Surface<WindowSurface>,
-    gl_context: &'c PossiblyCurrentContext,
-    painter: &'c mut Painter,
+    egl: &'c Egl,
+    state: &'c mut State,
+    textures: &'c mut TextureCache,
+    replay_textures: &'c mut bool,
 }
 
 impl Renderer<'_> {
-    pub(crate) fn handle_resize(&self) {
-        self.surface.resize(
-            self.gl_context,
-            NonZeroU32::new(self.native_window.width() as _).unwrap(),
-            NonZeroU32::new(self.native_window.height() as _).unwrap(),
-        );
+    fn active(&self) -> &Active {
+        match &*self.state {
+            State::Active(active) => active,
+            _ => unreachable!("renderer exists without an active window"),
+        }
     }
 
-    pub(crate) fn repaint(
+    fn active_mut(&mut self) -> &mut Active {
+        match self.state {
+            State::Active(active) => active,
+            _ => unreachable!("renderer exists without an active window"),
+        }
+    }
+
+    fn paint(
         &mut self,
-        full_output: &mut FullOutput,
-        clipped_primitives: &[egui::ClippedPrimitive],
-    ) {
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+        screenshot: bool,
+    ) -> glutin::error::Result<Option<ColorImage>> {
+        let egl = self.egl;
+        let active = self.active_mut();
+
         let screen_size = [
-            self.surface.width().unwrap(),
-            self.surface.height().unwrap(),
+            active
+                .surface
+                .width()
+                .unwrap_or(active.native_window.width() as _),
+            active
+                .surface
+                .height()
+                .unwrap_or(active.native_window.height() as _),
         ];
 
-        self.painter.clear(screen_size, [0.0, 0.0, 0.0, 0.0]);
+        active.painter.clear(screen_size, [0.0, 0.0, 0.0, 0.0]);
+
+        // egui does not use these buffers, but paint callbacks drawing 3D
+        // content expect them to start out cleared.
+        if egl.config.depth_size() > 0 || egl.config.stencil_size() > 0 {
+            let gl = active.painter.gl();
+
+            unsafe {
+                gl.depth_mask(true);
+                gl.stencil_mask(!0);
+                gl.clear(glow::DEPTH_BUFFER_BIT | glow::STENCIL_BUFFER_BIT);
+            }
+        }
 
-        self.painter.paint_and_update_textures(
+        active.painter.paint_and_update_textures(
             screen_size,
-            full_output.pixels_per_point,
-            clipped_primitives,
-            &full_output.textures_delta,
+            pixels_per_point,
+            &with_state_restored_after_callbacks(clipped_primitives),
+            textures_delta,
         );
 
-        self.surface.swap_buffers(self.gl_context).unwrap();
+        // The back buffer is undefined after swapping, so read it first.
+        let image = screenshot.then(|| active.painter.read_screen_rgba(screen_size));
+
+        active.surface.swap_buffers(&active.gl_context)?;
+
+        Ok(image)
     }
 
-    pub(crate) fn window_size(&self) -> [u32; 2] {
+    /// Recreate the surface in the same window, and the context too if it was
+    /// lost.
+    fn recreate(&mut self, context_lost: bool) -> Result<(), GraphicsError> {
+        let native_window = self.active().native_window.clone();
+
+        if context_lost {
+            self.state.reset();
+        } else {
+            self.state.detach(self.replay_textures);
+        }
+
+        self.state
+            .attach(self.egl, native_window, self.replay_textures)
+    }
+
+    /// Take all textures that need to be uploaded because the painter is new.
+    fn take_replay(&mut self) -> Option<TexturesDelta> {
+        take(self.replay_textures).then(|| self.textures.replay())
+    }
+}
+
+impl platform::Renderer for Renderer<'_> {
+    fn handle_resize(&mut self) {
+        let active = self.active();
+
+        if let (Some(width), Some(height)) = (
+            NonZeroU32::new(active.native_window.width() as _),
+            NonZeroU32::new(active.native_window.height() as _),
+        ) {
+            active.surface.resize(&active.gl_context, width, height);
+        }
+    }
+
+    fn repaint(
+        &mut self,
+        full_output: &mut FullOutput,
+        clipped_primitives: &[ClippedPrimitive],
+        screenshot: bool,
+    ) -> Result<Option<ColorImage>, GraphicsError> {
+        let pixels_per_point = full_output.pixels_per_point;
+        self.textures.update(&full_output.textures_delta);
+
+        // After the painter has been replaced, everything needs to be uploaded
+        // again, not just what changed this frame.
+        let replay = self.take_replay();
+        let textures_delta = replay.as_ref().unwrap_or(&full_output.textures_delta);
+
+        let e = match self.paint(
+            pixels_per_point,
+            clipped_primitives,
+            textures_delta,
+            screenshot,
+        ) {
+            Ok(image) => return Ok(image),
+            Err(e) => e,
+        };
+
+        // Try once more with a fresh surface, which usually fixes errors such
+        // as EGL_BAD_SURFACE after the window changed underneath us. Textures
+        // were already uploaded, unless the context was lost.
+        let context_lost = e.error_kind() == ErrorKind::ContextLost;
+        log::warn!("failed to swap buffers, recreating surface: {e}");
+        self.recreate(context_lost)?;
+
+        let textures_delta = self.take_replay().unwrap_or_default();
+        self.paint(
+            pixels_per_point,
+            clipped_primitives,
+            &textures_delta,
+            screenshot,
+        )
+        .map_err(|e| GraphicsError::SwapBuffers(e.into()))
+    }
+
+    fn window_size(&self) -> [u32; 2] {
+        let active = self.active();
+
         [
-            self.native_window.width() as _,
-            self.native_window.height() as _,
+            active.native_window.width() as _,
+            active.native_window.height() as _,
         ]
     }
 }
 
-// There is only one way to get the default display on Android, and it is stateless.
-fn get_default_display() -> Display {
-    let raw_display_handle = RawDisplayHandle::Android(AndroidDisplayHandle::new());
-    unsafe { Display::new(raw_display_handle) }.unwrap()
+/// Insert a callback after every paint callback, which resets any GL state the
+/// callback may have changed that egui_glow does not reset by itself.
+fn with_state_restored_after_callbacks(
+    clipped_primitives: &[ClippedPrimitive],
+) -> Cow<'_, [ClippedPrimitive]> {
+    if !clipped_primitives
+        .iter()
+        .any(|p| matches!(p.primitive, Primitive::Callback(_)))
+    {
+        return Cow::Borrowed(clipped_primitives);
+    }
+
+    let mut primitives = Vec::with_capacity(clipped_primitives.len() + 1);
+
+    for clipped_primitive in clipped_primitives {
+        primitives.push(clipped_primitive.clone());
+
+        if let Primitive::Callback(callback) = &clipped_primitive.primitive {
+            primitives.push(ClippedPrimitive {
+                clip_rect: clipped_primitive.clip_rect,
+                primitive: Primitive::Callback(PaintCallback {
+                    rect: callback.rect,
+                    callback: Arc::new(CallbackFn::new(restore_gl_state)),
+                }),
+            });
+        }
+    }
+
+    Cow::Owned(primitives)
+}
+
+fn restore_gl_state(_info: PaintCallbackInfo, painter: &Painter) {
+    let gl = painter.gl();
+
+    unsafe {
+        gl.bind_framebuffer(glow::FRAMEBUFFER, painter.intermediate_fbo());
+        gl.disable(glow::STENCIL_TEST);
+        gl.color_mask(true, true, true, true);
+        gl.active_texture(glow::TEXTURE0);
+    }
+}
+
+/// Create a context with the requested version of OpenGL ES, falling back to
+/// the driver's default version if it is not available.
+fn create_context(egl: &Egl) -> Result<NotCurrentContext, GraphicsError> {
+    let create = |version: Option<Version>| {
+        let context_attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::Gles(version))
+            .build(None);
+
+        unsafe { egl.display.create_context(&egl.config, &context_attributes) }
+    };
+
+    let result = match egl.options.gles_version {
+        Some((major, minor)) => create(Some(Version::new(major, minor))).or_else(|e| {
+            log::warn!("OpenGL ES {major}.{minor} is not available, using default: {e}");
+            create(None)
+        }),
+        None => create(None),
+    };
+
+    result.map_err(|e| GraphicsError::CreateContext(e.into()))
+}
+
+fn create_glow_context(display: &Display) -> glow::Context {
+    unsafe {
+        glow::Context::from_loader_function(|s| {
+            let s = CString::new(s).unwrap();
+            display.get_proc_address(&s).cast()
+        })
+    }
+}
+
+fn create_window_surface(
+    egl: &Egl,
+    native_window: &NativeWindow,
+) -> Result<Surface<WindowSurface>, GraphicsError> {
+    let (width, height) = (native_window.width(), native_window.height());
+
+    let (Some(non_zero_width), Some(non_zero_height)) = (
+        NonZeroU32::new(width.try_into().unwrap_or(0)),
+        NonZeroU32::new(height.try_into().unwrap_or(0)),
+    ) else {
+        return Err(GraphicsError::InvalidWindowSize { width, height });
+    };
+
+    let raw_window_handle =
+        RawWindowHandle::from(AndroidNdkWindowHandle::new(native_window.ptr().cast()));
+
+    // The window is opaque unless we ask for a format with an alpha channel.
+    if egl.options.transparent {
+        if let Err(e) =
+            native_window.set_buffers_geometry(0, 0, Some(HardwareBufferFormat::R8G8B8A8_UNORM))
+        {
+            log::warn!("failed to make window transparent: {e}");
+        }
+    }
+
+    let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new()
+        .with_srgb(egl.options.srgb.then_some(true))
+        .build(raw_window_handle, non_zero_width, non_zero_height);
+
+    unsafe {
+        egl.display
+            .create_window_surface(&egl.config, &surface_attributes)
+    }
+    .map_err(|e| GraphicsError::CreateSurface(e.into()))
 }
@@ -1,28 +1,53 @@
-use android_activity::{
-    input::{
-        Axis, Button, InputEvent, KeyAction, KeyEvent, KeyMapChar, Keycode, MetaState,
-        MotionAction, MotionEvent, Pointer, ToolType,
-    },
-    AndroidApp, InputStatus,
+use super::platform::input::{
+    InputEvent, InputStatus, JoystickEvent, KeyAction, KeyEvent, MotionAction, MotionEvent,
+    Pointer, TextInputState,
 };
+use crate::{GamepadButton, GamepadState, StylusSample, ToolType};
 use egui::{
-    pos2, vec2, Event, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, TouchDeviceId,
-    TouchId, TouchPhase, ViewportEvent,
+    Context, Event, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, RawInput, TouchDeviceId,
+    TouchId, TouchPhase, Vec2, ViewportEvent, NUM_POINTER_BUTTONS,
 };
+use std::collections::BTreeMap;
+
+/// The buttons of [`MotionEvent::button_state`], in order.
+const POINTER_BUTTONS: [PointerButton; NUM_POINTER_BUTTONS] = [
+    PointerButton::Primary,
+    PointerButton::Secondary,
+    PointerButton::Middle,
+    PointerButton::Extra1,
+    PointerButton::Extra2,
+];
 
 /// Stateful object that processes input events from Android, and translates
 /// them into egui input events.
+#[derive(Default)]
 pub(crate) struct InputHandler {
-    app: AndroidApp,
-    combining_accent: Option<char>,
+    /// The latest state of the text being edited that was reported by the
+    /// soft keyboard, which has yet to be given to egui.
+    text_input_state: Option<TextInputState>,
+
+    /// The mouse buttons held down as of the last mouse event.
+    mouse_buttons: [bool; NUM_POINTER_BUTTONS],
+
+    /// Where the mouse was last seen, in points, unless it left the window.
+    mouse_pos: Option<Pos2>,
+
+    /// Samples of touches and styluses that have yet to be given to the app.
+    stylus_samples: Vec<StylusSample>,
+
+    /// The state of each game controller that has been used, by device ID.
+    gamepads: BTreeMap<i32, GamepadState>,
+
+    /// The last position of the hat switch of each game controller.
+    hats: BTreeMap<i32, Vec2>,
+
+    /// Whether the A button of game controllers clicks the focused widget.
+    focus_navigation: bool,
 }
 
 impl InputHandler {
-    pub fn new(app: AndroidApp) -> Self {
-        Self {
-            app,
-            combining_accent: None,
-        }
+    pub fn new() -> Self {
+        Self::default()
     }
 
     /// Process an input event.
@@ -35,26 +60,30 @@ impl InputHandler {
     /// processed, or if it was an unknown event type.
     pub fn process(
         &mut self,
-        android_event: &InputEvent,
+        event: &InputEvent,
         pixels_per_point: f32,
         receiver: &mut RawInput,
     ) -> InputStatus {
-        log::info!("processing input event: {:?}", android_event);
+        match event {
+            InputEvent::Key(key_event) => self.process_key_event(key_event, receiver),
+
+            InputEvent::Joystick(joystick_event) => {
+                self.process_joystick_event(joystick_event, receiver);
+                InputStatus::Handled
+            }
 
-        match android_event {
-            InputEvent::KeyEvent(key_event) => self.process_key_event(key_event, receiver),
+            InputEvent::Motion(motion_event) if is_mouse_event(motion_event) => {
+                self.process_mouse_event(motion_event, pixels_per_point, receiver)
+            }
 
-            InputEvent::MotionEvent(motion_event) => {
-                log::info!("processing motion event: {:?}", motion_event.action());
-                match motion_event.action() {
+            InputEvent::Motion(motion_event) => {
+                log::trace!("processing motion event: {:?}", motion_event.action);
+                match motion_event.action {
                     MotionAction::Scroll => {
-                        for pointer in motion_event.pointers() {
+                        for pointer in &motion_event.pointers {
                             receiver.events.push(Event::MouseWheel {
-                                delta: vec2(
-                                    pointer.axis_value(Axis::Hscroll),
-                                    pointer.axis_value(Axis::Vscroll),
-                                ) / pixels_per_point,
-                                modifiers: modifiers_from_meta_state(motion_event.meta_state()),
+                                delta: pointer.scroll / pixels_per_point,
+                                modifiers: motion_event.modifiers,
                                 unit: MouseWheelUnit::Point,
                             });
                         }
@@ -63,19 +92,18 @@ impl InputHandler {
                     }
 
                     MotionAction::Down | MotionAction::PointerDown => {
-                        for pointer in motion_event.pointers() {
-                            receiver.events.push(create_touch_event(
-                                motion_event,
-                                &pointer,
-                                TouchPhase::Start,
-                                pixels_per_point,
-                            ));
-                        }
-
-                        if motion_event.pointer_count() == 1 {
+                        self.push_touch_events(
+                            motion_event,
+                            &motion_event.pointers,
+                            TouchPhase::Start,
+                            pixels_per_point,
+                            receiver,
+                        );
+
+                        if let [pointer] = motion_event.pointers.as_slice() {
                             receiver.events.push(create_click_event(
                                 motion_event,
-                                &motion_event.pointers().next().unwrap(),
+                                pointer,
                                 true,
                                 pixels_per_point,
                             ));
@@ -85,59 +113,64 @@ impl InputHandler {
                     }
 
                     MotionAction::Up | MotionAction::PointerUp => {
-                        for pointer in motion_event.pointers() {
-                            receiver.events.push(create_touch_event(
-                                motion_event,
-                                &pointer,
-                                TouchPhase::End,
-                                pixels_per_point,
-                            ));
-                        }
-
-                        if motion_event.pointer_count() == 1 {
+                        self.push_touch_events(
+                            motion_event,
+                            &motion_event.pointers,
+                            TouchPhase::End,
+                            pixels_per_point,
+                            receiver,
+                        );
+
+                        if let [pointer] = motion_event.pointers.as_slice() {
                             receiver.events.push(create_click_event(
                                 motion_event,
-                                &motion_event.pointers().next().unwrap(),
+                                pointer,
                                 false,
                                 pixels_per_point,
                             ));
                         }
 
-                        if motion_event.pointer_count() <= 1 {
+                        if motion_event.pointers.len() <= 1 {
                             receiver.events.push(Event::PointerGone);
                         }
 
                         InputStatus::Handled
                     }
 
+                    // Android batches movement, so the samples since the
+                    // previous event come first, oldest first.
                     MotionAction::Move => {
-                        for pointer in motion_event.pointers() {
-                            receiver.events.push(create_touch_event(
+                        let samples = motion_event.history.iter().chain([&motion_event.pointers]);
+                        for pointers in samples {
+                            self.push_touch_events(
                                 motion_event,
-                                &pointer,
+                                pointers,
                                 TouchPhase::Move,
                                 pixels_per_point,
-                            ));
-                        }
-
-                        if motion_event.pointer_count() == 1 {
-                            let pointer = motion_event.pointers().next().unwrap();
-                            receiver
-                                .events
-                                .push(Event::PointerMoved(pointer_pos(&pointer, pixels_per_point)));
+                                receiver,
+                            );
+
+                            if let [pointer] = pointers.as_slice() {
+                                receiver.events.push(Event::PointerMoved(pointer_pos(
+                                    pointer,
+                                    pixels_per_point,
+                                )));
+                            }
                         }
 
                         InputStatus::Handled
                     }
 
+                    // Cancels the whole gesture, so it applies to all pointers.
                     MotionAction::Cancel => {
-                        for pointer in motion_event.pointers() {
-                            receiver.events.push(create_touch_event(
+                        for pointer in &motion_event.pointers {
+                            self.push_touch_event(
                                 motion_event,
-                                &pointer,
+                                pointer,
                                 TouchPhase::Cancel,
                                 pixels_per_point,
-                            ));
+                                receiver,
+                            );
                         }
 
                         InputStatus::Handled
@@ -148,19 +181,28 @@ impl InputHandler {
                         InputStatus::Handled
                     }
 
-                    // Mouse over
-                    MotionAction::HoverMove => {
-                        for pointer in motion_event.pointers() {
-                            if pointer.tool_type() == ToolType::Mouse {
-                                receiver.events.push(Event::MouseMoved(
-                                    vec2(
-                                        pointer.axis_value(Axis::Hscroll),
-                                        pointer.axis_value(Axis::Vscroll),
-                                    ) / pixels_per_point,
-                                ));
-                            }
+                    // A stylus or finger hovering over the screen.
+                    MotionAction::HoverEnter | MotionAction::HoverMove => {
+                        if let Some(pointer) = motion_event.pointers.get(motion_event.pointer_index)
+                        {
+                            receiver
+                                .events
+                                .push(Event::PointerMoved(pointer_pos(pointer, pixels_per_point)));
                         }
 
+                        let phase = if motion_event.action == MotionAction::HoverEnter {
+                            TouchPhase::Start
+                        } else {
+                            TouchPhase::Move
+                        };
+                        self.push_hover_samples(motion_event, phase, pixels_per_point);
+
+                        InputStatus::Handled
+                    }
+
+                    MotionAction::HoverExit => {
+                        receiver.events.push(Event::PointerGone);
+                        self.push_hover_samples(motion_event, TouchPhase::End, pixels_per_point);
                         InputStatus::Handled
                     }
 
@@ -171,23 +213,259 @@ impl InputHandler {
                 }
             }
 
-            // Some unknown event type.
-            InputEvent::TextEvent(text_event) => {
-                log::warn!("unhandled text event: {text_event:?}");
-                InputStatus::Unhandled
+            // Each state replaces the whole text, so only the latest one is
+            // needed. It is applied to the focused text field before the next
+            // pass, see `TextInputSync`.
+            InputEvent::Text(state) => {
+                self.text_input_state = Some(state.clone());
+                InputStatus::Handled
             }
 
-            unknown => {
-                log::warn!("unhandled input event: {unknown:?}");
-                InputStatus::Unhandled
+            // These may need the clipboard, so they are handled by the runner.
+            InputEvent::TextAction(_) => InputStatus::Unhandled,
+        }
+    }
+
+    /// Process an event from a mouse, which egui understands directly rather
+    /// than as touches.
+    fn process_mouse_event(
+        &mut self,
+        motion_event: &MotionEvent,
+        pixels_per_point: f32,
+        receiver: &mut RawInput,
+    ) -> InputStatus {
+        let Some(pointer) = motion_event.pointers.get(motion_event.pointer_index) else {
+            return InputStatus::Unhandled;
+        };
+        let modifiers = motion_event.modifiers;
+
+        if pointer.relative != Vec2::ZERO {
+            receiver
+                .events
+                .push(Event::MouseMoved(pointer.relative / pixels_per_point));
+        }
+
+        // A captured mouse has no position, so it stays where it was last seen.
+        let gone = matches!(
+            motion_event.action,
+            MotionAction::HoverExit | MotionAction::Cancel | MotionAction::Outside
+        );
+        if !motion_event.captured && !gone {
+            let pos = pointer_pos(pointer, pixels_per_point);
+            if self.mouse_pos != Some(pos) {
+                receiver.events.push(Event::PointerMoved(pos));
+                self.mouse_pos = Some(pos);
+            }
+        }
+
+        if motion_event.action == MotionAction::Scroll {
+            receiver.events.push(Event::MouseWheel {
+                delta: pointer.scroll / pixels_per_point,
+                modifiers,
+                unit: MouseWheelUnit::Point,
+            });
+        }
+
+        // Buttons are compared with the ones held down before, as they change
+        // with both `Down` and `ButtonPress` actions, in either order.
+        let mut buttons = motion_event.button_state;
+        match motion_event.action {
+            // Some devices, such as emulators, don't report which button is
+            // down.
+            MotionAction::Down if buttons == [false; NUM_POINTER_BUTTONS] => buttons[0] = true,
+            MotionAction::Cancel => buttons = [false; NUM_POINTER_BUTTONS],
+            _ => {}
+        }
+        let pos = self.mouse_pos.unwrap_or(Pos2::ZERO);
+        for (index, &pressed) in buttons.iter().enumerate() {
+            if pressed != self.mouse_buttons[index] {
+                receiver.events.push(Event::PointerButton {
+                    pos,
+                    button: POINTER_BUTTONS[index],
+                    pressed,
+                    modifiers,
+                });
+            }
+        }
+        self.mouse_buttons = buttons;
+
+        if gone && self.mouse_pos.take().is_some() {
+            receiver.events.push(Event::PointerGone);
+        }
+
+        InputStatus::Handled
+    }
+
+    /// Push touch events for the given pointers, with `phase` for the pointer
+    /// the action applies to. The other pointers are still down, so they are
+    /// moved.
+    fn push_touch_events(
+        &mut self,
+        motion_event: &MotionEvent,
+        pointers: &[Pointer],
+        phase: TouchPhase,
+        pixels_per_point: f32,
+        receiver: &mut RawInput,
+    ) {
+        for (index, pointer) in pointers.iter().enumerate() {
+            let phase = if index == motion_event.pointer_index {
+                phase
+            } else {
+                TouchPhase::Move
+            };
+            self.push_touch_event(motion_event, pointer, phase, pixels_per_point, receiver);
+        }
+    }
+
+    /// Push a touch event for a pointer, and keep a sample of it with the
+    /// details egui leaves out.
+    fn push_touch_event(
+        &mut self,
+        motion_event: &MotionEvent,
+        pointer: &Pointer,
+        phase: TouchPhase,
+        pixels_per_point: f32,
+        receiver: &mut RawInput,
+    ) {
+        receiver.events.push(create_touch_event(
+            motion_event,
+            pointer,
+            phase,
+            pixels_per_point,
+        ));
+        self.stylus_samples.push(create_stylus_sample(
+            pointer,
+            phase,
+            false,
+            pixels_per_point,
+        ));
+    }
+
+    /// Keep samples of pointers hovering over the screen, which egui only
+    /// sees as pointer movement.
+    fn push_hover_samples(
+        &mut self,
+        motion_event: &MotionEvent,
+        phase: TouchPhase,
+        pixels_per_point: f32,
+    ) {
+        self.stylus_samples.extend(
+            motion_event
+                .pointers
+                .iter()
+                .map(|pointer| create_stylus_sample(pointer, phase, true, pixels_per_point)),
+        );
+    }
+
+    /// Take the text input state reported since this was last called, if any.
+    pub fn take_text_input_state(&mut self) -> Option<TextInputState> {
+        self.text_input_state.take()
+    }
+
+    /// Take the stylus samples received since this was last called.
+    pub fn take_stylus_samples(&mut self) -> Vec<StylusSample> {
+        std::mem::take(&mut self.stylus_samples)
+    }
+
+    /// Set whether the A button of game controllers clicks the focused widget,
+    /// like the center of the D-pad does.
+    pub fn set_focus_navigation(&mut self, focus_navigation: bool) {
+        self.focus_navigation = focus_navigation;
+    }
+
+    /// Get the state of the game controllers, and start tracking which
+    /// buttons are pressed anew.
+    pub fn take_gamepad_states(&mut self) -> Vec<GamepadState> {
+        let states = self.gamepads.values().cloned().collect();
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.pressed.clear();
+        }
+
+        states
+    }
+
+    fn gamepad(&mut self, device_id: i32) -> &mut GamepadState {
+        self.gamepads
+            .entry(device_id)
+            .or_insert_with(|| GamepadState {
+                device_id,
+                ..Default::default()
+            })
+    }
+
+    fn set_gamepad_button(&mut self, device_id: i32, button: GamepadButton, down: bool) {
+        let gamepad = self.gamepad(device_id);
+        if !down {
+            gamepad.down.remove(&button);
+        } else if gamepad.down.insert(button) {
+            gamepad.pressed.insert(button);
+        }
+    }
+
+    fn process_joystick_event(&mut self, joystick_event: &JoystickEvent, receiver: &mut RawInput) {
+        let device_id = joystick_event.device_id;
+        let gamepad = self.gamepad(device_id);
+        gamepad.left_stick = joystick_event.left_stick;
+        gamepad.right_stick = joystick_event.right_stick;
+        gamepad.left_trigger = joystick_event.left_trigger;
+        gamepad.right_trigger = joystick_event.right_trigger;
+
+        // Controllers that report their D-pad as a hat switch don't send key
+        // events for it, so those are made up here, to move focus like any
+        // other D-pad.
+        let hat = joystick_event.hat;
+        let old_hat = self.hats.insert(device_id, hat).unwrap_or_default();
+        let directions = [
+            (GamepadButton::DpadLeft, Key::ArrowLeft, Vec2::LEFT),
+            (GamepadButton::DpadRight, Key::ArrowRight, Vec2::RIGHT),
+            (GamepadButton::DpadUp, Key::ArrowUp, Vec2::UP),
+            (GamepadButton::DpadDown, Key::ArrowDown, Vec2::DOWN),
+        ];
+        for (button, key, direction) in directions {
+            let down = hat.dot(direction) > 0.5;
+            if down != (old_hat.dot(direction) > 0.5) {
+                self.set_gamepad_button(device_id, button, down);
+                receiver.events.push(Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed: down,
+                    repeat: false,
+                    modifiers: Modifiers::NONE,
+                });
             }
         }
     }
 
     fn process_key_event(&mut self, key_event: &KeyEvent, receiver: &mut RawInput) -> InputStatus {
+        if let Some(button) = key_event.gamepad_button {
+            let down = key_event.action == KeyAction::Down;
+            self.set_gamepad_button(key_event.device_id, button, down);
+
+            match button {
+                GamepadButton::A if self.focus_navigation => {
+                    receiver.events.push(Event::Key {
+                        key: Key::Enter,
+                        physical_key: None,
+                        pressed: down,
+                        repeat: key_event.repeat_count > 0,
+                        modifiers: key_event.modifiers,
+                    });
+                    return InputStatus::Handled;
+                }
+
+                // The D-pad also moves focus, as arrow keys.
+                GamepadButton::DpadUp
+                | GamepadButton::DpadDown
+                | GamepadButton::DpadLeft
+                | GamepadButton::DpadRight => {}
+
+                _ => return InputStatus::Handled,
+            }
+        }
+
         // Special handling for back button.
-        if key_event.key_code() == Keycode::Back {
-            if key_event.action() == KeyAction::Up {
+        if key_event.key == Some(Key::BrowserBack) {
+            if key_event.action == KeyAction::Up {
                 log::info!("back button pressed");
                 receiver
                     .viewports
@@ -199,93 +477,63 @@ impl InputHandler {
             return InputStatus::Handled;
         }
 
-        let device_id = key_event.device_id();
-
-        let key_map = match self.app.device_key_character_map(device_id) {
-            Ok(key_map) => key_map,
-            Err(err) => {
-                log::warn!("failed to look up `KeyCharacterMap` for device {device_id}: {err:?}");
-                return InputStatus::Unhandled;
+        // Keys like enter and tab produce control characters, but egui
+        // expects key presses for those. Like winit, don't type anything
+        // while a shortcut such as Ctrl+C is held.
+        let modifiers = key_event.modifiers;
+        let text = key_event
+            .text
+            .filter(|c| !c.is_control() && !modifiers.ctrl && !modifiers.command);
+
+        match key_event.key {
+            Some(Key::Copy) => {
+                receiver.events.push(Event::Copy);
+                return InputStatus::Handled;
             }
-        };
-
-        let cma = match key_map.get(key_event.key_code(), key_event.meta_state()) {
-            Ok(c) => c,
-            Err(err) => {
-                log::warn!("KeyEvent: Failed to get key map character: {err:?}");
-                KeyMapChar::None
+            Some(Key::Cut) => {
+                receiver.events.push(Event::Cut);
+                return InputStatus::Handled;
             }
-        };
-
-        match cma {
-            KeyMapChar::Unicode(unicode) => {
-                if key_event.action() == KeyAction::Down {
-                    if let Some(combining_accent) = self.combining_accent.take() {
-                        if let Some(c) = key_map
-                            .get_dead_char(combining_accent, unicode)
-                            .inspect_err(|e| {
-                                log::warn!(
-                                    "KeyEvent: Failed to combine 'dead key' accent '{combining_accent}' with \
-                                    '{unicode}': {e:?}"
-                                )
-                            })
-                            .ok()
-                            .flatten()
-                        {
-                            receiver.events.push(Event::Text(c.into()));
-                            InputStatus::Handled
-                        } else {
-                            InputStatus::Unhandled
-                        }
-                    } else {
-                        receiver.events.push(Event::Text(unicode.into()));
-                        InputStatus::Handled
-                    }
-                } else {
-                    InputStatus::Handled
-                }
+            Some(key) => {
+                receiver.events.push(Event::Key {
+                    key,
+                    physical_key: key_event.physical_key,
+                    pressed: key_event.action == KeyAction::Down,
+                    repeat: key_event.repeat_count > 0,
+                    modifiers,
+                });
             }
-            KeyMapChar::CombiningAccent(combining_accent) => {
-                self.combining_accent = Some(combining_accent);
-                InputStatus::Handled
+            None if text.is_none() => {
+                log::warn!("Unknown key event: {key_event:?}");
+                return InputStatus::Unhandled;
+            }
+            None => {}
+        }
+
+        if let Some(c) = text {
+            if key_event.action == KeyAction::Down {
+                receiver.events.push(Event::Text(c.into()));
             }
-            KeyMapChar::None => match key_event.key_code() {
-                Keycode::Copy => {
-                    receiver.events.push(Event::Copy);
-                    InputStatus::Handled
-                }
-                Keycode::Cut => {
-                    receiver.events.push(Event::Cut);
-                    InputStatus::Handled
-                }
-                keycode => {
-                    if let Some(key) = super::keycodes::to_physical_key(keycode) {
-                        receiver.events.push(Event::Key {
-                            key,
-                            physical_key: None,
-                            pressed: key_event.action() == KeyAction::Down,
-                            repeat: key_event.repeat_count() > 0,
-                            modifiers: modifiers_from_meta_state(key_event.meta_state()),
-                        });
-                        InputStatus::Handled
-                    } else {
-                        log::warn!("Unknown key code: {:?}", key_event.key_code());
-                        InputStatus::Unhandled
-                    }
-                }
-            },
         }
+        InputStatus::Handled
     }
 }
 
-/// Derive keyboard modifiers from the meta state of an Android key event.
-fn modifiers_from_meta_state(meta_state: MetaState) -> Modifiers {
-    Modifiers {
-        alt: meta_state.alt_on(),
-        ctrl: meta_state.ctrl_on(),
-        shift: meta_state.shift_on(),
-        mac_cmd: false,
-        command: meta_state.meta_on(),
+/// Make arrow keys focus a widget if none has focus, as egui only moves focus
+/// with them from an already focused widget.
+pub(crate) fn focus_first_widget(ctx: &Context, raw_input: &mut RawInput) {
+    if ctx.memory(|memory| memory.focused().is_some()) {
+        return;
+    }
+
+    for event in &mut raw_input.events {
+        if let Event::Key {
+            key: key @ (Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight),
+            ..
+        } = event
+        {
+            *key = Key::Tab;
+        }
     }
 }
 
@@ -296,11 +544,30 @@ fn create_touch_event(
     pixels_per_point: f32,
 ) -> Event {
     Event::Touch {
-        device_id: TouchDeviceId(motion_event.device_id() as u64),
-        id: TouchId(pointer.pointer_id() as u64),
+        device_id: TouchDeviceId(motion_event.device_id as u64),
+        id: TouchId(pointer.id as u64),
         phase,
         pos: pointer_pos(pointer, pixels_per_point),
-        force: Some(pointer.pressure()),
+        force: Some(pointer.pressure),
+    }
+}
+
+fn create_stylus_sample(
+    pointer: &Pointer,
+    phase: TouchPhase,
+    hovering: bool,
+    pixels_per_point: f32,
+) -> StylusSample {
+    StylusSample {
+        id: TouchId(pointer.id as u64),
+        tool_type: pointer.tool_type,
+        phase,
+        hovering,
+        pos: pointer_pos(pointer, pixels_per_point),
+        pressure: pointer.pressure,
+        tilt: pointer.tilt,
+        orientation: pointer.orientation,
+        distance: pointer.distance,
     }
 }
 
@@ -312,15 +579,343 @@ fn create_click_event(
 ) -> Event {
     Event::PointerButton {
         pos: pointer_pos(pointer, pixels_per_point),
-        button: match motion_event.action_button() {
-            Button::Secondary => PointerButton::Secondary,
+        button: match motion_event.action_button {
+            Some(PointerButton::Secondary) => PointerButton::Secondary,
             _ => PointerButton::Primary,
         },
         pressed,
-        modifiers: Modifiers::NONE,
+        modifiers: motion_event.modifiers,
     }
 }
 
+/// Whether an event is from a mouse, including touchpads that move a cursor.
+fn is_mouse_event(motion_event: &MotionEvent) -> bool {
+    motion_event.captured
+        || motion_event
+            .pointers
+            .get(motion_event.pointer_index)
+            .is_some_and(|pointer| pointer.tool_type == ToolType::Mouse)
+}
+
 fn pointer_pos(pointer: &Pointer, pixels_per_point: f32) -> Pos2 {
-    pos2(pointer.x(), pointer.y()) / pixels_per_point
+    pointer.pos / pixels_per_point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        headless::LifecycleEvent,
+        internal::testing::{self, launch, mouse, shared, CopyButton, Log, TestActivity},
+    };
+    use egui::{pos2, vec2, CursorGrab, Modifiers, Vec2, ViewportCommand};
+    use std::{cell::Cell, rc::Rc};
+
+    const PIXELS_PER_POINT: f32 = 2.0;
+
+    fn finger(id: i32, x: f32, y: f32) -> Pointer {
+        Pointer {
+            id,
+            pos: pos2(x, y),
+            pressure: 1.0,
+            tool_type: ToolType::Finger,
+            tilt: 0.0,
+            orientation: 0.0,
+            distance: 0.0,
+            scroll: Vec2::ZERO,
+            relative: Vec2::ZERO,
+        }
+    }
+
+    fn motion(action: MotionAction, pointer_index: usize, pointers: &[Pointer]) -> MotionEvent {
+        MotionEvent {
+            device_id: 0,
+            action,
+            pointer_index,
+            action_button: None,
+            button_state: [false; NUM_POINTER_BUTTONS],
+            captured: false,
+            modifiers: Modifiers::NONE,
+            pointers: pointers.to_vec(),
+            history: Vec::new(),
+        }
+    }
+
+    fn batched(history: &[&[Pointer]], pointers: &[Pointer]) -> MotionEvent {
+        MotionEvent {
+            history: history.iter().map(|pointers| pointers.to_vec()).collect(),
+            ..motion(MotionAction::Move, 0, pointers)
+        }
+    }
+
+    /// An egui touch event, at a position in physical pixels.
+    fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> Event {
+        Event::Touch {
+            device_id: TouchDeviceId(0),
+            id: TouchId(id),
+            phase,
+            pos: pos2(x, y) / PIXELS_PER_POINT,
+            force: Some(1.0),
+        }
+    }
+
+    fn button(pressed: bool, x: f32, y: f32) -> Event {
+        Event::PointerButton {
+            pos: pos2(x, y) / PIXELS_PER_POINT,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    fn moved(x: f32, y: f32) -> Event {
+        Event::PointerMoved(pos2(x, y) / PIXELS_PER_POINT)
+    }
+
+    #[test]
+    fn touch_events() {
+        use MotionAction::{Cancel, Down, PointerDown, PointerUp, Up};
+        use TouchPhase::{Cancel as Canceled, End, Move, Start};
+
+        let cases = [
+            (
+                "tap",
+                vec![
+                    motion(Down, 0, &[finger(0, 10.0, 20.0)]),
+                    motion(Up, 0, &[finger(0, 10.0, 20.0)]),
+                ],
+                vec![
+                    touch(0, Start, 10.0, 20.0),
+                    button(true, 10.0, 20.0),
+                    touch(0, End, 10.0, 20.0),
+                    button(false, 10.0, 20.0),
+                    Event::PointerGone,
+                ],
+            ),
+            (
+                "second finger down",
+                vec![motion(
+                    PointerDown,
+                    1,
+                    &[finger(0, 10.0, 20.0), finger(1, 30.0, 40.0)],
+                )],
+                vec![touch(0, Move, 10.0, 20.0), touch(1, Start, 30.0, 40.0)],
+            ),
+            (
+                "first finger up",
+                vec![
+                    motion(
+                        PointerUp,
+                        0,
+                        &[finger(0, 10.0, 20.0), finger(1, 30.0, 40.0)],
+                    ),
+                    motion(Up, 0, &[finger(1, 30.0, 40.0)]),
+                ],
+                vec![
+                    touch(0, End, 10.0, 20.0),
+                    touch(1, Move, 30.0, 40.0),
+                    touch(1, End, 30.0, 40.0),
+                    button(false, 30.0, 40.0),
+                    Event::PointerGone,
+                ],
+            ),
+            (
+                "batched move",
+                vec![batched(
+                    &[&[finger(0, 2.0, 2.0)], &[finger(0, 4.0, 4.0)]],
+                    &[finger(0, 6.0, 6.0)],
+                )],
+                vec![
+                    touch(0, Move, 2.0, 2.0),
+                    moved(2.0, 2.0),
+                    touch(0, Move, 4.0, 4.0),
+                    moved(4.0, 4.0),
+                    touch(0, Move, 6.0, 6.0),
+                    moved(6.0, 6.0),
+                ],
+            ),
+            (
+                "batched two finger move",
+                vec![batched(
+                    &[&[finger(0, 2.0, 2.0), finger(1, 20.0, 20.0)]],
+                    &[finger(0, 4.0, 4.0), finger(1, 40.0, 40.0)],
+                )],
+                vec![
+                    touch(0, Move, 2.0, 2.0),
+                    touch(1, Move, 20.0, 20.0),
+                    touch(0, Move, 4.0, 4.0),
+                    touch(1, Move, 40.0, 40.0),
+                ],
+            ),
+            (
+                "cancel",
+                vec![motion(
+                    Cancel,
+                    0,
+                    &[finger(0, 10.0, 20.0), finger(1, 30.0, 40.0)],
+                )],
+                vec![
+                    touch(0, Canceled, 10.0, 20.0),
+                    touch(1, Canceled, 30.0, 40.0),
+                ],
+            ),
+        ];
+
+        for (name, motion_events, expected) in cases {
+            let mut handler = InputHandler::new();
+            let mut raw_input = RawInput::default();
+            for motion_event in motion_events {
+                let status = handler.process(
+                    &InputEvent::Motion(motion_event),
+                    PIXELS_PER_POINT,
+                    &mut raw_input,
+                );
+                assert_eq!(status, InputStatus::Handled, "{name}");
+            }
+
+            assert_eq!(raw_input.events, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn tap_clicks_button() {
+        let button = Rc::<CopyButton>::default();
+        let mut runner =
+            launch(TestActivity::default().with_update(shared(&button, CopyButton::show)));
+
+        let pos = button.center();
+        runner.push_input(testing::touch(MotionAction::Down, pos.x, pos.y));
+        runner.run_once();
+        runner.push_input(testing::touch(MotionAction::Up, pos.x, pos.y));
+        runner.run_once();
+
+        assert_eq!(button.clicks.get(), 1);
+    }
+
+    #[test]
+    fn mouse_hovers_and_right_clicks() {
+        use egui::PointerButton::Secondary;
+
+        let button = Rc::<CopyButton>::default();
+        let hover_pos = Rc::new(Cell::new(None));
+        let mut runner = launch(TestActivity::default().with_update({
+            let (button, hover_pos) = (button.clone(), hover_pos.clone());
+            move |ctx| {
+                button.show(ctx);
+                hover_pos.set(ctx.input(|input| input.pointer.hover_pos()));
+            }
+        }));
+        let pos = button.center();
+
+        runner.push_input(mouse(MotionAction::HoverEnter, pos.x, pos.y, &[]));
+        runner.run_once();
+        assert_eq!(hover_pos.get(), Some(pos));
+
+        // Android stops hovering while a button is held down.
+        runner.push_input(mouse(MotionAction::HoverExit, pos.x, pos.y, &[]));
+        runner.push_input(mouse(MotionAction::Down, pos.x, pos.y, &[Secondary]));
+        runner.push_input(mouse(MotionAction::ButtonPress, pos.x, pos.y, &[Secondary]));
+        runner.run_once();
+        runner.push_input(mouse(MotionAction::ButtonRelease, pos.x, pos.y, &[]));
+        runner.push_input(mouse(MotionAction::Up, pos.x, pos.y, &[]));
+        runner.run_once();
+
+        assert_eq!(button.secondary_clicks.get(), 1);
+        assert_eq!(button.clicks.get(), 0);
+
+        runner.push_input(mouse(MotionAction::HoverExit, pos.x, pos.y, &[]));
+        runner.run_once();
+        assert_eq!(hover_pos.get(), None);
+    }
+
+    #[test]
+    fn captured_mouse_reports_motion() {
+        let grab = Rc::new(Cell::new(Some(CursorGrab::Locked)));
+        let motion = Rc::new(Cell::new(Vec2::ZERO));
+        let hover_pos = Rc::new(Cell::new(None));
+        let mut runner = launch(TestActivity::default().with_update({
+            let (grab, motion, hover_pos) = (grab.clone(), motion.clone(), hover_pos.clone());
+            move |ctx| {
+                if let Some(grab) = grab.take() {
+                    ctx.send_viewport_cmd(ViewportCommand::CursorGrab(grab));
+                }
+                ctx.input(|input| {
+                    motion.set(motion.get() + input.pointer.motion().unwrap_or_default());
+                    hover_pos.set(input.pointer.hover_pos());
+                });
+            }
+        }));
+        assert!(runner.is_pointer_captured());
+
+        let InputEvent::Motion(mut event) = mouse(MotionAction::Move, 0.0, 0.0, &[]) else {
+            unreachable!()
+        };
+        event.captured = true;
+        event.pointers[0].relative = vec2(3.0, -4.0);
+        runner.push_input(InputEvent::Motion(event));
+        runner.run_once();
+
+        assert_eq!(motion.get(), vec2(3.0, -4.0));
+        assert_eq!(hover_pos.get(), None);
+
+        grab.set(Some(CursorGrab::None));
+        runner.push_event(LifecycleEvent::RedrawNeeded);
+        runner.run_once();
+        assert!(!runner.is_pointer_captured());
+    }
+
+    #[test]
+    fn shortcut_sends_key_without_text() {
+        let events = Log::default();
+        let mut runner = launch(TestActivity::default().with_update(shared(
+            &events,
+            |events, ctx| {
+                ctx.input(|input| {
+                    let typed = input.raw.events.iter().filter(|event| {
+                        matches!(
+                            event,
+                            egui::Event::Key { pressed: true, .. } | egui::Event::Text(_)
+                        )
+                    });
+                    events.borrow_mut().extend(typed.cloned());
+                })
+            },
+        )));
+
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::NONE
+        };
+        for modifiers in [ctrl, Modifiers::NONE] {
+            for action in [KeyAction::Down, KeyAction::Up] {
+                runner.push_input(InputEvent::Key(KeyEvent {
+                    device_id: 0,
+                    action,
+                    key: Some(Key::C),
+                    physical_key: Some(Key::C),
+                    text: Some('c'),
+                    gamepad_button: None,
+                    repeat_count: 0,
+                    modifiers,
+                }));
+            }
+            runner.run_once();
+        }
+
+        let key = |modifiers| egui::Event::Key {
+            key: Key::C,
+            physical_key: Some(Key::C),
+            pressed: true,
+            repeat: false,
+            modifiers,
+        };
+        assert_eq!(
+            *events.borrow(),
+            [
+                key(ctrl),
+                key(Modifiers::NONE),
+                egui::Event::Text("c".into())
+            ]
+        );
+    }
 }
@@ -1,43 +1,38 @@
+use crate::GamepadButton;
 use android_activity::input::Keycode;
 use egui::Key;
 use std::sync::OnceLock;
 
 macro_rules! populate_key_map {
-    ($vec:expr, {}) => {
-        $vec
-    };
-
-    ($vec:expr, {
-        $ident:ident,
-        $($rest:tt)*
-    }) => {
-        populate_key_map!($vec, {
-            $ident => $ident,
-            $($rest)*
-        })
-    };
-
     ($vec:expr, {
-        $from:ident => $to:ident,
-        $($rest:tt)*
+        $($from:ident $(=> $to:ident)?),* $(,)?
     }) => {{
         let mut vec = $vec;
-        let index = u32::from(Keycode::$from) as usize;
-        let value = Key::$to;
-
-        if let Some(slot) = vec.get_mut(index) {
-            *slot = Some(value);
-        } else {
-            vec.resize(index, None);
-            vec.push(Some(value));
-        }
 
-        populate_key_map!(vec, {
-            $($rest)*
-        })
+        $(
+            let index = u32::from(Keycode::$from) as usize;
+
+            if index >= vec.len() {
+                vec.resize(index + 1, None);
+            }
+            vec[index] = Some(mapped_key!($from $(=> $to)?));
+        )*
+
+        vec
     }};
 }
 
+/// The key a key code is mapped to, which has the same name unless given.
+macro_rules! mapped_key {
+    ($from:ident) => {
+        Key::$from
+    };
+
+    ($from:ident => $to:ident) => {
+        Key::$to
+    };
+}
+
 // https://github.com/rust-windowing/winit/blob/master/src/platform_impl/android/keycodes.rs
 pub(crate) fn to_physical_key(keycode: Keycode) -> Option<Key> {
     static PHYSICAL_KEY_MAP: OnceLock<Vec<Option<Key>>> = OnceLock::new();
@@ -45,8 +40,45 @@ pub(crate) fn to_physical_key(keycode: Keycode) -> Option<Key> {
     let map = PHYSICAL_KEY_MAP.get_or_init(|| {
         populate_key_map!(vec![], {
             A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+            Keycode0 => Num0,
+            Keycode1 => Num1,
+            Keycode2 => Num2,
+            Keycode3 => Num3,
+            Keycode4 => Num4,
+            Keycode5 => Num5,
+            Keycode6 => Num6,
+            Keycode7 => Num7,
+            Keycode8 => Num8,
+            Keycode9 => Num9,
             F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
             Tab,
+            Enter,
+            Space,
+            Escape,
+            Del => Backspace,
+            ForwardDel => Delete,
+            Insert,
+            MoveHome => Home,
+            MoveEnd => End,
+            PageUp,
+            PageDown,
+            DpadUp => ArrowUp,
+            DpadDown => ArrowDown,
+            DpadLeft => ArrowLeft,
+            DpadRight => ArrowRight,
+            Comma,
+            Period,
+            Minus,
+            Equals,
+            Plus,
+            Semicolon,
+            Slash,
+            Backslash,
+            Apostrophe => Quote,
+            Grave => Backtick,
+            LeftBracket => OpenBracket,
+            RightBracket => CloseBracket,
+            NumpadEnter => Enter,
             Numpad0 => Num0,
             Numpad1 => Num1,
             Numpad2 => Num2,
@@ -57,26 +89,166 @@ pub(crate) fn to_physical_key(keycode: Keycode) -> Option<Key> {
             Numpad7 => Num7,
             Numpad8 => Num8,
             Numpad9 => Num9,
+            NumpadAdd => Plus,
             NumpadSubtract => Minus,
+            NumpadDivide => Slash,
+            NumpadDot => Period,
+            NumpadComma => Comma,
             NumpadEquals => Equals,
-            DpadUp => ArrowUp,
-            DpadDown => ArrowDown,
-            DpadLeft => ArrowLeft,
-            DpadRight => ArrowRight,
-            Del => Backspace,
-            ForwardDel => Delete,
+            Copy,
+            Cut,
+            Paste,
+            // egui has no media keys, so only the back key is mapped.
+            Back => BrowserBack,
         })
     });
 
     map.get(u32::from(keycode) as usize).cloned().flatten()
 }
 
+/// Get the game controller button a key is, for key events from a game
+/// controller.
+pub(crate) fn to_gamepad_button(keycode: Keycode) -> Option<GamepadButton> {
+    Some(match keycode {
+        Keycode::ButtonA => GamepadButton::A,
+        Keycode::ButtonB => GamepadButton::B,
+        Keycode::ButtonX => GamepadButton::X,
+        Keycode::ButtonY => GamepadButton::Y,
+        Keycode::ButtonL1 => GamepadButton::L1,
+        Keycode::ButtonR1 => GamepadButton::R1,
+        Keycode::ButtonL2 => GamepadButton::L2,
+        Keycode::ButtonR2 => GamepadButton::R2,
+        Keycode::ButtonThumbl => GamepadButton::LeftThumb,
+        Keycode::ButtonThumbr => GamepadButton::RightThumb,
+        Keycode::ButtonStart => GamepadButton::Start,
+        Keycode::ButtonSelect => GamepadButton::Select,
+        Keycode::ButtonMode => GamepadButton::Mode,
+        Keycode::DpadUp => GamepadButton::DpadUp,
+        Keycode::DpadDown => GamepadButton::DpadDown,
+        Keycode::DpadLeft => GamepadButton::DpadLeft,
+        Keycode::DpadRight => GamepadButton::DpadRight,
+        _ => return None,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn physical_key() {
-        assert_eq!(to_physical_key(Keycode::R), Some(Key::R));
+    fn physical_keys() {
+        let keys = [
+            (Keycode::R, Key::R),
+            (Keycode::Keycode0, Key::Num0),
+            (Keycode::Keycode1, Key::Num1),
+            (Keycode::Keycode9, Key::Num9),
+            (Keycode::F12, Key::F12),
+            (Keycode::Space, Key::Space),
+            (Keycode::Escape, Key::Escape),
+            (Keycode::Del, Key::Backspace),
+            (Keycode::ForwardDel, Key::Delete),
+            (Keycode::Insert, Key::Insert),
+            (Keycode::MoveHome, Key::Home),
+            (Keycode::MoveEnd, Key::End),
+            (Keycode::PageUp, Key::PageUp),
+            (Keycode::PageDown, Key::PageDown),
+            (Keycode::DpadUp, Key::ArrowUp),
+            (Keycode::DpadRight, Key::ArrowRight),
+            (Keycode::Comma, Key::Comma),
+            (Keycode::Period, Key::Period),
+            (Keycode::Minus, Key::Minus),
+            (Keycode::Equals, Key::Equals),
+            (Keycode::Plus, Key::Plus),
+            (Keycode::Semicolon, Key::Semicolon),
+            (Keycode::Slash, Key::Slash),
+            (Keycode::Backslash, Key::Backslash),
+            (Keycode::Apostrophe, Key::Quote),
+            (Keycode::Grave, Key::Backtick),
+            (Keycode::LeftBracket, Key::OpenBracket),
+            (Keycode::RightBracket, Key::CloseBracket),
+            (Keycode::NumpadEnter, Key::Enter),
+            (Keycode::Numpad5, Key::Num5),
+            (Keycode::NumpadAdd, Key::Plus),
+            (Keycode::NumpadSubtract, Key::Minus),
+            (Keycode::NumpadDivide, Key::Slash),
+            (Keycode::NumpadDot, Key::Period),
+            (Keycode::NumpadComma, Key::Comma),
+            (Keycode::NumpadEquals, Key::Equals),
+            (Keycode::Copy, Key::Copy),
+            (Keycode::Cut, Key::Cut),
+            (Keycode::Paste, Key::Paste),
+            (Keycode::Back, Key::BrowserBack),
+        ];
+
+        for (keycode, key) in keys {
+            assert_eq!(to_physical_key(keycode), Some(key), "{keycode:?}");
+        }
+    }
+
+    #[test]
+    fn unmapped_keys() {
+        let keycodes = [
+            Keycode::Unknown,
+            Keycode::ShiftLeft,
+            Keycode::CtrlLeft,
+            Keycode::Star,
+            Keycode::Pound,
+            Keycode::NumpadMultiply,
+            Keycode::VolumeUp,
+            Keycode::MediaPlayPause,
+            Keycode::DpadCenter,
+            Keycode::ButtonA,
+        ];
+
+        for keycode in keycodes {
+            assert_eq!(to_physical_key(keycode), None, "{keycode:?}");
+        }
+    }
+
+    /// Every egui key is mapped from some Android key code, unless Android
+    /// keyboards have no key for it.
+    #[test]
+    fn all_keys_are_mapped() {
+        let mapped: Vec<Key> = (0..1000_u32)
+            .filter_map(|keycode| to_physical_key(Keycode::from(keycode)))
+            .collect();
+
+        // These are typed with shift, and function keys only go up to F12.
+        let shifted = [
+            Key::Colon,
+            Key::Pipe,
+            Key::Questionmark,
+            Key::Exclamationmark,
+            Key::OpenCurlyBracket,
+            Key::CloseCurlyBracket,
+        ];
+        let beyond_f12 = |key: Key| {
+            key.name()
+                .strip_prefix('F')
+                .and_then(|n| n.parse::<u32>().ok())
+                .is_some_and(|n| n > 12)
+        };
+
+        let unmapped: Vec<Key> = Key::ALL
+            .iter()
+            .copied()
+            .filter(|key| !mapped.contains(key))
+            .collect();
+        let expected: Vec<Key> = Key::ALL
+            .iter()
+            .copied()
+            .filter(|&key| shifted.contains(&key) || beyond_f12(key))
+            .collect();
+        assert_eq!(unmapped, expected);
+    }
+
+    #[test]
+    fn gamepad_button() {
+        assert_eq!(to_gamepad_button(Keycode::ButtonA), Some(GamepadButton::A));
+        assert_eq!(
+            to_gamepad_button(Keycode::DpadLeft),
+            Some(GamepadButton::DpadLeft)
+        );
+        assert_eq!(to_gamepad_button(Keycode::A), None);
     }
 }
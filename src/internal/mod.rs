@@ -3,14 +3,28 @@
 //! in here is actually exposed to app developers, but is run automatically
 //! behind the scenes.
 
+#[cfg(target_os = "android")]
 pub(crate) mod bindings;
+#[cfg(target_os = "android")]
 pub(crate) mod logging;
+pub(crate) mod platform;
 pub(crate) mod runner;
 
+#[cfg(all(target_os = "android", not(feature = "wgpu")))]
 mod graphics;
+#[cfg(target_os = "android")]
 mod ime;
 mod input;
+#[cfg(target_os = "android")]
 mod keycodes;
-mod plugins;
+pub(crate) mod plugins;
 mod state;
-mod widgets;
+mod storage;
+#[cfg(test)]
+pub(crate) mod testing;
+mod text_input;
+#[cfg(any(target_os = "android", test))]
+mod textures;
+#[cfg(all(target_os = "android", feature = "wgpu"))]
+mod wgpu_graphics;
+pub(crate) mod widgets;
@@ -0,0 +1,622 @@
+//! Platform implementation for a real Android device.
+
+#[cfg(feature = "game-activity")]
+use super::input::TextSpan;
+use super::{
+    input::{
+        InputEvent, InputStatus, JoystickEvent, KeyAction, KeyEvent, MotionAction, MotionEvent,
+        Pointer, TextActionMenu, TextInputState,
+    },
+    ClipboardContent, Clock, Error, Platform, PlatformConfig, PlatformEvent, Waker,
+};
+#[cfg(feature = "game-activity")]
+use crate::internal::bindings::editor_info::set_editor_info;
+#[cfg(not(feature = "wgpu"))]
+use crate::internal::graphics::GraphicsContext;
+#[cfg(feature = "wgpu")]
+use crate::internal::wgpu_graphics::GraphicsContext;
+use crate::internal::{
+    bindings::{
+        clipboard::{clipboard_timestamp, get_clipboard, set_clipboard_image},
+        open_url::open_url,
+        pointer_capture::set_pointer_capture,
+        share::{self, share_target_chosen},
+        system_bars::set_system_bars,
+        text_action_mode::{self, set_text_action_menu},
+        window_insets::window_insets,
+    },
+    ime::show_hide_keyboard,
+    keycodes::{to_gamepad_button, to_physical_key},
+};
+use crate::{
+    GraphicsError, GraphicsOptions, OpenUrlError, ShareContent, SystemBars, TextInputOptions,
+    ToolType, WindowInsets,
+};
+use android_activity::{
+    input::{
+        self as android_input, Axis, Button, KeyCharacterMap, KeyMapChar, Keycode, MetaState,
+        Source,
+    },
+    AndroidApp, MainEvent, PollEvent, WindowManagerFlags,
+};
+use egui::{
+    epaint::MarginF32, pos2, vec2, Color32, ColorImage, Key, Modifiers, PointerButton, Rect, Theme,
+};
+use ndk::configuration::UiModeNight;
+use std::{
+    cell::Cell,
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone)]
+pub(crate) struct AndroidPlatform {
+    app: AndroidApp,
+
+    /// A dead key that was pressed and should be combined with the next
+    /// character typed.
+    combining_accent: Rc<Cell<Option<char>>>,
+}
+
+impl AndroidPlatform {
+    pub(crate) fn new(app: AndroidApp) -> Self {
+        Self {
+            app,
+            combining_accent: Rc::new(Cell::new(None)),
+        }
+    }
+
+    fn convert_key_event(&self, key_event: &android_input::KeyEvent) -> Option<KeyEvent> {
+        let action = match key_event.action() {
+            android_input::KeyAction::Down => KeyAction::Down,
+            android_input::KeyAction::Up => KeyAction::Up,
+            _ => return None,
+        };
+
+        let key_map = self.key_character_map(key_event.device_id());
+
+        // Android's key codes are for positions on the keyboard, as the
+        // keyboard layout only changes which characters they type. That
+        // character decides which key it is, so that Ctrl+Z undoes on an
+        // AZERTY keyboard too.
+        let physical_key = to_physical_key(key_event.key_code());
+        let key = match key_event.key_code() {
+            // Android treats the center of the D-pad, such as the OK button
+            // on a TV remote, as a confirm key like Enter.
+            Keycode::DpadCenter => Some(Key::Enter),
+            key_code => key_map
+                .as_ref()
+                .and_then(|key_map| layout_key(key_map, key_code))
+                .or(physical_key),
+        };
+
+        // D-pads are also on TV remotes, which aren't game controllers.
+        let gamepad_button = is_gamepad(key_event.source())
+            .then(|| to_gamepad_button(key_event.key_code()))
+            .flatten();
+
+        Some(KeyEvent {
+            device_id: key_event.device_id(),
+            action,
+            key,
+            physical_key,
+            text: key_map
+                .as_ref()
+                .and_then(|key_map| self.key_event_text(key_event, key_map)),
+            gamepad_button,
+            repeat_count: key_event.repeat_count().max(0) as u32,
+            modifiers: modifiers_from_meta_state(key_event.meta_state()),
+        })
+    }
+
+    /// Get the keyboard layout of an input device.
+    fn key_character_map(&self, device_id: i32) -> Option<KeyCharacterMap> {
+        self.app
+            .device_key_character_map(device_id)
+            .inspect_err(|err| {
+                log::warn!("failed to look up `KeyCharacterMap` for device {device_id}: {err:?}")
+            })
+            .ok()
+    }
+
+    /// Look up the character produced by a key event using the keyboard layout
+    /// of the device that produced it.
+    fn key_event_text(
+        &self,
+        key_event: &android_input::KeyEvent,
+        key_map: &KeyCharacterMap,
+    ) -> Option<char> {
+        if key_event.key_code() == Keycode::Back {
+            return None;
+        }
+
+        let cma = match key_map.get(key_event.key_code(), key_event.meta_state()) {
+            Ok(c) => c,
+            Err(err) => {
+                log::warn!("KeyEvent: Failed to get key map character: {err:?}");
+                KeyMapChar::None
+            }
+        };
+
+        match cma {
+            KeyMapChar::Unicode(unicode) => {
+                if key_event.action() != android_input::KeyAction::Down {
+                    return Some(unicode);
+                }
+
+                match self.combining_accent.take() {
+                    Some(combining_accent) => key_map
+                        .get_dead_char(combining_accent, unicode)
+                        .inspect_err(|e| {
+                            log::warn!(
+                                "KeyEvent: Failed to combine 'dead key' accent '{combining_accent}' with \
+                                '{unicode}': {e:?}"
+                            )
+                        })
+                        .ok()
+                        .flatten(),
+                    None => Some(unicode),
+                }
+            }
+            KeyMapChar::CombiningAccent(combining_accent) => {
+                self.combining_accent.set(Some(combining_accent));
+                None
+            }
+            KeyMapChar::None => None,
+        }
+    }
+}
+
+impl Platform for AndroidPlatform {
+    type Graphics = GraphicsContext;
+
+    fn create_graphics(&self, options: GraphicsOptions) -> Self::Graphics {
+        GraphicsContext::new(options)
+    }
+
+    fn attach_window(&self, graphics: &mut Self::Graphics) -> Result<(), GraphicsError> {
+        match self.app.native_window() {
+            Some(native_window) => graphics.attach_window(native_window),
+            None => Ok(()),
+        }
+    }
+
+    fn create_waker(&self) -> Waker {
+        let waker = self.app.create_waker();
+        Box::new(move || waker.wake())
+    }
+
+    fn create_clock(&self) -> Clock {
+        Arc::new(Instant::now)
+    }
+
+    fn poll_events(&self, timeout: Option<Duration>, mut callback: impl FnMut(PlatformEvent<'_>)) {
+        self.app.poll_events(timeout, |event| match event {
+            // Picking an item from the text selection menu wakes us up, and
+            // the picked item is read like any other input.
+            PollEvent::Wake if text_action_mode::has_text_actions() => {
+                callback(PlatformEvent::InputAvailable)
+            }
+            PollEvent::Wake => callback(PlatformEvent::Wake),
+            PollEvent::Timeout => callback(PlatformEvent::Timeout),
+            PollEvent::Main(main_event) => match main_event {
+                MainEvent::Start => callback(PlatformEvent::Start),
+                MainEvent::Resume { loader, .. } => callback(PlatformEvent::Resume {
+                    saved_state: loader.load(),
+                }),
+                MainEvent::SaveState { saver, .. } => {
+                    let mut saved_state = None;
+
+                    callback(PlatformEvent::SaveState {
+                        saved_state: &mut saved_state,
+                    });
+
+                    if let Some(saved_state) = saved_state {
+                        saver.store(&saved_state);
+                    }
+                }
+                MainEvent::Pause => callback(PlatformEvent::Pause),
+                MainEvent::Stop => callback(PlatformEvent::Stop),
+                MainEvent::Destroy => callback(PlatformEvent::Destroy),
+                MainEvent::InitWindow { .. } => callback(PlatformEvent::InitWindow),
+                MainEvent::TerminateWindow { .. } => callback(PlatformEvent::TerminateWindow),
+                MainEvent::WindowResized { .. } => callback(PlatformEvent::WindowResized),
+                MainEvent::RedrawNeeded { .. } => callback(PlatformEvent::RedrawNeeded),
+                MainEvent::ContentRectChanged { .. } => callback(PlatformEvent::ContentRectChanged),
+                MainEvent::GainedFocus => callback(PlatformEvent::GainedFocus),
+                MainEvent::LostFocus => callback(PlatformEvent::LostFocus),
+                MainEvent::ConfigChanged { .. } => callback(PlatformEvent::ConfigChanged),
+                MainEvent::LowMemory => callback(PlatformEvent::LowMemory),
+                MainEvent::InsetsChanged { .. } => callback(PlatformEvent::InsetsChanged),
+                MainEvent::InputAvailable => callback(PlatformEvent::InputAvailable),
+                main_event => log::warn!("unknown main event: {main_event:?}"),
+            },
+            _ => {}
+        });
+    }
+
+    fn input_events(&self, mut callback: impl FnMut(&InputEvent) -> InputStatus) {
+        for action in text_action_mode::take_text_actions() {
+            callback(&InputEvent::TextAction(action));
+        }
+
+        match self.app.input_events_iter() {
+            Ok(mut iter) => loop {
+                let read_input = iter.next(|event| {
+                    log::trace!("processing input event: {event:?}");
+
+                    let event = match event {
+                        android_input::InputEvent::KeyEvent(key_event) => {
+                            self.convert_key_event(key_event).map(InputEvent::Key)
+                        }
+                        android_input::InputEvent::MotionEvent(motion_event)
+                            if is_joystick(motion_event.source()) =>
+                        {
+                            Some(InputEvent::Joystick(convert_joystick_event(motion_event)))
+                        }
+                        android_input::InputEvent::MotionEvent(motion_event) => {
+                            convert_motion_event(motion_event).map(InputEvent::Motion)
+                        }
+                        // Only a `GameActivity` has a soft keyboard that edits
+                        // its own copy of the text.
+                        #[cfg(feature = "game-activity")]
+                        android_input::InputEvent::TextEvent(state) => {
+                            Some(InputEvent::Text(convert_text_input_state(state)))
+                        }
+                        unknown => {
+                            log::warn!("unhandled input event: {unknown:?}");
+                            None
+                        }
+                    };
+
+                    match event.as_ref().map(&mut callback) {
+                        Some(InputStatus::Handled) => android_activity::InputStatus::Handled,
+                        _ => android_activity::InputStatus::Unhandled,
+                    }
+                });
+
+                if !read_input {
+                    break;
+                }
+            },
+            Err(err) => {
+                log::error!("failed to get input events iterator: {err:?}");
+            }
+        }
+    }
+
+    fn config(&self) -> PlatformConfig {
+        let config = self.app.config();
+
+        PlatformConfig {
+            density: config.density(),
+            theme: match config.ui_mode_night() {
+                UiModeNight::No => Some(Theme::Light),
+                UiModeNight::Yes => Some(Theme::Dark),
+                _ => None,
+            },
+        }
+    }
+
+    fn content_rect(&self) -> Rect {
+        let content_rect = self.app.content_rect();
+
+        Rect::from_two_pos(
+            pos2(content_rect.left as _, content_rect.top as _),
+            pos2(content_rect.right as _, content_rect.bottom as _),
+        )
+    }
+
+    fn window_insets(&self) -> WindowInsets {
+        match window_insets(&self.app) {
+            Ok(Some(insets)) => return insets,
+            Ok(None) => {}
+            Err(e) => log::warn!("failed to get window insets: {e}"),
+        }
+
+        // Without real insets, assume whatever is outside of the content rect
+        // is covered by system bars.
+        let Some(window) = self.app.native_window() else {
+            return WindowInsets::default();
+        };
+        let content_rect = self.content_rect();
+
+        WindowInsets {
+            system_bars: MarginF32 {
+                left: content_rect.left().max(0.0),
+                right: (window.width() as f32 - content_rect.right()).max(0.0),
+                top: content_rect.top().max(0.0),
+                bottom: (window.height() as f32 - content_rect.bottom()).max(0.0),
+            },
+            ..WindowInsets::default()
+        }
+    }
+
+    fn show_soft_input(&self) {
+        show_hide_keyboard(&self.app, true);
+    }
+
+    fn hide_soft_input(&self) {
+        self.app.hide_soft_input(false);
+    }
+
+    #[cfg(feature = "game-activity")]
+    fn set_text_input_state(&self, state: TextInputState) {
+        let utf16_span = |span: TextSpan| android_input::TextSpan {
+            start: utf16_index(&state.text, span.start),
+            end: utf16_index(&state.text, span.end),
+        };
+
+        self.app
+            .set_text_input_state(android_input::TextInputState {
+                selection: utf16_span(state.selection),
+                compose_region: state.compose_region.map(utf16_span),
+                text: state.text,
+            });
+    }
+
+    #[cfg(not(feature = "game-activity"))]
+    fn set_text_input_state(&self, _state: TextInputState) {
+        // A `NativeActivity` soft keyboard only sends key presses, and has no
+        // text to keep in sync.
+    }
+
+    #[cfg(feature = "game-activity")]
+    fn set_text_input_options(&self, options: TextInputOptions) {
+        if let Err(e) = set_editor_info(&self.app, options) {
+            log::warn!("failed to set soft keyboard options: {e}");
+        }
+    }
+
+    #[cfg(not(feature = "game-activity"))]
+    fn set_text_input_options(&self, _options: TextInputOptions) {
+        // A `NativeActivity` always opens the same soft keyboard.
+    }
+
+    fn set_text_action_menu(&self, menu: Option<TextActionMenu>) -> Result<(), Error> {
+        set_text_action_menu(&self.app, menu.as_ref())
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        if fullscreen {
+            self.app
+                .set_window_flags(WindowManagerFlags::FULLSCREEN, WindowManagerFlags::empty());
+        } else {
+            self.app
+                .set_window_flags(WindowManagerFlags::empty(), WindowManagerFlags::FULLSCREEN);
+        }
+    }
+
+    fn set_system_bars(&self, system_bars: SystemBars, theme: Theme) {
+        if let Err(e) = set_system_bars(&self.app, system_bars, theme) {
+            log::error!("failed to set system bars: {e}");
+        }
+    }
+
+    fn set_focusable(&self, focusable: bool) {
+        if focusable {
+            self.app.set_window_flags(
+                WindowManagerFlags::empty(),
+                WindowManagerFlags::NOT_FOCUSABLE,
+            );
+        } else {
+            self.app.set_window_flags(
+                WindowManagerFlags::NOT_FOCUSABLE,
+                WindowManagerFlags::empty(),
+            );
+        }
+    }
+
+    fn set_pointer_capture(&self, captured: bool) {
+        if let Err(e) = set_pointer_capture(&self.app, captured) {
+            log::warn!("failed to set pointer capture: {e}");
+        }
+    }
+
+    fn clipboard(&self) -> Result<ClipboardContent, Error> {
+        get_clipboard(&self.app)
+    }
+
+    fn set_clipboard_text(&self, text: String) -> Result<(), Error> {
+        Ok(android_clipboard::set_text(text)?)
+    }
+
+    fn set_clipboard_image(&self, image: &ColorImage) -> Result<(), Error> {
+        set_clipboard_image(&self.app, image)
+    }
+
+    fn clipboard_timestamp(&self) -> Option<i64> {
+        clipboard_timestamp(&self.app).unwrap_or_else(|e| {
+            log::warn!("failed to get clipboard timestamp: {e}");
+            None
+        })
+    }
+
+    fn open_url(&self, url: &str, custom_tab: Option<Color32>) -> Result<(), OpenUrlError> {
+        open_url(&self.app, url, custom_tab)
+    }
+
+    fn share(&self, content: &ShareContent) -> Result<(), Error> {
+        share::share(&self.app, content)
+    }
+
+    fn share_target_chosen(&self) -> Option<bool> {
+        share_target_chosen()
+    }
+
+    fn internal_data_path(&self) -> Option<PathBuf> {
+        self.app.internal_data_path()
+    }
+}
+
+fn convert_motion_event(motion_event: &android_input::MotionEvent) -> Option<MotionEvent> {
+    let action = match motion_event.action() {
+        android_input::MotionAction::Down => MotionAction::Down,
+        android_input::MotionAction::Up => MotionAction::Up,
+        android_input::MotionAction::Move => MotionAction::Move,
+        android_input::MotionAction::Cancel => MotionAction::Cancel,
+        android_input::MotionAction::Outside => MotionAction::Outside,
+        android_input::MotionAction::PointerDown => MotionAction::PointerDown,
+        android_input::MotionAction::PointerUp => MotionAction::PointerUp,
+        android_input::MotionAction::HoverEnter => MotionAction::HoverEnter,
+        android_input::MotionAction::HoverMove => MotionAction::HoverMove,
+        android_input::MotionAction::HoverExit => MotionAction::HoverExit,
+        android_input::MotionAction::Scroll => MotionAction::Scroll,
+        android_input::MotionAction::ButtonPress => MotionAction::ButtonPress,
+        android_input::MotionAction::ButtonRelease => MotionAction::ButtonRelease,
+        e => {
+            log::warn!("unknown motion event: {e:?}");
+            return None;
+        }
+    };
+
+    let button_state = motion_event.button_state();
+
+    // Captured mouse events report movement on the X and Y axes, instead of a
+    // position.
+    let captured = motion_event.source() == Source::MouseRelative;
+
+    Some(MotionEvent {
+        device_id: motion_event.device_id(),
+        action,
+        pointer_index: motion_event.pointer_index(),
+        action_button: match motion_event.action_button() {
+            Button::Primary => Some(PointerButton::Primary),
+            Button::Secondary => Some(PointerButton::Secondary),
+            Button::Tertiary => Some(PointerButton::Middle),
+            Button::Back => Some(PointerButton::Extra1),
+            Button::Forward => Some(PointerButton::Extra2),
+            _ => None,
+        },
+        button_state: [
+            button_state.primary(),
+            button_state.secondary(),
+            button_state.teriary(),
+            button_state.back(),
+            button_state.forward(),
+        ],
+        captured,
+        modifiers: modifiers_from_meta_state(motion_event.meta_state()),
+        pointers: motion_event
+            .pointers()
+            .map(|pointer| Pointer {
+                id: pointer.pointer_id(),
+                pos: pos2(pointer.x(), pointer.y()),
+                pressure: pointer.pressure(),
+                tool_type: match pointer.tool_type() {
+                    android_input::ToolType::Finger => ToolType::Finger,
+                    android_input::ToolType::Stylus => ToolType::Stylus,
+                    android_input::ToolType::Mouse => ToolType::Mouse,
+                    android_input::ToolType::Eraser => ToolType::Eraser,
+                    android_input::ToolType::Palm => ToolType::Palm,
+                    _ => ToolType::Unknown,
+                },
+                tilt: pointer.axis_value(Axis::Tilt),
+                orientation: pointer.axis_value(Axis::Orientation),
+                distance: pointer.axis_value(Axis::Distance),
+                scroll: vec2(
+                    pointer.axis_value(Axis::Hscroll),
+                    pointer.axis_value(Axis::Vscroll),
+                ),
+                relative: if captured {
+                    vec2(pointer.x(), pointer.y())
+                } else {
+                    vec2(
+                        pointer.axis_value(Axis::RelativeX),
+                        pointer.axis_value(Axis::RelativeY),
+                    )
+                },
+            })
+            .collect(),
+
+        // `android-activity` doesn't expose the batched samples yet, as it
+        // can't get them from `GameActivity`.
+        history: Vec::new(),
+    })
+}
+
+fn convert_joystick_event(motion_event: &android_input::MotionEvent) -> JoystickEvent {
+    let axis = |axis| motion_event.pointer_at_index(0).axis_value(axis);
+
+    JoystickEvent {
+        device_id: motion_event.device_id(),
+        left_stick: vec2(axis(Axis::X), axis(Axis::Y)),
+        right_stick: vec2(axis(Axis::Z), axis(Axis::Rz)),
+        // Controllers report their triggers as either of these.
+        left_trigger: axis(Axis::Ltrigger).max(axis(Axis::Brake)),
+        right_trigger: axis(Axis::Rtrigger).max(axis(Axis::Gas)),
+        hat: vec2(axis(Axis::HatX), axis(Axis::HatY)),
+    }
+}
+
+/// Whether an input source includes a game controller. Devices report a
+/// combination of sources, such as a keyboard and a game controller.
+fn is_gamepad(source: Source) -> bool {
+    has_source(source, Source::Gamepad) || is_joystick(source)
+}
+
+fn is_joystick(source: Source) -> bool {
+    has_source(source, Source::Joystick)
+}
+
+fn has_source(source: Source, flags: Source) -> bool {
+    let flags = u32::from(flags);
+    u32::from(source) & flags == flags
+}
+
+#[cfg(feature = "game-activity")]
+fn convert_text_input_state(state: &android_input::TextInputState) -> TextInputState {
+    let char_span = |span: android_input::TextSpan| TextSpan {
+        start: char_index(&state.text, span.start),
+        end: char_index(&state.text, span.end),
+    };
+
+    TextInputState {
+        text: state.text.clone(),
+        selection: char_span(state.selection),
+        compose_region: state.compose_region.map(char_span),
+    }
+}
+
+/// Convert an index into `text` in UTF-16 code units, which is how Java
+/// indexes strings, into an index in characters.
+#[cfg(feature = "game-activity")]
+fn char_index(text: &str, utf16_index: usize) -> usize {
+    let mut units = 0;
+
+    text.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= utf16_index
+        })
+        .count()
+}
+
+/// Convert an index into `text` in characters into an index in UTF-16 code
+/// units.
+#[cfg(feature = "game-activity")]
+fn utf16_index(text: &str, char_index: usize) -> usize {
+    text.chars().take(char_index).map(char::len_utf16).sum()
+}
+
+/// Get the key that types a character in a keyboard layout without any
+/// modifiers held, if egui has a key for that character.
+fn layout_key(key_map: &KeyCharacterMap, key_code: Keycode) -> Option<Key> {
+    match key_map.get(key_code, MetaState(0)) {
+        Ok(KeyMapChar::Unicode(c)) => Key::from_name(c.encode_utf8(&mut [0; 4])),
+        _ => None,
+    }
+}
+
+/// Derive keyboard modifiers from the meta state of an Android key event.
+fn modifiers_from_meta_state(meta_state: MetaState) -> Modifiers {
+    Modifiers {
+        alt: meta_state.alt_on(),
+        ctrl: meta_state.ctrl_on(),
+        shift: meta_state.shift_on(),
+        mac_cmd: false,
+        command: meta_state.meta_on(),
+    }
+}
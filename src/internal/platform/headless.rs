@@ -0,0 +1,364 @@
+//! Platform implementation that is driven by a script instead of a device.
+
+use super::{
+    input::{InputEvent, InputStatus, TextActionMenu, TextInputState},
+    ClipboardContent, Clock, Error, Graphics, Platform, PlatformConfig, PlatformEvent, Renderer,
+    Waker,
+};
+use crate::{
+    headless::LifecycleEvent,
+    open_url::{url_target, UrlTarget},
+    GraphicsError, GraphicsOptions, OpenUrlError, ShareContent, SystemBars, TextInputOptions,
+    WindowInsets,
+};
+use egui::{ClippedPrimitive, Color32, ColorImage, FullOutput, Pos2, Rect, Theme};
+use egui_glow::glow;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Default)]
+pub(crate) struct HeadlessPlatform {
+    state: Arc<Mutex<HeadlessState>>,
+}
+
+/// Everything the headless platform has been told to do or been scripted with.
+pub(crate) struct HeadlessState {
+    pub(crate) events: VecDeque<LifecycleEvent>,
+    pub(crate) input_events: VecDeque<InputEvent>,
+    pub(crate) config: PlatformConfig,
+    pub(crate) window_size: Option<[u32; 2]>,
+    pub(crate) content_rect: Option<Rect>,
+    pub(crate) window_insets: WindowInsets,
+    pub(crate) saved_state: Option<Vec<u8>>,
+    pub(crate) keyboard_visible: bool,
+    pub(crate) text_input_state: Option<TextInputState>,
+    pub(crate) text_input_options: TextInputOptions,
+    pub(crate) text_action_menu: Option<TextActionMenu>,
+    pub(crate) fullscreen: bool,
+    pub(crate) system_bars: Option<(SystemBars, Theme)>,
+    pub(crate) focusable: bool,
+    pub(crate) pointer_captured: bool,
+    pub(crate) clipboard: ClipboardContent,
+
+    /// How many times something has been copied to the clipboard.
+    pub(crate) clipboard_copies: i64,
+    /// URLs that were opened, and the toolbar color of the Custom Tab they
+    /// were opened in, if any.
+    pub(crate) opened_urls: Vec<(String, UrlTarget)>,
+    pub(crate) shared: Vec<ShareContent>,
+
+    /// Whether an app was picked from the share sheet most recently opened.
+    pub(crate) share_target_chosen: Option<bool>,
+    pub(crate) frames: Vec<FullOutput>,
+    pub(crate) data_dir: Option<PathBuf>,
+
+    /// The time told by the platform's clock, which only moves when the
+    /// script says so.
+    pub(crate) now: Instant,
+}
+
+impl Default for HeadlessState {
+    fn default() -> Self {
+        Self {
+            events: VecDeque::new(),
+            input_events: VecDeque::new(),
+            config: PlatformConfig::default(),
+            window_size: None,
+            content_rect: None,
+            window_insets: WindowInsets::default(),
+            saved_state: None,
+            keyboard_visible: false,
+            text_input_state: None,
+            text_input_options: TextInputOptions::default(),
+            text_action_menu: None,
+            fullscreen: false,
+            system_bars: None,
+            focusable: true,
+            pointer_captured: false,
+            clipboard: ClipboardContent::Empty,
+            clipboard_copies: 0,
+            opened_urls: Vec::new(),
+            shared: Vec::new(),
+            share_target_chosen: None,
+            frames: Vec::new(),
+            data_dir: None,
+            now: Instant::now(),
+        }
+    }
+}
+
+impl HeadlessState {
+    /// Replace what is on the clipboard, like copying in any app would.
+    pub(crate) fn copy(&mut self, content: ClipboardContent) {
+        self.clipboard = content;
+        self.clipboard_copies += 1;
+    }
+}
+
+impl HeadlessPlatform {
+    /// Access the scripted state of the platform.
+    pub(crate) fn with_state<R>(&self, f: impl FnOnce(&mut HeadlessState) -> R) -> R {
+        f(&mut self.state.lock().unwrap())
+    }
+
+    fn dispatch(&self, event: LifecycleEvent, callback: &mut impl FnMut(PlatformEvent<'_>)) {
+        match event {
+            LifecycleEvent::Start => callback(PlatformEvent::Start),
+            LifecycleEvent::Resume { saved_state } => {
+                callback(PlatformEvent::Resume { saved_state })
+            }
+            LifecycleEvent::SaveState => {
+                let mut saved_state = None;
+
+                callback(PlatformEvent::SaveState {
+                    saved_state: &mut saved_state,
+                });
+
+                self.with_state(|state| state.saved_state = saved_state);
+            }
+            LifecycleEvent::Pause => callback(PlatformEvent::Pause),
+            LifecycleEvent::Stop => callback(PlatformEvent::Stop),
+            LifecycleEvent::Destroy => callback(PlatformEvent::Destroy),
+            LifecycleEvent::InitWindow { width, height } => {
+                self.with_state(|state| state.window_size = Some([width, height]));
+                callback(PlatformEvent::InitWindow);
+            }
+            LifecycleEvent::TerminateWindow => {
+                callback(PlatformEvent::TerminateWindow);
+                self.with_state(|state| state.window_size = None);
+            }
+            LifecycleEvent::WindowResized { width, height } => {
+                self.with_state(|state| state.window_size = Some([width, height]));
+                callback(PlatformEvent::WindowResized);
+            }
+            LifecycleEvent::RedrawNeeded => callback(PlatformEvent::RedrawNeeded),
+            LifecycleEvent::ContentRectChanged(rect) => {
+                self.with_state(|state| state.content_rect = Some(rect));
+                callback(PlatformEvent::ContentRectChanged);
+            }
+            LifecycleEvent::InsetsChanged(insets) => {
+                self.with_state(|state| state.window_insets = insets);
+                callback(PlatformEvent::InsetsChanged);
+            }
+            LifecycleEvent::GainedFocus => callback(PlatformEvent::GainedFocus),
+            LifecycleEvent::LostFocus => callback(PlatformEvent::LostFocus),
+            LifecycleEvent::ConfigChanged { density, theme } => {
+                self.with_state(|state| state.config = PlatformConfig { density, theme });
+                callback(PlatformEvent::ConfigChanged);
+            }
+            LifecycleEvent::LowMemory => callback(PlatformEvent::LowMemory),
+        }
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    type Graphics = HeadlessGraphics;
+
+    fn create_graphics(&self, _options: GraphicsOptions) -> Self::Graphics {
+        HeadlessGraphics {
+            state: self.state.clone(),
+            attached: false,
+        }
+    }
+
+    fn attach_window(&self, graphics: &mut Self::Graphics) -> Result<(), GraphicsError> {
+        graphics.attached = self.with_state(|state| state.window_size.is_some());
+        Ok(())
+    }
+
+    fn create_waker(&self) -> Waker {
+        // Events are only ever delivered when the script is run, so there is
+        // never anything to wake up.
+        Box::new(|| {})
+    }
+
+    fn create_clock(&self) -> Clock {
+        let platform = self.clone();
+        Arc::new(move || platform.with_state(|state| state.now))
+    }
+
+    fn poll_events(&self, timeout: Option<Duration>, mut callback: impl FnMut(PlatformEvent<'_>)) {
+        let mut polled = false;
+
+        // The lock must not be held while the callback runs, since the runner
+        // will call back into the platform.
+        while let Some(event) = self.with_state(|state| state.events.pop_front()) {
+            self.dispatch(event, &mut callback);
+            polled = true;
+        }
+
+        if self.with_state(|state| !state.input_events.is_empty()) {
+            callback(PlatformEvent::InputAvailable);
+            polled = true;
+        }
+
+        // Time passes instantly when nothing else is scripted.
+        if !polled {
+            callback(match timeout {
+                Some(timeout) => {
+                    self.with_state(|state| state.now += timeout);
+                    PlatformEvent::Timeout
+                }
+                None => PlatformEvent::Wake,
+            });
+        }
+    }
+
+    fn input_events(&self, mut callback: impl FnMut(&InputEvent) -> InputStatus) {
+        while let Some(event) = self.with_state(|state| state.input_events.pop_front()) {
+            callback(&event);
+        }
+    }
+
+    fn config(&self) -> PlatformConfig {
+        self.with_state(|state| state.config.clone())
+    }
+
+    fn content_rect(&self) -> Rect {
+        self.with_state(|state| {
+            state.content_rect.unwrap_or_else(|| {
+                let [width, height] = state.window_size.unwrap_or_default();
+                Rect::from_min_max(Pos2::ZERO, Pos2::new(width as _, height as _))
+            })
+        })
+    }
+
+    fn window_insets(&self) -> WindowInsets {
+        self.with_state(|state| state.window_insets)
+    }
+
+    fn show_soft_input(&self) {
+        self.with_state(|state| state.keyboard_visible = true);
+    }
+
+    fn hide_soft_input(&self) {
+        self.with_state(|state| state.keyboard_visible = false);
+    }
+
+    fn set_text_input_state(&self, text_input_state: TextInputState) {
+        self.with_state(|state| state.text_input_state = Some(text_input_state));
+    }
+
+    fn set_text_input_options(&self, options: TextInputOptions) {
+        self.with_state(|state| state.text_input_options = options);
+    }
+
+    fn set_text_action_menu(&self, menu: Option<TextActionMenu>) -> Result<(), Error> {
+        self.with_state(|state| state.text_action_menu = menu);
+        Ok(())
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        self.with_state(|state| state.fullscreen = fullscreen);
+    }
+
+    fn set_system_bars(&self, system_bars: SystemBars, theme: Theme) {
+        self.with_state(|state| state.system_bars = Some((system_bars, theme)));
+    }
+
+    fn set_focusable(&self, focusable: bool) {
+        self.with_state(|state| state.focusable = focusable);
+    }
+
+    fn set_pointer_capture(&self, captured: bool) {
+        self.with_state(|state| state.pointer_captured = captured);
+    }
+
+    fn clipboard(&self) -> Result<ClipboardContent, Error> {
+        Ok(self.with_state(|state| state.clipboard.clone()))
+    }
+
+    fn set_clipboard_text(&self, text: String) -> Result<(), Error> {
+        self.with_state(|state| state.copy(ClipboardContent::Text(text)));
+        Ok(())
+    }
+
+    fn set_clipboard_image(&self, image: &ColorImage) -> Result<(), Error> {
+        self.with_state(|state| state.copy(ClipboardContent::Image(Arc::new(image.clone()))));
+        Ok(())
+    }
+
+    fn clipboard_timestamp(&self) -> Option<i64> {
+        self.with_state(|state| match state.clipboard {
+            ClipboardContent::Empty => None,
+            _ => Some(state.clipboard_copies),
+        })
+    }
+
+    fn open_url(&self, url: &str, custom_tab: Option<Color32>) -> Result<(), OpenUrlError> {
+        let target = url_target(url, custom_tab)?;
+        self.with_state(|state| state.opened_urls.push((url.to_owned(), target)));
+        Ok(())
+    }
+
+    fn share(&self, content: &ShareContent) -> Result<(), Error> {
+        self.with_state(|state| {
+            state.shared.push(content.clone());
+            state.share_target_chosen = Some(false);
+        });
+        Ok(())
+    }
+
+    fn share_target_chosen(&self) -> Option<bool> {
+        self.with_state(|state| state.share_target_chosen)
+    }
+
+    fn internal_data_path(&self) -> Option<PathBuf> {
+        self.with_state(|state| state.data_dir.clone())
+    }
+}
+
+/// Pretends to draw frames, recording the output of each one instead.
+pub(crate) struct HeadlessGraphics {
+    state: Arc<Mutex<HeadlessState>>,
+    attached: bool,
+}
+
+impl Graphics for HeadlessGraphics {
+    type Renderer<'a> = HeadlessRenderer<'a>;
+
+    fn renderer(&mut self) -> Option<HeadlessRenderer<'_>> {
+        self.attached
+            .then_some(HeadlessRenderer { state: &self.state })
+    }
+
+    fn detach_window(&mut self) {
+        self.attached = false;
+    }
+
+    fn gl(&self) -> Option<Arc<glow::Context>> {
+        None
+    }
+
+    #[cfg(feature = "wgpu")]
+    fn wgpu_render_state(&self) -> Option<egui_wgpu::RenderState> {
+        None
+    }
+}
+
+pub(crate) struct HeadlessRenderer<'a> {
+    state: &'a Mutex<HeadlessState>,
+}
+
+impl Renderer for HeadlessRenderer<'_> {
+    fn handle_resize(&mut self) {}
+
+    fn repaint(
+        &mut self,
+        full_output: &mut FullOutput,
+        _clipped_primitives: &[ClippedPrimitive],
+        _screenshot: bool,
+    ) -> Result<Option<ColorImage>, GraphicsError> {
+        // Nothing is drawn, so there is nothing to take a screenshot of.
+        self.state.lock().unwrap().frames.push(full_output.clone());
+        Ok(None)
+    }
+
+    fn window_size(&self) -> [u32; 2] {
+        self.state.lock().unwrap().window_size.unwrap_or_default()
+    }
+}
@@ -0,0 +1,202 @@
+//! Platform-independent input events.
+//!
+//! These mirror the subset of `android_activity::input` that we translate into
+//! egui events, but can be constructed freely so that input handling can be
+//! driven by a headless runner.
+
+use crate::{GamepadButton, ToolType};
+use egui::{Key, Modifiers, PointerButton, Pos2, Rect, Vec2, NUM_POINTER_BUTTONS};
+
+/// An input event received from the platform.
+#[derive(Clone, Debug)]
+pub enum InputEvent {
+    Motion(MotionEvent),
+    Key(KeyEvent),
+    Joystick(JoystickEvent),
+    Text(TextInputState),
+
+    /// An item was picked from the system's text selection menu, or the menu
+    /// was closed without picking one if `None`.
+    TextAction(Option<TextAction>),
+}
+
+/// Indicates whether an input event was handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputStatus {
+    Handled,
+    Unhandled,
+}
+
+/// A touch, mouse, or other pointer event.
+#[derive(Clone, Debug)]
+pub struct MotionEvent {
+    pub device_id: i32,
+    pub action: MotionAction,
+
+    /// Index of the pointer in `pointers` that the action applies to, for
+    /// `PointerDown` and `PointerUp` actions.
+    pub pointer_index: usize,
+
+    /// The button that changed state, for `ButtonPress` and `ButtonRelease`
+    /// actions.
+    pub action_button: Option<PointerButton>,
+
+    /// The mouse buttons that are held down, indexed by [`PointerButton`].
+    pub button_state: [bool; NUM_POINTER_BUTTONS],
+
+    /// Whether the mouse is captured by the app. Captured mouse events only
+    /// report movement, in [`Pointer::relative`], and not a position.
+    pub captured: bool,
+
+    pub modifiers: Modifiers,
+
+    /// All pointers that are currently active.
+    pub pointers: Vec<Pointer>,
+
+    /// Earlier samples of the pointers that were batched into a `Move`
+    /// action, oldest first. Each has the same pointers in the same order as
+    /// `pointers`.
+    ///
+    /// Always empty on Android for now, as `android-activity` doesn't expose
+    /// the batched samples. Only events sent to the headless runner have it.
+    pub history: Vec<Vec<Pointer>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MotionAction {
+    Down,
+    Up,
+    Move,
+    Cancel,
+    Outside,
+    PointerDown,
+    PointerUp,
+    HoverEnter,
+    HoverMove,
+    HoverExit,
+    Scroll,
+    ButtonPress,
+    ButtonRelease,
+}
+
+/// The state of a single pointer within a [`MotionEvent`].
+#[derive(Clone, Copy, Debug)]
+pub struct Pointer {
+    pub id: i32,
+
+    /// Position in physical pixels.
+    pub pos: Pos2,
+
+    pub pressure: f32,
+    pub tool_type: ToolType,
+
+    /// Angle of a stylus away from perpendicular to the screen, in radians.
+    pub tilt: f32,
+
+    /// Direction a stylus points in, in radians clockwise from the top of the
+    /// screen.
+    pub orientation: f32,
+
+    /// Distance of a hovering stylus from the screen.
+    pub distance: f32,
+
+    /// Value of the horizontal and vertical scroll axes.
+    pub scroll: Vec2,
+
+    /// Movement of a mouse since the previous event in physical pixels,
+    /// before acceleration and without being stopped by the screen's edges.
+    pub relative: Vec2,
+}
+
+/// A hardware or soft keyboard key event, or a button on a game controller.
+#[derive(Clone, Debug)]
+pub struct KeyEvent {
+    pub device_id: i32,
+    pub action: KeyAction,
+
+    /// The key as egui understands it, if there is an equivalent. The Android
+    /// back button is reported as [`Key::BrowserBack`].
+    pub key: Option<Key>,
+
+    /// The key at the position on the keyboard that was pressed, regardless
+    /// of the keyboard layout, if egui has an equivalent.
+    pub physical_key: Option<Key>,
+
+    /// The character produced by the key press, according to the device's
+    /// keyboard layout.
+    pub text: Option<char>,
+
+    /// The button, if the key is on a game controller.
+    pub gamepad_button: Option<GamepadButton>,
+
+    pub repeat_count: u32,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    Down,
+    Up,
+}
+
+/// Movement of the sticks, triggers or hat switch of a game controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JoystickEvent {
+    pub device_id: i32,
+
+    /// Position of the left stick, from -1 to 1 on each axis.
+    pub left_stick: Vec2,
+
+    /// Position of the right stick, from -1 to 1 on each axis.
+    pub right_stick: Vec2,
+
+    /// How far the left trigger is pulled, from 0 to 1.
+    pub left_trigger: f32,
+
+    /// How far the right trigger is pulled, from 0 to 1.
+    pub right_trigger: f32,
+
+    /// Position of the hat switch, from -1 to 1 on each axis, which is how
+    /// many controllers report their D-pad.
+    pub hat: Vec2,
+}
+
+/// State of the text being edited by the soft keyboard.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextInputState {
+    pub text: String,
+    pub selection: TextSpan,
+    pub compose_region: Option<TextSpan>,
+}
+
+/// A span of characters within a [`TextInputState`].
+///
+/// Indices count characters, not bytes. `start` is where the selection is
+/// anchored, so it may be greater than `end`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// An action on the selected text of a text field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAction {
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+}
+
+/// The system's floating text selection menu, as it should be shown.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextActionMenu {
+    /// The area to keep clear, usually the selected text, in physical pixels.
+    pub rect: Rect,
+
+    pub actions: Vec<TextAction>,
+
+    /// The selected text, which other apps may offer actions for, such as
+    /// translating or sharing it. Not given for passwords.
+    pub selected_text: Option<String>,
+}
@@ -0,0 +1,210 @@
+//! Abstraction over the operating system services that the runner depends on.
+//!
+//! On a real device this is implemented on top of `android-activity`, but the
+//! same runner can also be driven by a scripted, headless implementation so
+//! that app logic can be exercised off-device.
+
+use crate::{
+    GraphicsError, GraphicsOptions, OpenUrlError, ShareContent, SystemBars, TextInputOptions,
+    WindowInsets,
+};
+use egui::{ClippedPrimitive, Color32, ColorImage, FullOutput, Rect, Theme};
+use egui_glow::glow;
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+pub(crate) mod headless;
+pub(crate) mod input;
+
+#[cfg(target_os = "android")]
+pub(crate) mod android;
+
+pub(crate) type Error = Box<dyn std::error::Error>;
+
+/// Callback that wakes up a blocked [`Platform::poll_events`] call.
+pub(crate) type Waker = Box<dyn Fn() + Send + Sync>;
+
+/// Callback that tells the current time.
+pub(crate) type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+/// Services provided by the host operating system.
+///
+/// Implementations are expected to be cheap handles that can be cloned freely,
+/// in the same way as `AndroidApp`.
+pub(crate) trait Platform: Clone {
+    /// Graphics backend used to draw frames into the platform's window.
+    type Graphics: Graphics;
+
+    /// Create the graphics backend for this platform. No window is attached
+    /// initially.
+    fn create_graphics(&self, options: GraphicsOptions) -> Self::Graphics;
+
+    /// Attach the platform's current window to the given graphics backend, if
+    /// there is one.
+    fn attach_window(&self, graphics: &mut Self::Graphics) -> Result<(), GraphicsError>;
+
+    /// Create a callback that wakes the event loop when invoked.
+    fn create_waker(&self) -> Waker;
+
+    /// Create the clock that repaints, animations and gestures are timed by.
+    fn create_clock(&self) -> Clock;
+
+    /// Wait for events for up to the given timeout, passing each event to the
+    /// callback.
+    fn poll_events(&self, timeout: Option<Duration>, callback: impl FnMut(PlatformEvent<'_>));
+
+    /// Drain all pending input events, passing each event to the callback.
+    fn input_events(&self, callback: impl FnMut(&input::InputEvent) -> input::InputStatus);
+
+    /// Get the current device configuration.
+    fn config(&self) -> PlatformConfig;
+
+    /// Get the rectangle in the window in which content should be placed, in
+    /// physical pixels.
+    fn content_rect(&self) -> Rect;
+
+    /// Get the parts of the window covered by system UI, in physical pixels.
+    fn window_insets(&self) -> WindowInsets;
+
+    fn show_soft_input(&self);
+
+    fn hide_soft_input(&self);
+
+    /// Inform the soft keyboard of the state of the currently edited text.
+    fn set_text_input_state(&self, state: input::TextInputState);
+
+    /// Set up the soft keyboard for the kind of text being edited. Takes
+    /// effect the next time the keyboard is shown.
+    fn set_text_input_options(&self, options: TextInputOptions);
+
+    /// Show the system's floating text selection menu, or update it if it is
+    /// already shown, or hide it if `None`. Fails if the system menu is not
+    /// available.
+    fn set_text_action_menu(&self, menu: Option<input::TextActionMenu>) -> Result<(), Error>;
+
+    fn set_fullscreen(&self, fullscreen: bool);
+
+    /// Change how the system bars are shown, with icons suiting the given
+    /// theme.
+    fn set_system_bars(&self, system_bars: SystemBars, theme: Theme);
+
+    fn set_focusable(&self, focusable: bool);
+
+    /// Capture the mouse, so that it is hidden and only reports relative
+    /// movement, or release it.
+    fn set_pointer_capture(&self, captured: bool);
+
+    fn clipboard(&self) -> Result<ClipboardContent, Error>;
+
+    fn set_clipboard_text(&self, text: String) -> Result<(), Error>;
+
+    fn set_clipboard_image(&self, image: &ColorImage) -> Result<(), Error>;
+
+    /// Get a value that changes whenever something new is copied to the
+    /// clipboard, by this app or any other, if known.
+    fn clipboard_timestamp(&self) -> Option<i64>;
+
+    /// Open a URL in the app that handles it. Web links are opened in a Custom
+    /// Tab with the given toolbar color if one is given.
+    fn open_url(&self, url: &str, custom_tab: Option<Color32>) -> Result<(), OpenUrlError>;
+
+    /// Open the share sheet to share the given content with another app.
+    fn share(&self, content: &ShareContent) -> Result<(), Error>;
+
+    /// Whether an app was picked from the share sheet most recently opened, if
+    /// known.
+    fn share_target_chosen(&self) -> Option<bool>;
+
+    /// Get the app's private directory for persistent files, if available.
+    fn internal_data_path(&self) -> Option<PathBuf>;
+}
+
+/// A graphics backend that may or may not currently have a window to draw to.
+pub(crate) trait Graphics {
+    type Renderer<'a>: Renderer
+    where
+        Self: 'a;
+
+    /// Get a renderer for the current window. If a window is not currently
+    /// associated with the backend, then `None` is returned.
+    fn renderer(&mut self) -> Option<Self::Renderer<'_>>;
+
+    fn detach_window(&mut self);
+
+    /// Get the OpenGL context used for drawing, if one has been created.
+    fn gl(&self) -> Option<Arc<glow::Context>>;
+
+    /// Get the wgpu device and renderer used for drawing, if they have been
+    /// created.
+    #[cfg(feature = "wgpu")]
+    fn wgpu_render_state(&self) -> Option<egui_wgpu::RenderState>;
+}
+
+pub(crate) trait Renderer {
+    fn handle_resize(&mut self);
+
+    /// Draw a frame. If `screenshot` is true, the frame is also read back
+    /// before it is presented and returned as an image.
+    fn repaint(
+        &mut self,
+        full_output: &mut FullOutput,
+        clipped_primitives: &[ClippedPrimitive],
+        screenshot: bool,
+    ) -> Result<Option<ColorImage>, GraphicsError>;
+
+    fn window_size(&self) -> [u32; 2];
+}
+
+/// Device configuration relevant to egui.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PlatformConfig {
+    /// Screen density in dots per inch.
+    pub(crate) density: Option<u32>,
+
+    pub(crate) theme: Option<Theme>,
+}
+
+/// What is on the clipboard, in the form it can be pasted into egui.
+#[derive(Clone, Debug, Default)]
+pub(crate) enum ClipboardContent {
+    #[default]
+    Empty,
+
+    /// Plain text, or rich text such as HTML converted to plain text.
+    Text(String),
+
+    Image(Arc<ColorImage>),
+}
+
+/// A platform-independent equivalent of `android_activity::MainEvent`.
+#[derive(Debug)]
+pub(crate) enum PlatformEvent<'a> {
+    Wake,
+    Timeout,
+    Start,
+    Resume {
+        saved_state: Option<Vec<u8>>,
+    },
+    /// The callback may store state to be given back to a future `Resume`.
+    SaveState {
+        saved_state: &'a mut Option<Vec<u8>>,
+    },
+    Pause,
+    Stop,
+    Destroy,
+    InitWindow,
+    TerminateWindow,
+    WindowResized,
+    RedrawNeeded,
+    /// The parts of the window covered by system UI have changed.
+    InsetsChanged,
+    ContentRectChanged,
+    GainedFocus,
+    LostFocus,
+    ConfigChanged,
+    LowMemory,
+    InputAvailable,
+}
@@ -0,0 +1,77 @@
+use crate::{insets::soft_input_adjust, keyboard_height, SoftInputAdjust};
+use egui::{emath::TSTransform, vec2, Context, FullOutput, LayerId, Plugin, Rect};
+use std::collections::HashMap;
+
+/// Space to leave between the text cursor and the top of the soft keyboard, in
+/// points.
+const CURSOR_MARGIN: f32 = 16.0;
+
+/// Pans the whole UI up when the soft keyboard would cover the text cursor, in
+/// the same way as Android's `adjustPan` soft input mode.
+#[derive(Default)]
+pub(crate) struct KeyboardPlugin {
+    /// The text cursor of the focused text field during the last pass, in
+    /// global coordinates as they would be without panning.
+    cursor_rect: Option<Rect>,
+
+    /// How far each layer is currently panned up, in points.
+    panned_layers: HashMap<LayerId, f32>,
+
+    /// How far the UI should currently be panned up, in points.
+    pan: f32,
+}
+
+impl KeyboardPlugin {
+    fn target_pan(&self, ctx: &Context) -> f32 {
+        if soft_input_adjust(ctx) != SoftInputAdjust::Pan {
+            return 0.0;
+        }
+
+        let Some(cursor_rect) = self.cursor_rect else {
+            return 0.0;
+        };
+
+        let keyboard_height = keyboard_height(ctx);
+        let keyboard_top = ctx.viewport_rect().bottom() - keyboard_height;
+
+        (cursor_rect.bottom() + CURSOR_MARGIN - keyboard_top).clamp(0.0, keyboard_height)
+    }
+}
+
+impl Plugin for KeyboardPlugin {
+    fn debug_name(&self) -> &'static str {
+        "Android Keyboard Support"
+    }
+
+    fn on_begin_pass(&mut self, ctx: &Context) {
+        self.pan = self.target_pan(ctx);
+
+        // Layers may have transforms of their own, so only add the difference
+        // from what was previously applied. Layers that appeared since then
+        // have not been panned at all.
+        let layer_ids: Vec<_> = ctx.memory(|memory| memory.layer_ids().collect());
+
+        for layer_id in layer_ids.into_iter().chain([LayerId::background()]) {
+            let panned = self.panned_layers.get(&layer_id).copied().unwrap_or(0.0);
+            if panned == self.pan {
+                continue;
+            }
+
+            let transform = ctx.layer_transform_to_global(layer_id).unwrap_or_default();
+            ctx.set_transform_layer(
+                layer_id,
+                TSTransform::from_translation(vec2(0.0, panned - self.pan)) * transform,
+            );
+            self.panned_layers.insert(layer_id, self.pan);
+            ctx.request_repaint();
+        }
+    }
+
+    fn output_hook(&mut self, output: &mut FullOutput) {
+        self.cursor_rect = output
+            .platform_output
+            .ime
+            .as_ref()
+            .map(|ime| ime.cursor_rect.translate(vec2(0.0, self.pan)));
+    }
+}
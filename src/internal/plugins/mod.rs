@@ -1,11 +1,38 @@
 //! egui plugins that are internal to the Android integration. These plugins are
 //! loaded into the egui context automatically.
 
+use crate::internal::platform::input::TextActionMenu;
 use egui::Context;
+#[cfg(test)]
+use {crate::internal::platform::input::TextAction, egui::Pos2};
 
+mod keyboard;
 mod text;
 
+pub(crate) use text::TextMagnifier;
+
 /// Register all internal plugins to the given egui context.
 pub(crate) fn register_all_plugins(ctx: &Context) {
+    ctx.add_plugin(keyboard::KeyboardPlugin::default());
     ctx.add_plugin(text::TextPlugin::default());
 }
+
+/// The system text selection menu to show after the last pass, with its area
+/// in points. Only used with [`TextSelectionMenu::System`](crate::TextSelectionMenu::System).
+pub(crate) fn text_action_menu(ctx: &Context) -> Option<TextActionMenu> {
+    ctx.with_plugin(|plugin: &mut text::TextPlugin| plugin.system_menu())
+        .flatten()
+}
+
+/// Hide the text selection toolbar or menu until the selection changes.
+pub(crate) fn dismiss_text_toolbar(ctx: &Context) {
+    ctx.with_plugin(|plugin: &mut text::TextPlugin| plugin.dismiss_toolbar());
+}
+
+/// Where the button for `action` is on the text selection toolbar, if the
+/// toolbar is shown.
+#[cfg(test)]
+pub(crate) fn text_toolbar_button(ctx: &Context, action: TextAction) -> Option<Pos2> {
+    ctx.with_plugin(|plugin: &mut text::TextPlugin| plugin.toolbar_button(action))
+        .flatten()
+}
@@ -1,28 +1,416 @@
-use crate::internal::widgets::text_context_menu::TextContextMenu;
+use crate::{
+    internal::{
+        platform::input::{TextAction, TextActionMenu},
+        text_input::push_select_all,
+        widgets::{
+            magnifier::Magnifier,
+            selection_handle::{HandleSide, SelectionHandle},
+            text_toolbar::TextToolbar,
+        },
+    },
+    TextSelectionMenu,
+};
 use egui::{
-    pos2, vec2, Align2, Color32, Context, Event, FontId, FullOutput, Id, LayerId, Plugin, Popup,
-    PopupAnchor, PopupKind, RawInput, Rect, ViewportCommand,
+    output::OutputEvent, text::CCursorRange, text_edit::TextEditState, Context, Event, FullOutput,
+    Id, InputOptions, LayerId, Modifiers, Order, Plugin, PointerButton, Pos2, RawInput, Rect,
+    TouchPhase, Vec2, ViewportCommand, WidgetType,
 };
+use std::collections::HashMap;
+
+/// How long a touch has to be held on a text field to select a word, in
+/// seconds.
+const LONG_PRESS_DURATION: f64 = 0.5;
+
+/// Whether a magnifier is shown while a selection handle is dragged, as chosen
+/// by [`Activity::show_text_magnifier`](crate::Activity::show_text_magnifier).
+#[derive(Clone, Copy)]
+pub(crate) struct TextMagnifier(pub(crate) bool);
+
+impl TextMagnifier {
+    pub(crate) fn store(self, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, self));
+    }
 
+    fn enabled(ctx: &Context) -> bool {
+        ctx.data(|data| data.get_temp::<Self>(Id::NULL))
+            .is_none_or(|magnifier| magnifier.0)
+    }
+}
+
+/// Touch friendly text selection, in the style of Android's own text fields.
+///
+/// Holding a finger on a text field selects the word under it, and selections
+/// get a handle at either end that can be dragged to change what is selected,
+/// along with a toolbar of clipboard actions.
+///
+/// None of this is made of egui widgets, as interacting with them would take
+/// focus away from the text field. Instead, touches on the handles and toolbar
+/// are intercepted before egui sees them, and handle drags are turned into the
+/// mouse drags that egui already selects text with.
+#[derive(Default)]
 pub(crate) struct TextPlugin {
-    id: Id,
-    menu: Option<MenuInfo>,
-    captured_events: Vec<Event>,
+    /// The focused text field during the last pass.
+    field: Option<Id>,
+
+    /// Text cursors of the focused text field by character index, relative to
+    /// the field. egui only reports the primary cursor of a selection, so these
+    /// are collected as the cursor moves, and the cursor at the other end of a
+    /// selection is found with a [`Probe`] when it isn't known.
+    cursor_rects: HashMap<usize, Rect>,
+
+    /// The selection of the focused text field during the last pass.
+    selection: Option<CCursorRange>,
+
+    probe: Option<Probe>,
+
+    /// The handles shown during the last pass.
+    handles: Vec<SelectionHandle>,
+
+    /// The toolbar shown during the last pass.
+    toolbar: Option<TextToolbar>,
+
+    /// The system's text selection menu, if it is shown instead of the
+    /// toolbar. The area it is shown for is in points.
+    system_menu: Option<TextActionMenu>,
+
+    /// Show the toolbar even without a selection, to paste into the field.
+    toolbar_requested: bool,
+
+    /// Hide the toolbar until the selection changes.
+    toolbar_dismissed: bool,
+
+    /// The toolbar button being pressed, while a touch on the toolbar is
+    /// captured.
+    toolbar_press: Option<Option<TextAction>>,
+
+    /// Toolbar buttons that were tapped, which are acted on at the start of the
+    /// next pass.
+    actions: Vec<TextAction>,
+
+    long_press: Option<LongPress>,
+
+    /// Whether egui reported an editable text field under the pointer during
+    /// the last pass.
+    text_under_pointer: bool,
+
+    drag: Option<HandleDrag>,
+
+    /// Pointer events held back for the next pass, see [`HandleDrag`].
+    deferred_events: Vec<Event>,
+
+    magnifier: Option<Magnifier>,
+
+    input_options: InputOptions,
 }
 
-struct MenuInfo {
-    layer: LayerId,
-    area: Rect,
+/// Finding the cursor at the secondary end of a selection, by swapping the
+/// ends of the selection for a pass so that egui reports it as the primary
+/// cursor. The pass is then discarded, so this isn't visible.
+#[derive(Clone, Copy)]
+enum Probe {
+    /// Swap the ends of this selection in the next pass.
+    Pending(CCursorRange),
+
+    /// The ends of this selection are swapped during the current pass.
+    Active(CCursorRange),
 }
 
-impl Default for TextPlugin {
-    fn default() -> Self {
-        Self {
-            id: Id::new("egui_android.text_popup"),
-            menu: None,
-            captured_events: Vec::new(),
+/// A touch that may become a long press.
+struct LongPress {
+    origin: Pos2,
+    start_time: f64,
+
+    /// Whether the touch has been held long enough to select a word. The rest
+    /// of the touch is hidden from egui, so that lifting it does not change the
+    /// selection.
+    fired: bool,
+}
+
+/// A selection handle being dragged.
+///
+/// egui selects text by pressing the pointer where the selection starts and
+/// dragging it to where it ends, so the drag is passed on as a drag from the
+/// cursor at the other end of the selection, the anchor, to the cursor of the
+/// dragged handle. egui places the cursor wherever the pointer is once it is
+/// pressed, so the press on the anchor has to be handled in a pass of its own,
+/// and any events following it are held back until the next pass.
+struct HandleDrag {
+    anchor: Pos2,
+
+    /// Offset from the touch to the cursor of the dragged handle.
+    offset: Vec2,
+
+    /// Whether the touch has moved, and the press on the anchor has been
+    /// passed on to egui. Until then the selection is left as it is, so that
+    /// tapping a handle does nothing.
+    pressed: bool,
+}
+
+impl TextPlugin {
+    pub(crate) fn system_menu(&self) -> Option<TextActionMenu> {
+        self.system_menu.clone()
+    }
+
+    /// Hide the toolbar or system menu until the selection changes, after one
+    /// of its actions was used or it was closed.
+    pub(crate) fn dismiss_toolbar(&mut self) {
+        self.toolbar_dismissed = true;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn toolbar_button(&self, action: TextAction) -> Option<Pos2> {
+        self.toolbar
+            .as_ref()?
+            .button_rect(action)
+            .map(|rect| rect.center())
+    }
+
+    fn layer_id(order: Order) -> LayerId {
+        LayerId::new(order, Id::new("egui_android.text_selection"))
+    }
+
+    /// Handle a touch starting, returning whether it is hidden from egui.
+    fn handle_press(&mut self, pos: Pos2, time: f64) -> bool {
+        if let Some(toolbar) = &self.toolbar {
+            if toolbar.rect().contains(pos) {
+                self.toolbar_press = Some(toolbar.action_at(pos));
+                return true;
+            }
+        }
+
+        let grabbed = self
+            .handles
+            .iter()
+            .filter(|handle| handle.hit_rect().contains(pos))
+            .min_by(|a, b| {
+                let a = a.rect().distance_sq_to_pos(pos);
+                let b = b.rect().distance_sq_to_pos(pos);
+                a.total_cmp(&b)
+            });
+        if let Some(grabbed) = grabbed {
+            let anchor = self
+                .handles
+                .iter()
+                .find(|handle| handle.side != grabbed.side)
+                .map_or(grabbed.cursor_rect.center(), |handle| {
+                    handle.cursor_rect.center()
+                });
+            self.drag = Some(HandleDrag {
+                anchor,
+                offset: grabbed.cursor_rect.center() - pos,
+                pressed: false,
+            });
+            return true;
+        }
+
+        self.toolbar_requested = false;
+        self.long_press = Some(LongPress {
+            origin: pos,
+            start_time: time,
+            fired: false,
+        });
+        false
+    }
+
+    /// Handle a touch ending, returning whether it is hidden from egui.
+    fn handle_release(&mut self, pos: Pos2, out: &mut Vec<Event>) -> bool {
+        if let Some(pressed) = self.toolbar_press.take() {
+            let released = self
+                .toolbar
+                .as_ref()
+                .and_then(|toolbar| toolbar.action_at(pos));
+            if let Some(action) = pressed.filter(|_| released == pressed) {
+                self.tap_toolbar(action, out);
+            }
+            return true;
+        }
+
+        if let Some(drag) = self.drag.take() {
+            if drag.pressed {
+                out.push(primary_button(pos + drag.offset, false));
+            }
+            return true;
+        }
+
+        self.long_press.take().is_some_and(|press| press.fired)
+    }
+
+    fn tap_toolbar(&mut self, action: TextAction, out: &mut Vec<Event>) {
+        match action {
+            // Selecting all doesn't need the clipboard, so egui can do it
+            // right away.
+            TextAction::SelectAll => push_select_all(out),
+            action => self.actions.push(action),
         }
     }
+
+    /// Select the word under a long press, by passing it on to egui as a
+    /// double click.
+    fn fire_long_press(&mut self, time: f64, out: &mut Vec<Event>) {
+        let Some(press) = self.long_press.as_mut() else {
+            return;
+        };
+        if press.fired || !self.text_under_pointer || time - press.start_time < LONG_PRESS_DURATION
+        {
+            return;
+        }
+
+        press.fired = true;
+        self.toolbar_requested = true;
+        self.toolbar_dismissed = false;
+
+        // Releasing the touch counts as the first click of the double click,
+        // unless it was held for too long to be a click at all.
+        out.push(primary_button(press.origin, false));
+        if time - press.start_time > self.input_options.max_click_duration {
+            out.extend([
+                primary_button(press.origin, true),
+                primary_button(press.origin, false),
+            ]);
+        }
+        out.extend([
+            primary_button(press.origin, true),
+            primary_button(press.origin, false),
+        ]);
+    }
+
+    fn update_selection(&mut self, ctx: &Context) {
+        let ime = ctx.output(|output| output.ime);
+        let focused = ime.and_then(|_| ctx.memory(|memory| memory.focused()));
+
+        let value_changed = ctx.output(|output| {
+            output.events.iter().any(|event| {
+                matches!(event, OutputEvent::ValueChanged(info) if info.typ == WidgetType::TextEdit)
+            })
+        });
+        if focused != self.field || value_changed {
+            self.cursor_rects.clear();
+        }
+        if focused != self.field {
+            self.toolbar_requested = false;
+            self.toolbar_dismissed = false;
+            self.field = focused;
+        }
+
+        self.handles.clear();
+        self.toolbar = None;
+        self.system_menu = None;
+        self.magnifier = None;
+
+        let (Some(id), Some(ime)) = (focused, ime) else {
+            self.selection = None;
+            self.probe = None;
+            return;
+        };
+        let Some(mut selection) =
+            TextEditState::load(ctx, id).and_then(|state| state.cursor.char_range())
+        else {
+            return;
+        };
+
+        let origin = ime.rect.min.to_vec2();
+        let cursor_rect = ime.cursor_rect.translate(-origin);
+        if self
+            .cursor_rects
+            .get(&selection.primary.index)
+            .is_some_and(|known| *known != cursor_rect)
+        {
+            // The text has moved within the field, such as by scrolling.
+            self.cursor_rects.clear();
+        }
+        self.cursor_rects
+            .insert(selection.primary.index, cursor_rect);
+
+        if let Some(Probe::Active(probed)) = self.probe.take() {
+            if selection.primary.index == probed.secondary.index
+                && selection.secondary.index == probed.primary.index
+            {
+                let mut state = TextEditState::load(ctx, id).unwrap_or_default();
+                state.cursor.set_char_range(Some(probed));
+                state.store(ctx, id);
+                ctx.request_discard("finding the end of the text selection");
+                selection = probed;
+            }
+        }
+
+        let previous = self.selection.replace(selection);
+        if previous.map(|previous| (previous.primary.index, previous.secondary.index))
+            != Some((selection.primary.index, selection.secondary.index))
+        {
+            self.toolbar_dismissed = false;
+        }
+
+        let start = selection.primary.index.min(selection.secondary.index);
+        let end = selection.primary.index.max(selection.secondary.index);
+        if start != end {
+            match (self.cursor_rects.get(&start), self.cursor_rects.get(&end)) {
+                (Some(start_rect), Some(end_rect)) => {
+                    let visible = ime.rect.expand(1.0);
+                    self.handles.extend(
+                        [
+                            (HandleSide::Start, *start_rect),
+                            (HandleSide::End, *end_rect),
+                        ]
+                        .into_iter()
+                        .map(|(side, cursor_rect)| SelectionHandle {
+                            side,
+                            cursor_rect: cursor_rect.translate(origin),
+                        })
+                        .filter(|handle| visible.contains(handle.cursor_rect.center())),
+                    );
+                }
+                _ => {
+                    if self.probe.is_none() {
+                        self.probe = Some(Probe::Pending(selection));
+                        ctx.request_repaint();
+                    }
+                }
+            }
+        }
+
+        let dragging = self.drag.as_ref().is_some_and(|drag| drag.pressed);
+        if dragging && TextMagnifier::enabled(ctx) {
+            self.magnifier = Some(Magnifier::new(ctx, ime.cursor_rect));
+        }
+
+        let holding = self.long_press.as_ref().is_some_and(|press| press.fired);
+        if self.drag.is_some()
+            || holding
+            || self.toolbar_dismissed
+            || !(start != end || self.toolbar_requested)
+        {
+            return;
+        }
+
+        let mut actions = Vec::new();
+        if start != end {
+            actions.extend([TextAction::Cut, TextAction::Copy]);
+        }
+        actions.extend([TextAction::Paste, TextAction::SelectAll]);
+
+        let target = self
+            .handles
+            .iter()
+            .map(|handle| handle.cursor_rect.union(handle.rect()))
+            .reduce(Rect::union)
+            .unwrap_or(ime.cursor_rect);
+        if TextSelectionMenu::load(ctx) == TextSelectionMenu::System {
+            self.system_menu = Some(TextActionMenu {
+                rect: target,
+                actions,
+                selected_text: None,
+            });
+            return;
+        }
+
+        let painter = ctx.layer_painter(Self::layer_id(Order::Tooltip));
+        self.toolbar = Some(TextToolbar::new(
+            &painter,
+            &actions,
+            target,
+            ctx.content_rect(),
+        ));
+    }
 }
 
 impl Plugin for TextPlugin {
@@ -31,75 +419,160 @@ impl Plugin for TextPlugin {
     }
 
     fn input_hook(&mut self, input: &mut RawInput) {
-        // We don't want any user interaction with the input menu to steal focus
-        // from any currently-focused text element, so we look for such events
-        // here, record them, and then remove them to prevent them from causing
-        // text focus to be lost.
-        if let Some(menu) = self.menu.as_mut() {
-            input.events.retain(|event| {
-                if let Event::PointerButton {
+        let time = input.time.unwrap_or_default();
+        let incoming =
+            std::mem::replace(&mut input.events, std::mem::take(&mut self.deferred_events));
+        let out = &mut input.events;
+
+        // Where events start being held back for the next pass, if they are.
+        let mut deferred_from = None;
+
+        for event in incoming {
+            match event {
+                Event::PointerButton {
                     pos,
-                    button,
-                    pressed,
-                    modifiers,
-                } = event
-                {
-                    if menu.area.contains(*pos) {
-                        self.captured_events.push(event.clone());
-                        return false;
+                    button: PointerButton::Primary,
+                    pressed: true,
+                    ..
+                } => {
+                    if !self.handle_press(pos, time) {
+                        out.push(event);
                     }
                 }
 
-                true
-            });
+                Event::PointerButton {
+                    pos,
+                    button: PointerButton::Primary,
+                    pressed: false,
+                    ..
+                } => {
+                    if !self.handle_release(pos, out) {
+                        out.push(event);
+                    }
+                }
+
+                Event::PointerMoved(pos) => {
+                    if let Some(drag) = self.drag.as_mut() {
+                        if !drag.pressed {
+                            drag.pressed = true;
+                            out.push(primary_button(drag.anchor, true));
+                            deferred_from.get_or_insert(out.len());
+                        }
+                        out.push(Event::PointerMoved(pos + drag.offset));
+                        continue;
+                    }
+
+                    if self.toolbar_press.is_some() {
+                        continue;
+                    }
+
+                    if let Some(press) = &self.long_press {
+                        if press.fired {
+                            continue;
+                        }
+                        if press.origin.distance(pos) > self.input_options.max_click_dist {
+                            self.long_press = None;
+                        }
+                    }
+
+                    out.push(event);
+                }
+
+                Event::Touch {
+                    phase: TouchPhase::Cancel,
+                    ..
+                } => {
+                    self.long_press = None;
+                    self.drag = None;
+                    self.toolbar_press = None;
+                    out.push(event);
+                }
+
+                event => out.push(event),
+            }
+        }
+
+        if let Some(index) = deferred_from {
+            self.deferred_events = out.split_off(index);
+        } else {
+            self.fire_long_press(time, out);
         }
     }
 
-    fn output_hook(&mut self, output: &mut FullOutput) {
-        // Check if we need to show the text context menu.
-        if let Some(ime) = output.platform_output.ime.as_mut() {
-            // Configure the popup menu.
-            let had_menu_before = self.menu.is_some();
-
-            self.menu = Some(MenuInfo {
-                layer: LayerId::debug(),
-                area: Rect::from_min_size(ime.rect.min - vec2(0.0, 40.0), vec2(100.0, 30.0)),
-            });
+    fn on_begin_pass(&mut self, ctx: &Context) {
+        self.input_options = ctx.options(|options| options.input_options);
+
+        if let Some(press) = self.long_press.as_ref().filter(|press| !press.fired) {
+            let elapsed = ctx.input(|input| input.time) - press.start_time;
+            ctx.request_repaint_after_secs((LONG_PRESS_DURATION - elapsed).max(0.0) as f32);
+        }
+
+        if !self.deferred_events.is_empty() {
+            ctx.request_repaint();
+        }
 
-            if !had_menu_before {
-                // need repaint
+        for action in std::mem::take(&mut self.actions) {
+            let command = match action {
+                TextAction::Cut => ViewportCommand::RequestCut,
+                TextAction::Copy => ViewportCommand::RequestCopy,
+                TextAction::Paste => ViewportCommand::RequestPaste,
+                TextAction::SelectAll => continue,
+            };
+            ctx.send_viewport_cmd(command);
+            self.dismiss_toolbar();
+        }
+
+        if let Some(Probe::Pending(selection)) = self.probe {
+            self.probe = None;
+
+            let focused = ctx.memory(|memory| memory.focused());
+            let state = self
+                .field
+                .filter(|id| focused == Some(*id))
+                .and_then(|id| Some((id, TextEditState::load(ctx, id)?)));
+
+            if let Some((id, mut state)) = state {
+                if state.cursor.char_range() == Some(selection) {
+                    state.cursor.set_char_range(Some(CCursorRange {
+                        primary: selection.secondary,
+                        secondary: selection.primary,
+                        h_pos: None,
+                    }));
+                    state.store(ctx, id);
+                    self.probe = Some(Probe::Active(selection));
+                }
             }
-        } else {
-            self.menu = None;
         }
     }
 
     fn on_end_pass(&mut self, ctx: &Context) {
-        if let Some(menu) = self.menu.as_ref() {
-            let painter = ctx.layer_painter(menu.layer).with_clip_rect(menu.area);
-
-            painter.rect_filled(menu.area, 3.0, Color32::GRAY);
-            painter.text(
-                menu.area.left_center() + vec2(0.0, 0.0),
-                Align2::LEFT_CENTER,
-                "Cut",
-                FontId::proportional(12.0),
-                Color32::BLACK,
-            );
-            painter.text(
-                menu.area.left_center() + vec2(40.0, 0.0),
-                Align2::LEFT_CENTER,
-                "Copy",
-                FontId::proportional(12.0),
-                Color32::BLACK,
-            );
-            painter.text(
-                menu.area.left_center() + vec2(80.0, 0.0),
-                Align2::LEFT_CENTER,
-                "Paste",
-                FontId::proportional(12.0),
-                Color32::BLACK,
-            );
+        self.update_selection(ctx);
+
+        let painter = ctx.layer_painter(Self::layer_id(Order::Foreground));
+        let color = ctx.style().visuals.selection.bg_fill;
+        for handle in &self.handles {
+            handle.paint(&painter, color);
+        }
+
+        if let Some(toolbar) = &self.toolbar {
+            toolbar.paint(&ctx.layer_painter(Self::layer_id(Order::Tooltip)));
         }
     }
+
+    fn output_hook(&mut self, output: &mut FullOutput) {
+        self.text_under_pointer = output.platform_output.mutable_text_under_cursor;
+
+        if let Some(magnifier) = &self.magnifier {
+            magnifier.paint(&mut output.shapes);
+        }
+    }
+}
+
+fn primary_button(pos: Pos2, pressed: bool) -> Event {
+    Event::PointerButton {
+        pos,
+        button: PointerButton::Primary,
+        pressed,
+        modifiers: Modifiers::NONE,
+    }
 }
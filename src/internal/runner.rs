@@ -1,37 +1,110 @@
 use super::{
-    graphics::GraphicsContext, ime::show_hide_keyboard, input::InputHandler, state::AppState,
+    input::{focus_first_widget, InputHandler},
+    platform::{
+        input::{InputEvent, InputStatus, TextAction, TextActionMenu},
+        ClipboardContent, Clock, Graphics, Platform, PlatformEvent, Renderer,
+    },
+    plugins::{dismiss_text_toolbar, text_action_menu, TextMagnifier},
+    state::AppState,
+    storage::FileStorage,
+    text_input::{push_select_all, TextInputSync},
 };
-use crate::Activity;
-use android_activity::{
-    input::{TextInputState, TextSpan},
-    AndroidApp, MainEvent, PollEvent, WindowManagerFlags,
+use crate::{
+    gamepad::Gamepads,
+    share::{take_share_requests, ShareCallback, ShareResult},
+    stylus::StylusSamples,
+    system_bars::requested_system_bars,
+    text_input_options, window_insets, Activity, GraphicsError, SoftInputAdjust, Storage,
+    SystemBars, TextInputOptions, TextSelectionMenu,
 };
 use egui::{
-    output::OutputEvent, pos2, vec2, Event, FullOutput, Margin, OpenUrl, OutputCommand,
-    PlatformOutput, Pos2, RawInput, Rect, Theme, ViewportCommand, ViewportId, ViewportOutput,
-    WidgetInfo, WidgetType,
+    epaint::MarginF32, output::OutputEvent, vec2, ColorImage, CursorGrab, Event, OpenUrl,
+    OutputCommand, PlatformOutput, Pos2, RawInput, Rect, SafeAreaInsets, Theme, UserData,
+    ViewportCommand, ViewportId, ViewportOutput, WidgetInfo,
 };
-use ndk::configuration::UiModeNight;
+use egui_glow::glow;
 use std::{
     mem::take,
     sync::{Arc, Mutex},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 /// Actual base DPI in Android is 160, but we use a smaller value to get egui to
 /// scale a bit larger, for better legibility on mobile.
 const BASE_DPI: f32 = 160.0;
 
-pub(crate) struct Runner<T: Activity> {
+/// How long to keep checking the window insets after showing or hiding the
+/// soft keyboard, which is animated.
+const KEYBOARD_ANIMATION_DURATION: Duration = Duration::from_secs(1);
+
+/// How often to check the window insets while the soft keyboard is animating.
+const INSETS_REFRESH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long to wait for the activity to report that an app was picked from the
+/// share sheet, after the window regains focus. The report is a broadcast,
+/// which can be delivered after the focus change.
+const SHARE_RESULT_DELAY: Duration = Duration::from_millis(500);
+
+pub(crate) struct Runner<T: Activity, P: Platform> {
     app_state: AppState<T>,
-    android_app: AndroidApp,
-    graphics_context: GraphicsContext,
+    platform: P,
+    clock: Clock,
+    graphics: P::Graphics,
     raw_input: RawInput,
     input_handler: InputHandler,
+    text_input: TextInputSync,
     repaint_info: Arc<Mutex<RepaintInfo>>,
     keyboard_visible: bool,
     close_requested: bool,
     text_selection_widget: Option<WidgetInfo>,
+    storage: Option<FileStorage>,
+    last_auto_save: Instant,
+
+    /// Whether the activity has been resumed since it was created.
+    resumed: bool,
+
+    /// Until when to keep checking the window insets for changes.
+    insets_refresh_deadline: Option<Instant>,
+
+    /// The soft keyboard options most recently applied.
+    applied_text_input_options: TextInputOptions,
+
+    /// The system text selection menu most recently shown.
+    applied_text_action_menu: Option<TextActionMenu>,
+
+    /// Whether showing the system text selection menu failed, in which case
+    /// the egui toolbar is used instead.
+    text_action_menu_failed: bool,
+
+    /// The system bar settings and theme most recently applied.
+    applied_system_bars: Option<(SystemBars, Theme)>,
+
+    /// When the clip the activity was last told about was copied.
+    clipboard_timestamp: Option<i64>,
+
+    /// The share sheet that is open, if its result is wanted.
+    share_sheet: Option<ShareSheet>,
+
+    /// Screenshots egui has asked for that have not been taken yet.
+    screenshot_requests: Vec<UserData>,
+
+    /// The OpenGL context the activity was most recently given.
+    gl: Option<Arc<glow::Context>>,
+
+    /// The wgpu renderer the activity was most recently given.
+    #[cfg(feature = "wgpu")]
+    wgpu_renderer: Option<Arc<egui::mutex::RwLock<egui_wgpu::Renderer>>>,
+}
+
+struct ShareSheet {
+    on_result: ShareCallback,
+
+    /// Whether the window has lost focus to the share sheet yet, after which
+    /// regaining focus means it was closed.
+    lost_focus: bool,
+
+    /// When the window regained focus after the share sheet was closed.
+    closed_at: Option<Instant>,
 }
 
 struct RepaintInfo {
@@ -39,46 +112,85 @@ struct RepaintInfo {
     deadline: Instant,
 }
 
-impl<T: Activity> Runner<T> {
-    pub fn new(android_app: AndroidApp) -> Self {
-        let app_state = AppState::new(T::create());
+impl<T: Activity, P: Platform> Runner<T, P> {
+    pub fn new(platform: P) -> Self {
+        let clock = platform.create_clock();
+        let mut app_state = AppState::new(T::create(), clock());
+
+        // Let the app load any data it persisted in a previous run.
+        let storage = platform.internal_data_path().map(FileStorage::in_dir);
+        if let Some(storage) = &storage {
+            app_state.inner_mut().load(storage);
+        }
 
         let repaint_info = Arc::new(Mutex::new(RepaintInfo {
             needs_repaint: false,
-            deadline: Instant::now(),
+            deadline: clock(),
         }));
 
         // Configure repaint requests to trigger a wake up of the Android event
         // loop.
         app_state.context().set_request_repaint_callback({
-            let waker = android_app.create_waker();
+            let waker = platform.create_waker();
+            let clock = clock.clone();
             let repaint_info = repaint_info.clone();
             move |info| {
                 let mut repaint_info = repaint_info.lock().unwrap();
 
                 repaint_info.needs_repaint = true;
-                repaint_info.deadline = Instant::now() + info.delay;
+                repaint_info.deadline = clock() + info.delay;
 
-                waker.wake();
+                waker();
             }
         });
 
+        let graphics = platform.create_graphics(app_state.inner().graphics_options());
+
         // Register all internal support plugins.
         super::plugins::register_all_plugins(app_state.context());
 
         Self {
             app_state,
-            android_app: android_app.clone(),
-            graphics_context: GraphicsContext::new(),
+            graphics,
+            platform,
+            last_auto_save: clock(),
+            resumed: false,
+            clock,
             raw_input: RawInput::default(),
-            input_handler: InputHandler::new(android_app),
+            input_handler: InputHandler::new(),
+            text_input: TextInputSync::default(),
             repaint_info,
             keyboard_visible: false,
             close_requested: false,
             text_selection_widget: None,
+            storage,
+            insets_refresh_deadline: None,
+            applied_text_input_options: TextInputOptions::default(),
+            applied_text_action_menu: None,
+            text_action_menu_failed: false,
+            applied_system_bars: None,
+            clipboard_timestamp: None,
+            share_sheet: None,
+            screenshot_requests: Vec::new(),
+            gl: None,
+            #[cfg(feature = "wgpu")]
+            wgpu_renderer: None,
         }
     }
 
+    pub(crate) fn app_state(&self) -> &AppState<T> {
+        &self.app_state
+    }
+
+    pub(crate) fn app_state_mut(&mut self) -> &mut AppState<T> {
+        &mut self.app_state
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.close_requested
+    }
+
+    #[cfg(target_os = "android")]
     pub(crate) fn run_until_closed(&mut self) {
         while !self.close_requested {
             self.run_once();
@@ -92,19 +204,22 @@ impl<T: Activity> Runner<T> {
         if repaint_info.needs_repaint {
             let duration = repaint_info
                 .deadline
-                .saturating_duration_since(Instant::now());
+                .saturating_duration_since((self.clock)());
 
             timeout = timeout.map(|d| d.min(duration)).or(Some(duration));
         }
 
         drop(repaint_info);
 
-        self.android_app.clone().poll_events(timeout, |event| {
+        self.platform.clone().poll_events(timeout, |event| {
             self.process_event(event);
         });
 
         // Event handled, now check if we need to repaint.
+        self.refresh_insets_if_needed();
         self.repaint_if_needed();
+        self.finish_share_if_closed();
+        self.auto_save_if_needed();
     }
 
     fn request_repaint(&self) {
@@ -112,126 +227,231 @@ impl<T: Activity> Runner<T> {
     }
 
     fn attach_window_if_needed(&mut self) {
-        if let Some(native_window) = self.android_app.native_window() {
-            self.graphics_context.attach_window(native_window);
-        };
+        if let Err(e) = self.platform.attach_window(&mut self.graphics) {
+            self.handle_graphics_error(e);
+        }
+
+        self.notify_graphics_context_if_changed();
+    }
+
+    /// Give the activity the graphics context if it has never seen it before.
+    fn notify_graphics_context_if_changed(&mut self) {
+        if let Some(gl) = self.graphics.gl() {
+            if !self.gl.as_ref().is_some_and(|old| Arc::ptr_eq(old, &gl)) {
+                self.app_state.inner_mut().on_gl_context_created(&gl);
+                self.gl = Some(gl);
+            }
+        }
+
+        #[cfg(feature = "wgpu")]
+        if let Some(render_state) = self.graphics.wgpu_render_state() {
+            let renderer = &render_state.renderer;
+            if !self
+                .wgpu_renderer
+                .as_ref()
+                .is_some_and(|old| Arc::ptr_eq(old, renderer))
+            {
+                self.app_state
+                    .inner_mut()
+                    .on_wgpu_render_state_created(&render_state);
+                self.wgpu_renderer = Some(renderer.clone());
+            }
+        }
+    }
+
+    fn handle_graphics_error(&mut self, error: GraphicsError) {
+        log::error!("graphics error: {error}");
+        self.app_state.inner_mut().on_graphics_error(&error);
     }
 
-    fn process_event(&mut self, event: PollEvent) {
+    fn process_event(&mut self, event: PlatformEvent) {
         match event {
-            PollEvent::Wake => {}
-            PollEvent::Timeout => {
+            PlatformEvent::Wake => {}
+            PlatformEvent::Timeout => {
                 // info!("Timed out");
                 // Real app would probably rely on vblank sync via graphics API...
                 self.request_repaint();
             }
-            PollEvent::Main(main_event) => match main_event {
-                MainEvent::Destroy => {
-                    self.graphics_context.detach_window();
-                    self.close_requested = true;
-                }
+            PlatformEvent::Destroy => {
+                self.graphics.detach_window();
+                self.save();
+                self.app_state.inner_mut().on_destroy();
+                self.close_requested = true;
+            }
 
-                MainEvent::InitWindow { .. } => {
-                    // TODO: Need to reset textures
-                    // self.app_state = AppState::new(T::create());
-                    self.apply_current_config();
-                    self.attach_window_if_needed();
-                    self.request_repaint();
-                }
+            PlatformEvent::Start => self.app_state.inner_mut().on_start(),
+
+            PlatformEvent::Pause => {
+                self.save();
+                self.app_state.inner_mut().on_pause();
+            }
+
+            PlatformEvent::Stop => {
+                self.graphics.detach_window();
+                self.save();
+                self.app_state.inner_mut().on_stop();
+            }
+
+            PlatformEvent::InitWindow => {
+                self.applied_system_bars = None;
+                self.apply_current_config();
+                self.attach_window_if_needed();
+                self.app_state.inner_mut().on_window_created();
+                self.request_repaint();
+            }
 
-                MainEvent::TerminateWindow { .. } | MainEvent::Stop => {
-                    self.graphics_context.detach_window();
+            PlatformEvent::TerminateWindow => {
+                self.graphics.detach_window();
+                self.app_state.inner_mut().on_window_destroyed();
+            }
+
+            PlatformEvent::WindowResized => {
+                self.apply_current_config();
+                if let Some(mut renderer) = self.graphics.renderer() {
+                    renderer.handle_resize();
                 }
+                self.request_repaint();
+            }
 
-                MainEvent::WindowResized { .. } => {
-                    self.apply_current_config();
-                    if let Some(renderer) = self.graphics_context.renderer() {
-                        renderer.handle_resize();
+            PlatformEvent::GainedFocus => {
+                self.update_focus(true);
+                self.app_state.inner_mut().on_focus_changed(true);
+
+                // Other apps may have copied something while we were in the
+                // background, and only now can the clipboard be read again.
+                self.check_clipboard();
+                if let Some(share_sheet) = &mut self.share_sheet {
+                    if share_sheet.lost_focus {
+                        share_sheet.closed_at.get_or_insert((self.clock)());
                     }
-                    self.request_repaint();
                 }
+                self.request_repaint();
+            }
 
-                MainEvent::GainedFocus => {
-                    self.update_focus(true);
-                    self.request_repaint();
+            PlatformEvent::LostFocus => {
+                self.update_focus(false);
+                self.app_state.inner_mut().on_focus_changed(false);
+                if let Some(share_sheet) = &mut self.share_sheet {
+                    share_sheet.lost_focus = true;
                 }
+                self.request_repaint();
+            }
 
-                MainEvent::LostFocus => {
-                    self.update_focus(false);
-                    self.request_repaint();
-                }
+            PlatformEvent::RedrawNeeded => self.request_repaint(),
 
-                MainEvent::RedrawNeeded { .. } => self.request_repaint(),
+            PlatformEvent::LowMemory => self.app_state.inner_mut().on_low_memory(),
 
-                MainEvent::LowMemory => self.app_state.inner_mut().on_low_memory(),
+            PlatformEvent::ContentRectChanged => {
+                let content_rect = self.platform.content_rect();
+                self.app_state
+                    .inner_mut()
+                    .on_content_rect_changed(content_rect);
+                self.apply_current_config();
+                self.request_repaint();
+            }
 
-                MainEvent::ContentRectChanged { .. } => {
-                    let content_rect = self.android_app.content_rect();
-                    let egui_rect = Rect::from_two_pos(
-                        pos2(content_rect.left as _, content_rect.top as _),
-                        pos2(content_rect.right as _, content_rect.bottom as _),
-                    );
-                    self.app_state
-                        .inner_mut()
-                        .on_content_rect_changed(egui_rect);
-                    self.apply_current_config();
-                }
+            PlatformEvent::InsetsChanged => {
+                self.apply_current_config();
+                self.request_repaint();
+            }
 
-                MainEvent::ConfigChanged { .. } => {
-                    self.apply_current_config();
-                    self.request_repaint();
-                }
+            PlatformEvent::ConfigChanged => {
+                // Android may have reset the system bars, such as when
+                // switching between light and dark mode.
+                self.applied_system_bars = None;
+                self.apply_current_config();
+                self.request_repaint();
+            }
 
-                MainEvent::InputAvailable => {
-                    self.process_pending_input();
-                    self.request_repaint();
-                }
+            PlatformEvent::InputAvailable => {
+                self.process_pending_input();
+                self.request_repaint();
+            }
 
-                // The app is going to be suspended.
-                MainEvent::SaveState { saver, .. } => {
-                    // To make the app easily resumable, we serialize the egui
-                    // memory into the persistence region Android provides.
-                    if let Some(memory) = self.app_state.save_memory() {
-                        saver.store(&memory);
-                    }
-                }
+            // The app is going to be suspended.
+            PlatformEvent::SaveState { saved_state } => {
+                // To make the app easily resumable, we serialize the egui
+                // memory and the app's own state into the persistence region
+                // Android provides.
+                *saved_state = self.app_state.save_state();
+            }
 
-                MainEvent::Resume { loader, .. } => {
-                    // If Android remembers the data we saved previously,
-                    // re-hydrate it.
-                    if let Some(bytes) = loader.load() {
-                        self.app_state.load_memory(&bytes);
+            PlatformEvent::Resume { saved_state } => {
+                // If Android remembers the data we saved previously,
+                // re-hydrate it. Android passes the same data on every resume,
+                // but after the first the app already has newer state in
+                // memory.
+                if !self.resumed {
+                    if let Some(bytes) = saved_state {
+                        self.app_state.load_state(&bytes);
                     }
                 }
+                self.resumed = true;
 
-                main_event => log::warn!("unknown main event: {main_event:?}"),
-            },
-            _ => {}
+                self.app_state.inner_mut().on_resume();
+            }
         }
     }
 
     fn process_pending_input(&mut self) {
         let pixels_per_point = self.pixels_per_point();
+        self.input_handler
+            .set_focus_navigation(self.app_state.inner().focus_navigation());
 
-        match self.android_app.input_events_iter() {
-            Ok(mut iter) => loop {
-                let read_input = iter.next(|event| {
-                    self.input_handler
-                        .process(event, pixels_per_point, &mut self.raw_input)
-                });
-
-                if !read_input {
-                    break;
-                }
-            },
-            Err(err) => {
-                log::error!("failed to get input events iterator: {err:?}");
+        self.platform.clone().input_events(|event| match event {
+            &InputEvent::TextAction(action) => {
+                self.handle_text_action(action);
+                InputStatus::Handled
             }
+            event => self
+                .input_handler
+                .process(event, pixels_per_point, &mut self.raw_input),
+        });
+    }
+
+    /// Apply an item picked from the system text selection menu to the focused
+    /// text field.
+    fn handle_text_action(&mut self, action: Option<TextAction>) {
+        log::debug!("text action: {action:?}");
+
+        match action {
+            Some(TextAction::Cut) => self.raw_input.events.push(Event::Cut),
+            Some(TextAction::Copy) => self.raw_input.events.push(Event::Copy),
+            Some(TextAction::Paste) => self.paste(),
+            Some(TextAction::SelectAll) => push_select_all(&mut self.raw_input.events),
+            // The menu was closed by the system.
+            None => self.applied_text_action_menu = None,
+        }
+
+        // Android keeps the menu open after selecting all, as there are
+        // still things to do with the new selection.
+        if action != Some(TextAction::SelectAll) {
+            dismiss_text_toolbar(self.app_state.context());
+        }
+
+        self.request_repaint();
+    }
+
+    /// Give the app a chance to write to storage, and flush it to disk.
+    fn save(&mut self) {
+        if let Some(storage) = &mut self.storage {
+            self.app_state.inner_mut().save(storage);
+            storage.flush();
+        }
+
+        self.last_auto_save = (self.clock)();
+    }
+
+    fn auto_save_if_needed(&mut self) {
+        let interval = self.app_state.inner().auto_save_interval();
+
+        if (self.clock)().saturating_duration_since(self.last_auto_save) >= interval {
+            self.save();
         }
     }
 
     fn repaint_if_needed(&mut self) {
-        self.app_state.update_clock();
+        self.app_state.update_clock((self.clock)());
 
         let mut repaint_info = self.repaint_info.lock().unwrap();
 
@@ -245,20 +465,73 @@ impl<T: Activity> Runner<T> {
     /// Do a full app update. Input events will be passed into egui, the user's
     /// update routine will be called, and the UI will be redrawn.
     fn repaint(&mut self) {
-        let mut full_output = self.app_state.update(self.raw_input.take());
+        if let Some(state) = self.input_handler.take_text_input_state() {
+            self.text_input
+                .apply(self.app_state.context(), state, &mut self.raw_input);
+        }
+        self.text_input
+            .apply_ime_actions(self.app_state.context(), &mut self.raw_input);
+        StylusSamples::store(
+            self.input_handler.take_stylus_samples(),
+            self.app_state.context(),
+        );
+        Gamepads::store(
+            self.input_handler.take_gamepad_states(),
+            self.app_state.context(),
+        );
+        if self.app_state.inner().focus_navigation() {
+            focus_first_widget(self.app_state.context(), &mut self.raw_input);
+        }
 
-        if let Some(mut renderer) = self.graphics_context.renderer() {
-            if full_output.platform_output.requested_discard() {
-                self.request_repaint();
-            } else {
-                let clipped_primitives = self
-                    .app_state
-                    .context()
-                    .tessellate(take(&mut full_output.shapes), full_output.pixels_per_point);
+        let mut full_output = self.app_state.update(self.raw_input.take());
+        self.apply_system_bars_if_changed();
+        self.start_requested_shares();
 
-                renderer.repaint(&mut full_output, &clipped_primitives);
+        // Screenshots have to be taken while painting, before the frame is
+        // presented. Requests are kept until a frame is actually painted.
+        if let Some(viewport_output) = full_output.viewport_output.get(&ViewportId::ROOT) {
+            self.screenshot_requests
+                .extend(
+                    viewport_output
+                        .commands
+                        .iter()
+                        .filter_map(|command| match command {
+                            ViewportCommand::Screenshot(user_data) => Some(user_data.clone()),
+                            _ => None,
+                        }),
+                );
+        }
+        let screenshot = !self.screenshot_requests.is_empty();
+
+        let result = match self.graphics.renderer() {
+            Some(mut renderer) => {
+                if full_output.platform_output.requested_discard() {
+                    self.app_state.context().request_repaint();
+                    Some(Ok(None))
+                } else {
+                    let clipped_primitives = self
+                        .app_state
+                        .context()
+                        .tessellate(take(&mut full_output.shapes), full_output.pixels_per_point);
+
+                    Some(renderer.repaint(&mut full_output, &clipped_primitives, screenshot))
+                }
             }
+            None => None,
+        };
+
+        let has_window = result.is_some();
 
+        match result {
+            Some(Ok(Some(image))) => self.deliver_screenshot(image),
+            Some(Err(e)) => self.handle_graphics_error(e),
+            _ => {}
+        }
+
+        // The context may have been recreated while drawing.
+        self.notify_graphics_context_if_changed();
+
+        if has_window {
             self.handle_platform_output(full_output.platform_output);
         }
 
@@ -267,92 +540,202 @@ impl<T: Activity> Runner<T> {
         }
     }
 
+    /// Apply the app's system bar settings if they, or the theme they depend
+    /// on, have changed since they were last applied.
+    fn apply_system_bars_if_changed(&mut self) {
+        let context = self.app_state.context();
+        let Some(system_bars) = requested_system_bars(context) else {
+            return;
+        };
+
+        let theme = system_bars.theme.unwrap_or_else(|| context.theme());
+        if self.applied_system_bars == Some((system_bars, theme)) {
+            return;
+        }
+
+        self.platform.set_system_bars(system_bars, theme);
+        self.applied_system_bars = Some((system_bars, theme));
+    }
+
+    /// Open the share sheet for anything the app asked to share.
+    fn start_requested_shares(&mut self) {
+        for request in take_share_requests(self.app_state.context()) {
+            log::info!("share requested");
+
+            // Only one share sheet can be open at a time, so an earlier one
+            // has been replaced.
+            if let Some(share_sheet) = self.share_sheet.take() {
+                (share_sheet.on_result)(ShareResult::Closed);
+            }
+
+            match self.platform.share(&request.content) {
+                Ok(()) => {
+                    self.share_sheet = request.on_result.map(|on_result| ShareSheet {
+                        on_result,
+                        lost_focus: false,
+                        closed_at: None,
+                    });
+                }
+                Err(e) => {
+                    log::error!("failed to share: {e}");
+                    if let Some(on_result) = request.on_result {
+                        on_result(ShareResult::Failed(e.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Report the result of the share sheet if it has been closed.
+    fn finish_share_if_closed(&mut self) {
+        let Some(closed_at) = self
+            .share_sheet
+            .as_ref()
+            .and_then(|share_sheet| share_sheet.closed_at)
+        else {
+            return;
+        };
+
+        let result = match self.platform.share_target_chosen() {
+            Some(true) => ShareResult::Shared,
+            Some(false) => {
+                // Give the report that an app was picked a chance to arrive.
+                let waited = (self.clock)().saturating_duration_since(closed_at);
+                if waited < SHARE_RESULT_DELAY {
+                    self.app_state
+                        .context()
+                        .request_repaint_after(SHARE_RESULT_DELAY - waited);
+                    return;
+                }
+                ShareResult::Dismissed
+            }
+            None => ShareResult::Closed,
+        };
+        let share_sheet = self.share_sheet.take().unwrap();
+        log::info!("share sheet closed: {result:?}");
+        (share_sheet.on_result)(result);
+        self.request_repaint();
+    }
+
+    /// Set up the soft keyboard for the focused text field, if it needs
+    /// different options from the last one.
+    fn apply_text_input_options_if_changed(&mut self, platform_output: &PlatformOutput) {
+        let context = self.app_state.context();
+        let Some(id) = platform_output
+            .ime
+            .as_ref()
+            .and_then(|_| context.memory(|memory| memory.focused()))
+        else {
+            return;
+        };
+
+        let options = text_input_options(context, id);
+        if self.applied_text_input_options == options {
+            return;
+        }
+
+        self.platform.set_text_input_options(options);
+        self.applied_text_input_options = options;
+
+        // The keyboard only picks up new options when it is shown.
+        if self.keyboard_visible {
+            self.platform.show_soft_input();
+        }
+    }
+
+    /// Show, move or hide the system text selection menu to match the last
+    /// pass.
+    fn apply_text_action_menu_if_changed(&mut self) {
+        let context = self.app_state.context();
+        let pixels_per_point = self.pixels_per_point();
+        let menu = text_action_menu(context).map(|menu| TextActionMenu {
+            rect: menu.rect * pixels_per_point,
+            selected_text: self.text_input.selected_text(context),
+            ..menu
+        });
+
+        if self.applied_text_action_menu == menu {
+            return;
+        }
+
+        if let Err(e) = self.platform.set_text_action_menu(menu.clone()) {
+            log::warn!(
+                "failed to show the system text selection menu, using the egui toolbar: {e}"
+            );
+            self.text_action_menu_failed = true;
+            self.apply_current_config();
+            self.request_repaint();
+            return;
+        }
+
+        self.applied_text_action_menu = menu;
+    }
+
+    /// Answer all pending screenshot requests with the given image.
+    fn deliver_screenshot(&mut self, image: ColorImage) {
+        let image = Arc::new(image);
+
+        for user_data in self.screenshot_requests.drain(..) {
+            self.raw_input.events.push(Event::Screenshot {
+                viewport_id: ViewportId::ROOT,
+                user_data,
+                image: image.clone(),
+            });
+        }
+
+        self.request_repaint();
+    }
+
     fn handle_platform_output(&mut self, platform_output: PlatformOutput) {
+        // Keep the soft keyboard's copy of the focused text up to date, before
+        // it is shown.
+        if let Some(state) = self
+            .text_input
+            .finish(self.app_state.context(), &platform_output)
+        {
+            self.platform.set_text_input_state(state);
+        }
+
+        self.apply_text_input_options_if_changed(&platform_output);
+        self.apply_text_action_menu_if_changed();
+
         // Check if egui wants to show or hide the keyboard, based on the
         // last UI update.
 
         match (platform_output.ime.is_some(), self.keyboard_visible) {
             (true, false) => {
                 log::info!("show keyboard requested");
-                show_hide_keyboard(&self.android_app, true);
+                self.platform.show_soft_input();
                 self.keyboard_visible = true;
+                self.insets_refresh_deadline = Some((self.clock)() + KEYBOARD_ANIMATION_DURATION);
                 // self.raw_input.events.push(Event::Ime(ImeEvent::Enabled));
                 self.request_repaint();
             }
             (false, true) => {
                 log::info!("hide keyboard requested");
-                // show_hide_keyboard(&self.android_app, false);
-                self.android_app.hide_soft_input(false);
+                self.platform.hide_soft_input();
                 self.keyboard_visible = false;
+                self.insets_refresh_deadline = Some((self.clock)() + KEYBOARD_ANIMATION_DURATION);
                 // self.raw_input.events.push(Event::Ime(ImeEvent::Disabled));
                 self.request_repaint();
             }
             _ => {}
         }
 
-        // if let Some(ime) = full_output.platform_output.ime.as_ref() {
-        //     self.android_app.set_text_input_state(TextInputState {
-        //         text: "".into(),
-        //         selection: ime.selection,
-        //         compose_region: None,
-        //     });
-        // }
-
         for event in platform_output.events {
-            log::info!("output event: {event:?}");
+            log::trace!("output event: {event:?}");
 
             match event {
-                OutputEvent::Clicked(info) => {
-                    if info.typ == WidgetType::TextEdit {
-                        self.android_app.set_text_input_state(TextInputState {
-                            text: info.current_text_value.unwrap_or_default(),
-                            selection: TextSpan { start: 0, end: 0 },
-                            compose_region: None,
-                        });
-                    } else {
-                        self.android_app.set_text_input_state(TextInputState {
-                            text: Default::default(),
-                            selection: TextSpan { start: 0, end: 0 },
-                            compose_region: None,
-                        });
-                    }
-                }
-
-                // TODO: We should open a custom context menu for copy and paste.
-                OutputEvent::DoubleClicked(info) => {
-                    if info.typ == WidgetType::TextEdit {
-                        if let Ok(text) = android_clipboard::get_text() {
-                            self.raw_input.events.push(Event::Paste(text));
-                            self.request_repaint();
-                        }
-                    }
-                }
-
                 OutputEvent::TextSelectionChanged(info) => {
-                    self.text_selection_widget = Some(info.clone());
-
-                    self.android_app.set_text_input_state(TextInputState {
-                        text: info.current_text_value.unwrap_or_default(),
-                        selection: info
-                            .text_selection
-                            .map(|s| TextSpan {
-                                start: *s.start(),
-                                end: *s.end(),
-                            })
-                            .unwrap_or(TextSpan { start: 0, end: 0 }),
-                        compose_region: None,
-                    });
+                    self.text_selection_widget = Some(info);
                 }
 
-                OutputEvent::ValueChanged(info) => {
-                    if info.typ == WidgetType::TextEdit {
-                        self.android_app.set_text_input_state(TextInputState {
-                            text: info.current_text_value.unwrap_or_default(),
-                            selection: TextSpan { start: 0, end: 0 },
-                            compose_region: None,
-                        });
-                    }
-                }
+                // Text field changes are picked up by `TextInputSync`, and
+                // double clicks on text by `TextPlugin`.
+                OutputEvent::Clicked(_)
+                | OutputEvent::DoubleClicked(_)
+                | OutputEvent::FocusGained(_)
+                | OutputEvent::ValueChanged(_) => {}
 
                 event => log::warn!("unsupported output event: {event:?}"),
             }
@@ -366,24 +749,65 @@ impl<T: Activity> Runner<T> {
     fn handle_output_command(&mut self, command: OutputCommand) {
         match command {
             OutputCommand::CopyText(text) => {
-                if let Err(err) = android_clipboard::set_text(text) {
+                if let Err(err) = self.platform.set_clipboard_text(text) {
                     log::error!("failed to copy text to clipboard: {err:?}");
                 }
+                self.check_clipboard();
             }
 
-            OutputCommand::OpenUrl(OpenUrl { url, .. }) => {
-                if let Err(e) = webbrowser::open(&url) {
+            OutputCommand::CopyImage(image) => {
+                if let Err(err) = self.platform.set_clipboard_image(&image) {
+                    log::error!("failed to copy image to clipboard: {err}");
+                }
+                self.check_clipboard();
+            }
+
+            OutputCommand::OpenUrl(OpenUrl { url, new_tab }) => {
+                // Links that would replace the current page on the web stay
+                // within the app in a Custom Tab, and others open the browser.
+                let custom_tab = (!new_tab && self.app_state.inner().open_links_in_custom_tabs())
+                    .then(|| self.app_state.context().style().visuals.panel_fill);
+
+                if let Err(e) = self.platform.open_url(&url, custom_tab) {
                     log::error!("failed to open URL: {e}");
+                    self.app_state.inner_mut().on_open_url_error(&url, &e);
                 }
             }
+        }
+    }
 
-            command => log::warn!("unsupported output command: {command:?}"),
+    /// Paste what is on the clipboard: text into egui, and images into the
+    /// activity, as egui has no way to paste them.
+    fn paste(&mut self) {
+        match self.platform.clipboard() {
+            Ok(ClipboardContent::Text(text)) => {
+                log::info!("paste requested");
+                self.raw_input.events.push(Event::Paste(text));
+                self.request_repaint();
+            }
+            Ok(ClipboardContent::Image(image)) => {
+                log::info!("image paste requested");
+                self.app_state.inner_mut().on_paste_image(image);
+                self.request_repaint();
+            }
+            Ok(ClipboardContent::Empty) => {}
+            Err(e) => log::error!("failed to read clipboard: {e}"),
+        }
+    }
+
+    /// Tell the activity if something new was copied to the clipboard since
+    /// it was last told.
+    fn check_clipboard(&mut self) {
+        let timestamp = self.platform.clipboard_timestamp();
+        if timestamp.is_some() && timestamp != self.clipboard_timestamp {
+            self.clipboard_timestamp = timestamp;
+            self.app_state.inner_mut().on_clipboard_changed();
         }
     }
 
     fn handle_viewport_output(&mut self, viewport_output: &ViewportOutput) {
         for command in &viewport_output.commands {
-            log::info!("viewport command: {command:?}");
+            log::trace!("viewport command: {command:?}");
             match command {
                 ViewportCommand::Close => {
                     self.close_requested = true;
@@ -394,31 +818,18 @@ impl<T: Activity> Runner<T> {
                 }
 
                 &ViewportCommand::Fullscreen(fullscreen) => {
-                    if fullscreen {
-                        self.android_app.set_window_flags(
-                            WindowManagerFlags::FULLSCREEN,
-                            WindowManagerFlags::empty(),
-                        );
-                    } else {
-                        self.android_app.set_window_flags(
-                            WindowManagerFlags::empty(),
-                            WindowManagerFlags::FULLSCREEN,
-                        );
-                    }
+                    self.platform.set_fullscreen(fullscreen);
                 }
 
                 &ViewportCommand::MousePassthrough(passthrough) => {
-                    if passthrough {
-                        self.android_app.set_window_flags(
-                            WindowManagerFlags::NOT_FOCUSABLE,
-                            WindowManagerFlags::empty(),
-                        );
-                    } else {
-                        self.android_app.set_window_flags(
-                            WindowManagerFlags::empty(),
-                            WindowManagerFlags::NOT_FOCUSABLE,
-                        );
-                    }
+                    self.platform.set_focusable(!passthrough);
+                }
+
+                // Android can only hide the cursor and report relative
+                // movement, which is what locking it is for.
+                &ViewportCommand::CursorGrab(grab) => {
+                    self.platform
+                        .set_pointer_capture(grab == CursorGrab::Locked);
                 }
 
                 // All we have to do to implement copy and cut is to tell egui
@@ -435,13 +846,10 @@ impl<T: Activity> Runner<T> {
                     self.request_repaint();
                 }
 
-                ViewportCommand::RequestPaste => match android_clipboard::get_text() {
-                    Ok(text) => {
-                        self.raw_input.events.push(Event::Paste(text));
-                        self.request_repaint();
-                    }
-                    Err(e) => log::error!("failed to get clipboard text: {e:?}"),
-                },
+                ViewportCommand::RequestPaste => self.paste(),
+
+                // Already taken while painting the frame.
+                ViewportCommand::Screenshot(_) => {}
 
                 _ => log::warn!("unsupported viewport command: {command:?}"),
             }
@@ -469,13 +877,9 @@ impl<T: Activity> Runner<T> {
 
     /// Inspect configuration supplied to us from Android, and update our state to match.
     fn apply_current_config(&mut self) {
-        let config = self.android_app.config();
+        let config = self.platform.config();
 
-        self.raw_input.system_theme = match config.ui_mode_night() {
-            UiModeNight::No => Some(Theme::Light),
-            UiModeNight::Yes => Some(Theme::Dark),
-            _ => None,
-        };
+        self.raw_input.system_theme = config.theme;
 
         let viewport_info = self
             .raw_input
@@ -485,46 +889,71 @@ impl<T: Activity> Runner<T> {
 
         // Calculate pixels per point based on screen density.
         viewport_info.native_pixels_per_point =
-            config.density().map(|density| density as f32 / BASE_DPI);
+            config.density.map(|density| density as f32 / BASE_DPI);
         // .map(|ppp| ppp.round());
 
         let pixels_per_point = viewport_info.native_pixels_per_point.unwrap_or(1.0);
 
-        if let Some(renderer) = self.graphics_context.renderer() {
+        if let Some(renderer) = self.graphics.renderer() {
             let [width, height] = renderer.window_size();
             let width = width as f32 / pixels_per_point;
             let height = height as f32 / pixels_per_point;
 
-            // let android_activity::Rect {
-            //     left,
-            //     top,
-            //     right,
-            //     bottom,
-            // } = self.android_app.content_rect();
-
-            // let top_left = pos2(left as f32, top as f32) / pixels_per_point;
-            // let bottom_right = pos2(right as f32, bottom as f32) / pixels_per_point;
+            // let content_rect = self.platform.content_rect();
 
-            // self.screen_rect = Some(Rect::from_two_pos(top_left, bottom_right));
+            // self.screen_rect = Some(Rect::from_min_max(
+            //     content_rect.min / pixels_per_point,
+            //     content_rect.max / pixels_per_point,
+            // ));
             self.raw_input.screen_rect = Some(Rect::from_min_size(
                 Pos2::new(0.0, 0.0),
                 vec2(width, height),
             ));
         }
 
-        self.app_state.context().style_mut(|style| {
-            style.spacing.window_margin = self.window_margin(pixels_per_point);
-        });
+        // Keep content clear of system UI, which the window extends under.
+        let insets = self.platform.window_insets().to_points(pixels_per_point);
+        let soft_input_adjust = self.app_state.inner().soft_input_adjust();
+
+        let mut safe_area = insets;
+        if soft_input_adjust == SoftInputAdjust::Pan {
+            // The keyboard is made room for by panning instead, see
+            // `KeyboardPlugin`.
+            safe_area.ime = MarginF32::ZERO;
+        }
+        self.raw_input.safe_area_insets = Some(SafeAreaInsets(safe_area.safe_area_insets()));
+
+        insets.store(self.app_state.context());
+        soft_input_adjust.store(self.app_state.context());
+        TextMagnifier(self.app_state.inner().show_text_magnifier()).store(self.app_state.context());
+
+        let text_selection_menu = match self.app_state.inner().text_selection_menu() {
+            TextSelectionMenu::System if self.text_action_menu_failed => TextSelectionMenu::Egui,
+            menu => menu,
+        };
+        text_selection_menu.store(self.app_state.context());
     }
 
-    fn window_margin(&self, pixels_per_point: f32) -> Margin {
-        let content_rect = self.android_app.content_rect();
+    /// Keep checking the window insets while the soft keyboard is opening or
+    /// closing, since its size is not always reported with an event.
+    fn refresh_insets_if_needed(&mut self) {
+        let Some(deadline) = self.insets_refresh_deadline else {
+            return;
+        };
+
+        if (self.clock)() >= deadline {
+            self.insets_refresh_deadline = None;
+        } else {
+            self.app_state
+                .context()
+                .request_repaint_after(INSETS_REFRESH_INTERVAL);
+        }
+
+        let insets = window_insets(self.app_state.context());
+        self.apply_current_config();
 
-        Margin {
-            left: (content_rect.left as f32 / pixels_per_point) as i8,
-            right: 0,  // (content_rect.right as f32 / pixels_per_point) as i8,
-            top: 127,  // + (content_rect.top as f32 / pixels_per_point) as i8,
-            bottom: 0, //(content_rect.bottom as f32 / pixels_per_point) as i8,
+        if window_insets(self.app_state.context()) != insets {
+            self.request_repaint();
         }
     }
 }
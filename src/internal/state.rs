@@ -1,7 +1,25 @@
 use crate::Activity;
 use egui::{Context, FullOutput, RawInput};
+use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
+/// Version of the [`SavedState`] format. This must be incremented whenever the
+/// format changes, so that state saved by an older version of an app is not
+/// misinterpreted after an upgrade.
+const SAVED_STATE_VERSION: u32 = 1;
+
+/// Envelope for all state saved when the activity is suspended.
+#[derive(Deserialize, Serialize)]
+struct SavedState {
+    version: u32,
+
+    /// Serialized egui memory.
+    memory: Option<Vec<u8>>,
+
+    /// State returned by [`Activity::save_state`].
+    app: Option<Vec<u8>>,
+}
+
 /// Wrap an `App` and manages its egui state and execution lifecycle.
 ///
 /// This does not handle any platform APIS, just compartmentalizes purely
@@ -14,10 +32,9 @@ pub(crate) struct AppState<T> {
 }
 
 impl<T: Activity> AppState<T> {
-    /// Create a new `AppState` with the given `App`.
-    pub(crate) fn new(app: T) -> Self {
-        let now = Instant::now();
-
+    /// Create a new `AppState` with the given `App`, starting the clock at
+    /// `now`.
+    pub(crate) fn new(app: T, now: Instant) -> Self {
         Self {
             app,
             context: Context::default(),
@@ -51,13 +68,54 @@ impl<T: Activity> AppState<T> {
         })
     }
 
+    /// Export the state of both egui and the app to a byte array.
+    pub(crate) fn save_state(&self) -> Option<Vec<u8>> {
+        let saved_state = SavedState {
+            version: SAVED_STATE_VERSION,
+            memory: self.save_memory(),
+            app: self.app.save_state(),
+        };
+
+        bincode::serialize(&saved_state)
+            .inspect_err(|e| log::warn!("failed to serialize saved state: {e}"))
+            .ok()
+    }
+
+    /// Restore the state of both egui and the app from a byte array created by
+    /// [`AppState::save_state`].
+    pub(crate) fn load_state(&mut self, bytes: &[u8]) {
+        let saved_state = match bincode::deserialize::<SavedState>(bytes) {
+            Ok(saved_state) => saved_state,
+            Err(e) => {
+                log::warn!("failed to deserialize saved state: {e}");
+                return;
+            }
+        };
+
+        if saved_state.version != SAVED_STATE_VERSION {
+            log::warn!(
+                "ignoring saved state with unsupported version {}",
+                saved_state.version
+            );
+            return;
+        }
+
+        if let Some(memory) = saved_state.memory {
+            self.load_memory(&memory);
+        }
+
+        if let Some(app) = saved_state.app {
+            self.app.restore_state(&app);
+        }
+    }
+
     /// Export the current egui memory to a byte array.
-    pub(crate) fn save_memory(&self) -> Option<Vec<u8>> {
+    fn save_memory(&self) -> Option<Vec<u8>> {
         self.context.memory(bincode::serialize).ok()
     }
 
     /// Restore egui memory from a byte array.
-    pub(crate) fn load_memory(&mut self, bytes: &[u8]) {
+    fn load_memory(&mut self, bytes: &[u8]) {
         match bincode::deserialize(bytes) {
             Ok(saved_memory) => {
                 self.context.memory_mut(|memory| {
@@ -70,7 +128,7 @@ impl<T: Activity> AppState<T> {
         }
     }
 
-    pub(crate) fn update_clock(&mut self) {
-        self.now = Instant::now();
+    pub(crate) fn update_clock(&mut self, now: Instant) {
+        self.now = now;
     }
 }
@@ -0,0 +1,85 @@
+use crate::Storage;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Name of the file inside the app's internal data directory that holds the
+/// storage contents.
+const FILE_NAME: &str = "egui_android_storage.bin";
+
+/// A [`Storage`] that is persisted as a single file.
+pub(crate) struct FileStorage {
+    path: PathBuf,
+    values: HashMap<String, String>,
+    dirty: bool,
+}
+
+impl FileStorage {
+    /// Open the storage in the given directory, loading any values previously
+    /// saved there.
+    pub(crate) fn in_dir(dir: PathBuf) -> Self {
+        let path = dir.join(FILE_NAME);
+
+        let values = match fs::read(&path) {
+            Ok(bytes) => bincode::deserialize(&bytes).unwrap_or_else(|e| {
+                log::warn!("failed to deserialize storage at {}: {e}", path.display());
+                HashMap::new()
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                log::warn!("failed to read storage at {}: {e}", path.display());
+                HashMap::new()
+            }
+        };
+
+        Self {
+            path,
+            values,
+            dirty: false,
+        }
+    }
+
+    /// Write all values to disk. The values are written to a temporary file
+    /// first which is then renamed over the real file, so that the storage is
+    /// never left half-written if the process is killed.
+    fn write(&self) -> io::Result<()> {
+        let bytes = bincode::serialize(&self.values).map_err(io::Error::other)?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let temp_path = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp_path, &self.path)
+    }
+}
+
+impl Storage for FileStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        if self.values.get(key) != Some(&value) {
+            self.values.insert(key.to_owned(), value);
+            self.dirty = true;
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.dirty {
+            match self.write() {
+                Ok(()) => self.dirty = false,
+                Err(e) => log::error!("failed to write storage to {}: {e}", self.path.display()),
+            }
+        }
+    }
+}
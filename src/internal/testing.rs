@@ -0,0 +1,383 @@
+//! Activities and input events shared by the tests of different modules, which
+//! run them with a [`HeadlessRunner`].
+
+use crate::{
+    headless::{
+        HeadlessRunner, InputEvent, KeyAction, KeyEvent, LifecycleEvent, MotionAction, MotionEvent,
+        Pointer,
+    },
+    Activity, ImeAction, OpenUrlError, SoftInputAdjust, Storage, TextInputOptions,
+    TextSelectionMenu, ToolType,
+};
+use egui::{pos2, vec2, ColorImage, Context, Key, Modifiers, Pos2, Rect};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+};
+
+/// Things a test and its activity both keep track of, in the order they
+/// happened.
+pub(crate) type Log<T> = Rc<RefCell<Vec<T>>>;
+
+type Update = Box<dyn FnMut(&Context)>;
+type Callback<T> = Box<dyn FnMut(T)>;
+
+/// An activity that each test builds from just the parts it needs.
+#[derive(Default)]
+pub(crate) struct TestActivity {
+    update: Option<Update>,
+    callbacks: Option<Callback<&'static str>>,
+    pasted_images: Option<Callback<Arc<ColorImage>>>,
+    open_url_errors: Option<Callback<(String, String)>>,
+    focus_navigation: bool,
+    soft_input_adjust: SoftInputAdjust,
+    text_selection_menu: TextSelectionMenu,
+    custom_tabs: bool,
+}
+
+impl TestActivity {
+    /// Show the UI with `update` on each pass.
+    pub(crate) fn with_update(mut self, update: impl FnMut(&Context) + 'static) -> Self {
+        self.update = Some(Box::new(update));
+        self
+    }
+
+    /// Tell `callbacks` the name of each lifecycle, memory and clipboard
+    /// callback as it is called.
+    pub(crate) fn with_callbacks(mut self, callbacks: impl FnMut(&'static str) + 'static) -> Self {
+        self.callbacks = Some(Box::new(callbacks));
+        self
+    }
+
+    pub(crate) fn with_pasted_images(
+        mut self,
+        pasted: impl FnMut(Arc<ColorImage>) + 'static,
+    ) -> Self {
+        self.pasted_images = Some(Box::new(pasted));
+        self
+    }
+
+    /// Tell `errors` each URL that couldn't be opened, and why.
+    pub(crate) fn with_open_url_errors(
+        mut self,
+        errors: impl FnMut((String, String)) + 'static,
+    ) -> Self {
+        self.open_url_errors = Some(Box::new(errors));
+        self
+    }
+
+    pub(crate) fn with_focus_navigation(mut self) -> Self {
+        self.focus_navigation = true;
+        self
+    }
+
+    pub(crate) fn with_soft_input_adjust(mut self, soft_input_adjust: SoftInputAdjust) -> Self {
+        self.soft_input_adjust = soft_input_adjust;
+        self
+    }
+
+    pub(crate) fn with_text_selection_menu(
+        mut self,
+        text_selection_menu: TextSelectionMenu,
+    ) -> Self {
+        self.text_selection_menu = text_selection_menu;
+        self
+    }
+
+    pub(crate) fn with_custom_tabs(mut self) -> Self {
+        self.custom_tabs = true;
+        self
+    }
+
+    fn callback(&mut self, name: &'static str) {
+        if let Some(callbacks) = &mut self.callbacks {
+            callbacks(name);
+        }
+    }
+}
+
+impl Activity for TestActivity {
+    fn create() -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, ctx: &Context) {
+        if let Some(update) = &mut self.update {
+            update(ctx);
+        }
+    }
+
+    fn on_low_memory(&mut self) {
+        self.callback("low_memory");
+    }
+
+    fn focus_navigation(&self) -> bool {
+        self.focus_navigation
+    }
+
+    fn soft_input_adjust(&self) -> SoftInputAdjust {
+        self.soft_input_adjust
+    }
+
+    fn text_selection_menu(&self) -> TextSelectionMenu {
+        self.text_selection_menu
+    }
+
+    fn on_paste_image(&mut self, image: Arc<ColorImage>) {
+        if let Some(pasted_images) = &mut self.pasted_images {
+            pasted_images(image);
+        }
+    }
+
+    fn on_clipboard_changed(&mut self) {
+        self.callback("clipboard_changed");
+    }
+
+    fn open_links_in_custom_tabs(&self) -> bool {
+        self.custom_tabs
+    }
+
+    fn on_open_url_error(&mut self, url: &str, error: &OpenUrlError) {
+        if let Some(open_url_errors) = &mut self.open_url_errors {
+            open_url_errors((url.to_owned(), error.to_string()));
+        }
+    }
+
+    fn on_start(&mut self) {
+        self.callback("start");
+    }
+
+    fn on_resume(&mut self) {
+        self.callback("resume");
+    }
+
+    fn on_pause(&mut self) {
+        self.callback("pause");
+    }
+
+    fn on_stop(&mut self) {
+        self.callback("stop");
+    }
+
+    fn on_window_created(&mut self) {
+        self.callback("window_created");
+    }
+
+    fn on_window_destroyed(&mut self) {
+        self.callback("window_destroyed");
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) {
+        self.callback(if focused { "focused" } else { "unfocused" });
+    }
+
+    fn on_destroy(&mut self) {
+        self.callback("destroy");
+    }
+}
+
+/// An activity that counts its updates, and keeps a number in both its
+/// saved state and storage.
+#[derive(Default)]
+pub(crate) struct Counter {
+    pub(crate) count: usize,
+    pub(crate) updates: usize,
+}
+
+impl Activity for Counter {
+    fn create() -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, _ctx: &Context) {
+        self.updates += 1;
+    }
+
+    fn load(&mut self, storage: &dyn Storage) {
+        if let Some(count) = storage.get_string("count") {
+            self.count = count.parse().unwrap();
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn Storage) {
+        storage.set_string("count", self.count.to_string());
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.count.to_le_bytes().to_vec())
+    }
+
+    fn restore_state(&mut self, bytes: &[u8]) {
+        self.count = usize::from_le_bytes(bytes.try_into().unwrap());
+    }
+}
+
+/// A button filling the screen that copies text when clicked.
+#[derive(Default)]
+pub(crate) struct CopyButton {
+    pub(crate) rect: Cell<Option<Rect>>,
+    pub(crate) clicks: Cell<usize>,
+    pub(crate) secondary_clicks: Cell<usize>,
+}
+
+impl CopyButton {
+    pub(crate) fn show(&self, ctx: &Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let response = ui.button("Copy");
+            self.rect.set(Some(response.rect));
+
+            if response.clicked() {
+                self.clicks.set(self.clicks.get() + 1);
+                ui.ctx().copy_text("copied".into());
+            }
+            if response.secondary_clicked() {
+                self.secondary_clicks.set(self.secondary_clicks.get() + 1);
+            }
+        });
+    }
+
+    pub(crate) fn center(&self) -> Pos2 {
+        self.rect.get().unwrap().center()
+    }
+}
+
+/// A focused text field at the bottom of the screen.
+#[derive(Default)]
+pub(crate) struct TextField {
+    pub(crate) text: RefCell<String>,
+    pub(crate) options: Option<TextInputOptions>,
+    pub(crate) ime_actions: RefCell<Vec<ImeAction>>,
+}
+
+impl TextField {
+    pub(crate) fn new(text: &str) -> Self {
+        Self {
+            text: RefCell::new(text.into()),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn show(&self, ctx: &Context) {
+        egui::TopBottomPanel::bottom("text").show(ctx, |ui| {
+            let response = ui.text_edit_singleline(&mut *self.text.borrow_mut());
+            response.request_focus();
+
+            if let Some(options) = self.options {
+                crate::set_text_input_options(ctx, response.id, options);
+            }
+            self.ime_actions
+                .borrow_mut()
+                .extend(crate::ime_action(ctx, response.id));
+        });
+    }
+
+    pub(crate) fn text(&self) -> String {
+        self.text.borrow().clone()
+    }
+}
+
+/// Call `update` with some state the test keeps hold of too.
+pub(crate) fn shared<T: 'static>(
+    state: &Rc<T>,
+    update: impl Fn(&T, &Context) + 'static,
+) -> impl FnMut(&Context) {
+    let state = state.clone();
+    move |ctx| update(&state, ctx)
+}
+
+/// Add everything passed to the returned callback to `log`.
+pub(crate) fn record<T: 'static>(log: &Log<T>) -> impl FnMut(T) {
+    let log = log.clone();
+    move |item| log.borrow_mut().push(item)
+}
+
+pub(crate) fn launch(activity: TestActivity) -> HeadlessRunner<TestActivity> {
+    let mut runner = HeadlessRunner::<TestActivity>::new();
+    *runner.activity_mut() = activity;
+    runner.push_event(LifecycleEvent::Start);
+    runner.push_event(LifecycleEvent::Resume { saved_state: None });
+    runner.push_event(LifecycleEvent::InitWindow {
+        width: 400,
+        height: 800,
+    });
+    runner.push_event(LifecycleEvent::GainedFocus);
+    runner.run_once();
+    runner
+}
+
+pub(crate) fn touch(action: MotionAction, x: f32, y: f32) -> InputEvent {
+    InputEvent::Motion(MotionEvent {
+        device_id: 0,
+        action,
+        pointer_index: 0,
+        action_button: None,
+        button_state: [false; egui::NUM_POINTER_BUTTONS],
+        captured: false,
+        modifiers: Modifiers::NONE,
+        pointers: vec![Pointer {
+            id: 0,
+            pos: pos2(x, y),
+            pressure: 1.0,
+            tool_type: ToolType::Finger,
+            tilt: 0.0,
+            orientation: 0.0,
+            distance: 0.0,
+            scroll: vec2(0.0, 0.0),
+            relative: vec2(0.0, 0.0),
+        }],
+        history: Vec::new(),
+    })
+}
+
+pub(crate) fn gamepad_button(action: KeyAction, button: crate::GamepadButton) -> InputEvent {
+    let key = match button {
+        crate::GamepadButton::DpadDown => Some(Key::ArrowDown),
+        _ => None,
+    };
+
+    InputEvent::Key(KeyEvent {
+        device_id: 2,
+        action,
+        key,
+        physical_key: key,
+        text: None,
+        gamepad_button: Some(button),
+        repeat_count: 0,
+        modifiers: Modifiers::NONE,
+    })
+}
+
+pub(crate) fn mouse(
+    action: MotionAction,
+    x: f32,
+    y: f32,
+    buttons: &[egui::PointerButton],
+) -> InputEvent {
+    let mut button_state = [false; egui::NUM_POINTER_BUTTONS];
+    for &button in buttons {
+        button_state[button as usize] = true;
+    }
+
+    InputEvent::Motion(MotionEvent {
+        device_id: 1,
+        action,
+        pointer_index: 0,
+        action_button: None,
+        button_state,
+        captured: false,
+        modifiers: Modifiers::NONE,
+        pointers: vec![Pointer {
+            id: 0,
+            pos: pos2(x, y),
+            pressure: 0.0,
+            tool_type: ToolType::Mouse,
+            tilt: 0.0,
+            orientation: 0.0,
+            distance: 0.0,
+            scroll: vec2(0.0, 0.0),
+            relative: vec2(0.0, 0.0),
+        }],
+        history: Vec::new(),
+    })
+}
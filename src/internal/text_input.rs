@@ -0,0 +1,350 @@
+//! Keeps the focused text field and the soft keyboard's copy of its text in
+//! sync.
+//!
+//! The soft keyboard edits its own copy of the text and reports the whole state
+//! after every change, including the region of text it is still composing, such
+//! as a word being swipe typed or a CJK character being built up. We work out
+//! what changed since the state egui is known to have and replay that as IME
+//! preedit and commit events. Changes made on the egui side, such as typing on
+//! a hardware keyboard or moving the cursor with a tap, are sent back the other
+//! way.
+
+use super::platform::input::{TextInputState, TextSpan};
+use crate::{text_input::TriggeredImeAction, text_input_options, ImeAction, InputType};
+use egui::{
+    output::OutputEvent,
+    text::{CCursor, CCursorRange},
+    text_edit::TextEditState,
+    Context, Event, Id, ImeEvent, Key, Modifiers, PlatformOutput, RawInput, WidgetType,
+};
+
+#[derive(Default)]
+pub(crate) struct TextInputSync {
+    /// The focused text field, and the state that both it and the soft
+    /// keyboard are known to have.
+    synced: Option<(Id, TextInputState)>,
+
+    /// Whether egui has reported the text of the focused text field.
+    ///
+    /// egui only reports it when the field is clicked, gains focus or changes,
+    /// which is missed if focus was requested after the field was shown. Until
+    /// then, the soft keyboard is given an empty text, and its edits are made
+    /// at egui's cursor.
+    text_known: bool,
+
+    /// The selection the text field has while it is in sync. This differs from
+    /// the synced selection while composing, as egui selects the preedit text.
+    expected_selection: TextSpan,
+
+    /// Selection to give the text field once it has applied the edits sent to
+    /// it during the current pass.
+    pending_selection: Option<TextSpan>,
+}
+
+impl TextInputSync {
+    /// Apply a state reported by the soft keyboard to the focused text field.
+    ///
+    /// Must be called before the pass that `raw_input` is used for.
+    pub fn apply(&mut self, ctx: &Context, state: TextInputState, raw_input: &mut RawInput) {
+        let Some((id, synced)) = self.synced.as_mut() else {
+            log::warn!("ignoring text input state, no text field is focused");
+            return;
+        };
+        if ctx.memory(|memory| memory.focused()) != Some(*id) {
+            log::warn!("ignoring text input state for a text field that lost focus");
+            return;
+        }
+
+        let old: Vec<char> = synced.text.chars().collect();
+        let new: Vec<char> = state.text.chars().collect();
+        let compose_region = state
+            .compose_region
+            .map(|span| clamp(ordered(span), new.len()))
+            .filter(|span| span.start < span.end);
+
+        if old == new {
+            let selection = compose_region.unwrap_or(state.selection);
+            if self.text_known {
+                set_selection(ctx, *id, selection);
+            }
+            self.expected_selection = selection;
+            *synced = TextInputState {
+                compose_region,
+                ..state
+            };
+            return;
+        }
+
+        // Find the range of text that was replaced, extended to cover both the
+        // old composing text, which egui has selected as preedit text, and the
+        // new composing text, which is sent as preedit text again.
+        let mut prefix = common_prefix(&old, &new);
+        let mut suffix = common_prefix(old[prefix..].iter().rev(), new[prefix..].iter().rev());
+        if let Some(span) = synced.compose_region.map(|span| clamp(span, old.len())) {
+            prefix = prefix.min(span.start);
+            suffix = suffix.min(old.len() - span.end);
+        }
+        if let Some(span) = compose_region {
+            prefix = prefix.min(span.start);
+            suffix = suffix.min(new.len() - span.end);
+        }
+        let new_end = new.len() - suffix;
+
+        if self.text_known {
+            set_selection(
+                ctx,
+                *id,
+                TextSpan {
+                    start: prefix,
+                    end: old.len() - suffix,
+                },
+            );
+        }
+
+        let text = |start: usize, end: usize| new[start..end].iter().collect::<String>();
+        let events = &mut raw_input.events;
+
+        match compose_region {
+            // Text after the composing text can't be inserted once egui has
+            // the preedit text selected, so it is only shown as preedit text
+            // if it ends the replaced range, as it nearly always does.
+            Some(span) if span.end == new_end => {
+                events.push(Event::Ime(ImeEvent::Enabled));
+                push_commit(events, text(prefix, span.start));
+                events.push(Event::Ime(ImeEvent::Preedit(text(span.start, span.end))));
+                self.expected_selection = span;
+            }
+            _ => {
+                let committed = text(prefix, new_end);
+                if committed == "\n" {
+                    // egui ignores newlines from the IME, expecting an enter
+                    // key press instead.
+                    for pressed in [true, false] {
+                        events.push(Event::Key {
+                            key: Key::Enter,
+                            physical_key: None,
+                            pressed,
+                            repeat: false,
+                            modifiers: Modifiers::NONE,
+                        });
+                    }
+                } else {
+                    events.push(Event::Ime(ImeEvent::Enabled));
+                    push_commit(events, committed);
+                }
+
+                if self.text_known
+                    && state.selection
+                        != (TextSpan {
+                            start: new_end,
+                            end: new_end,
+                        })
+                {
+                    self.pending_selection = Some(state.selection);
+                }
+                self.expected_selection = state.selection;
+            }
+        }
+
+        *synced = TextInputState {
+            compose_region,
+            ..state
+        };
+    }
+
+    /// Turn enter key presses on the focused text field into the IME action it
+    /// was given, if any.
+    ///
+    /// Must be called before the pass that `raw_input` is used for.
+    pub fn apply_ime_actions(&mut self, ctx: &Context, raw_input: &mut RawInput) {
+        let Some((id, _)) = self.synced else {
+            return;
+        };
+        if ctx.memory(|memory| memory.focused()) != Some(id) {
+            return;
+        }
+
+        let action = text_input_options(ctx, id).ime_action;
+        let (key, modifiers) = match action {
+            ImeAction::Unspecified | ImeAction::None => return,
+            ImeAction::Next => (Key::Tab, Modifiers::NONE),
+            ImeAction::Previous => (Key::Tab, Modifiers::SHIFT),
+            _ => (Key::Enter, Modifiers::NONE),
+        };
+
+        for event in &mut raw_input.events {
+            if let Event::Key {
+                key: Key::Enter,
+                pressed,
+                repeat: false,
+                ..
+            } = *event
+            {
+                if pressed {
+                    TriggeredImeAction { id, action }.store(ctx);
+                }
+
+                *event = Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed,
+                    repeat: false,
+                    modifiers,
+                };
+            }
+        }
+    }
+
+    /// The text selected in the focused text field, unless it is a password.
+    pub fn selected_text(&self, ctx: &Context) -> Option<String> {
+        let (id, state) = self.synced.as_ref().filter(|_| self.text_known)?;
+        if matches!(
+            text_input_options(ctx, *id).input_type,
+            InputType::Password | InputType::NumberPassword
+        ) {
+            return None;
+        }
+
+        let span = ordered(state.selection);
+        let text: String = state
+            .text
+            .chars()
+            .skip(span.start)
+            .take(span.end - span.start)
+            .collect();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Find out how the focused text field changed during a pass, and return
+    /// the state to give to the soft keyboard if it no longer matches.
+    pub fn finish(
+        &mut self,
+        ctx: &Context,
+        platform_output: &PlatformOutput,
+    ) -> Option<TextInputState> {
+        TriggeredImeAction::clear(ctx);
+
+        let focused = platform_output
+            .ime
+            .as_ref()
+            .and_then(|_| ctx.memory(|memory| memory.focused()));
+
+        let Some(id) = focused else {
+            // Clear the keyboard's text once no text field has focus.
+            self.pending_selection = None;
+            return self.synced.take().map(|_| TextInputState::default());
+        };
+
+        if let Some(selection) = self.pending_selection.take() {
+            set_selection(ctx, id, selection);
+            ctx.request_repaint();
+        }
+
+        // Output events are the only place egui reports the text of the field.
+        let text = platform_output
+            .events
+            .iter()
+            .rev()
+            .map(OutputEvent::widget_info)
+            .filter(|info| info.typ == WidgetType::TextEdit)
+            .find_map(|info| info.current_text_value.clone());
+        let synced = self
+            .synced
+            .as_ref()
+            .filter(|(synced_id, _)| *synced_id == id);
+
+        let known_text = synced
+            .filter(|_| self.text_known)
+            .map(|(_, synced)| synced.text.clone());
+
+        let Some(text) = text.or(known_text) else {
+            if synced.is_some() {
+                return None;
+            }
+
+            self.synced = Some((id, TextInputState::default()));
+            self.text_known = false;
+            self.expected_selection = TextSpan::default();
+            return Some(TextInputState::default());
+        };
+
+        let selection = TextEditState::load(ctx, id)
+            .and_then(|state| state.cursor.char_range())
+            .map(|range| TextSpan {
+                start: range.secondary.index,
+                end: range.primary.index,
+            })
+            .unwrap_or_default();
+
+        if let Some((_, synced)) = synced {
+            if synced.text == text && selection == self.expected_selection {
+                return None;
+            }
+        }
+
+        // egui has changed the text or selection itself, which ends any
+        // composition.
+        let state = TextInputState {
+            text,
+            selection,
+            compose_region: None,
+        };
+        self.synced = Some((id, state.clone()));
+        self.text_known = true;
+        self.expected_selection = selection;
+
+        Some(state)
+    }
+}
+
+/// Replace the selected text with `text`, leaving the cursor after it.
+fn push_commit(events: &mut Vec<Event>, text: String) {
+    // A commit only replaces the selection if it is preceded by preedit text,
+    // which deletes the selection when empty.
+    events.push(Event::Ime(ImeEvent::Preedit(text.clone())));
+    events.push(Event::Ime(ImeEvent::Commit(text)));
+}
+
+/// Select all text in the focused text field, with the same shortcut as a
+/// hardware keyboard.
+pub(crate) fn push_select_all(events: &mut Vec<Event>) {
+    for pressed in [true, false] {
+        events.push(Event::Key {
+            key: Key::A,
+            physical_key: None,
+            pressed,
+            repeat: false,
+            modifiers: Modifiers::COMMAND,
+        });
+    }
+}
+
+fn set_selection(ctx: &Context, id: Id, selection: TextSpan) {
+    let mut state = TextEditState::load(ctx, id).unwrap_or_default();
+    state.cursor.set_char_range(Some(CCursorRange::two(
+        CCursor::new(selection.start),
+        CCursor::new(selection.end),
+    )));
+    state.store(ctx, id);
+}
+
+fn common_prefix<'a>(
+    a: impl IntoIterator<Item = &'a char>,
+    b: impl IntoIterator<Item = &'a char>,
+) -> usize {
+    a.into_iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+fn ordered(span: TextSpan) -> TextSpan {
+    TextSpan {
+        start: span.start.min(span.end),
+        end: span.start.max(span.end),
+    }
+}
+
+fn clamp(span: TextSpan, len: usize) -> TextSpan {
+    TextSpan {
+        start: span.start.min(len),
+        end: span.end.min(len),
+    }
+}
@@ -0,0 +1,141 @@
+//! Keeps a copy of every texture egui has asked to be uploaded to the GPU.
+//!
+//! egui only sends each texture to the backend once, and afterwards only sends
+//! the parts that changed. If the graphics context is lost, all of the textures
+//! on the GPU are lost with it, so we need our own copy to upload them again.
+
+use egui::{epaint::ImageDelta, ImageData, TextureId, TexturesDelta};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Default)]
+pub(crate) struct TextureCache {
+    /// The full contents of every live texture.
+    textures: HashMap<TextureId, ImageDelta>,
+}
+
+impl TextureCache {
+    /// Apply the changes in a delta that is about to be uploaded.
+    pub(crate) fn update(&mut self, delta: &TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            match image_delta.pos {
+                None => {
+                    self.textures.insert(*id, image_delta.clone());
+                }
+
+                Some(pos) => match self.textures.get_mut(id) {
+                    Some(texture) => {
+                        patch(&mut texture.image, &image_delta.image, pos);
+                        texture.options = image_delta.options;
+                    }
+                    None => log::warn!("partial update of unknown texture {id:?}"),
+                },
+            }
+        }
+
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    /// Create a delta that uploads every live texture from scratch.
+    pub(crate) fn replay(&self) -> TexturesDelta {
+        TexturesDelta {
+            set: self
+                .textures
+                .iter()
+                .map(|(id, texture)| (*id, texture.clone()))
+                .collect(),
+            free: Vec::new(),
+        }
+    }
+}
+
+/// Copy a patch into an image at the given position.
+fn patch(image: &mut ImageData, patch: &ImageData, [x, y]: [usize; 2]) {
+    let (ImageData::Color(image), ImageData::Color(patch)) = (image, patch);
+    let image = Arc::make_mut(image);
+
+    let [image_width, _] = image.size;
+    let [patch_width, _] = patch.size;
+
+    for (row, pixels) in patch.pixels.chunks_exact(patch_width).enumerate() {
+        let start = (y + row) * image_width + x;
+        image.pixels[start..start + patch_width].copy_from_slice(pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Color32, ColorImage, TextureOptions};
+
+    fn image(size: [usize; 2], color: Color32) -> ImageData {
+        ColorImage::new(size, vec![color; size[0] * size[1]]).into()
+    }
+
+    fn pixels(delta: &TexturesDelta, id: TextureId) -> Vec<Color32> {
+        let (_, texture) = delta.set.iter().find(|(i, _)| *i == id).unwrap();
+        let ImageData::Color(image) = &texture.image;
+        image.pixels.clone()
+    }
+
+    #[test]
+    fn replays_patched_textures() {
+        let id = TextureId::Managed(1);
+        let mut cache = TextureCache::default();
+
+        cache.update(&TexturesDelta {
+            set: vec![(
+                id,
+                ImageDelta::full(image([3, 2], Color32::BLACK), TextureOptions::LINEAR),
+            )],
+            free: vec![],
+        });
+        cache.update(&TexturesDelta {
+            set: vec![(
+                id,
+                ImageDelta::partial(
+                    [1, 1],
+                    image([2, 1], Color32::WHITE),
+                    TextureOptions::LINEAR,
+                ),
+            )],
+            free: vec![],
+        });
+
+        let replay = cache.replay();
+        assert_eq!(replay.set.len(), 1);
+        assert_eq!(replay.set[0].1.pos, None);
+        assert_eq!(
+            pixels(&replay, id),
+            [
+                Color32::BLACK,
+                Color32::BLACK,
+                Color32::BLACK,
+                Color32::BLACK,
+                Color32::WHITE,
+                Color32::WHITE,
+            ]
+        );
+    }
+
+    #[test]
+    fn forgets_freed_textures() {
+        let id = TextureId::Managed(1);
+        let mut cache = TextureCache::default();
+
+        cache.update(&TexturesDelta {
+            set: vec![(
+                id,
+                ImageDelta::full(image([1, 1], Color32::BLACK), TextureOptions::LINEAR),
+            )],
+            free: vec![],
+        });
+        cache.update(&TexturesDelta {
+            set: vec![],
+            free: vec![id],
+        });
+
+        assert!(cache.replay().set.is_empty());
+    }
+}
@@ -0,0 +1,646 @@
+//! Internal graphics module, implemented with wgpu instead of OpenGL ES.
+//!
+//! This mirrors the glow implementation in `graphics.rs`, and is used instead
+//! of it when the `wgpu` feature is enabled.
+
+// https://github.com/emilk/egui/blob/master/crates/egui-wgpu/src/winit.rs
+
+use super::{
+    platform::{self, Graphics},
+    textures::TextureCache,
+};
+use crate::{GraphicsError, GraphicsOptions};
+use egui::{mutex::RwLock, ClippedPrimitive, Color32, ColorImage, FullOutput, TexturesDelta};
+use egui_glow::glow;
+use egui_wgpu::{
+    capture::CaptureState, wgpu, RenderState, Renderer as EguiRenderer, RendererOptions,
+    ScreenDescriptor, WgpuConfiguration,
+};
+use ndk::native_window::NativeWindow;
+use raw_window_handle::{
+    AndroidDisplayHandle, AndroidNdkWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+use std::{
+    mem::{replace, take},
+    sync::{mpsc, Arc},
+};
+
+/// Establishes a connection to Android's graphics API through wgpu.
+pub(crate) struct GraphicsContext {
+    /// Preferences for the surface and renderer.
+    options: GraphicsOptions,
+
+    configuration: WgpuConfiguration,
+
+    /// The wgpu instance. This is only created once the first window is
+    /// attached, so that errors can be reported to the activity.
+    instance: Option<wgpu::Instance>,
+
+    /// Sometimes we have a window surface, sometimes we don't. This holds
+    /// objects specific to each of these possible states.
+    state: State,
+
+    /// A copy of every texture, for uploading again to a new renderer.
+    textures: TextureCache,
+
+    /// Set when a new renderer has been created, and all textures need to be
+    /// uploaded to it again.
+    replay_textures: bool,
+}
+
+#[derive(Default)]
+enum State {
+    /// We have not initialized wgpu yet. We only do so lazily, since we want a
+    /// surface to pick a compatible adapter.
+    #[default]
+    Uninitialized,
+
+    /// A window surface has been associated with the device and is currently
+    /// active.
+    Active(Box<Active>),
+
+    /// Android took away our window, but we keep the device and renderer, and
+    /// with them all uploaded textures, until the window comes back.
+    Suspended { render_state: RenderState },
+}
+
+struct Active {
+    /// Declared before the window, so that it is dropped first.
+    surface: wgpu::Surface<'static>,
+    native_window: NativeWindow,
+    render_state: RenderState,
+
+    /// Size the surface was last configured with.
+    size: [u32; 2],
+
+    depth_view: Option<wgpu::TextureView>,
+    msaa_view: Option<wgpu::TextureView>,
+
+    /// Texture that frames are drawn into instead when a screenshot is
+    /// requested, since the surface texture cannot always be copied from.
+    capture: Option<CaptureState>,
+}
+
+impl GraphicsContext {
+    /// Create a new instance. Only one instance should be created per process.
+    pub(crate) fn new(options: GraphicsOptions) -> Self {
+        GraphicsContext {
+            options,
+            configuration: WgpuConfiguration::default(),
+            instance: None,
+            state: State::default(),
+            textures: TextureCache::default(),
+            replay_textures: false,
+        }
+    }
+
+    /// Attach a window to the context. A surface will be created for the
+    /// window, and a renderer will become available for drawing to it.
+    ///
+    /// If a window is already attached, its surface is torn down and replaced.
+    pub(crate) fn attach_window(
+        &mut self,
+        native_window: NativeWindow,
+    ) -> Result<(), GraphicsError> {
+        // Android sometimes gives us a new window without taking away the old
+        // one first, so make sure the old surface is released.
+        self.detach_window();
+
+        let instance = match self.instance.take() {
+            Some(instance) => instance,
+            None => pollster::block_on(self.configuration.wgpu_setup.new_instance()),
+        };
+        let instance = self.instance.insert(instance);
+
+        let surface = create_surface(instance, &native_window)?;
+
+        let render_state = match self.state.take() {
+            // This is the first time a window has been created. Initialize
+            // everything with an adapter that can draw to the surface.
+            State::Uninitialized => {
+                create_render_state(&self.configuration, instance, &surface, &self.options)?
+            }
+
+            State::Suspended { render_state } => {
+                if render_state.adapter.is_surface_supported(&surface) {
+                    render_state
+                } else {
+                    log::warn!("adapter does not support the new surface, recreating it");
+                    let render_state = create_render_state(
+                        &self.configuration,
+                        instance,
+                        &surface,
+                        &self.options,
+                    )?;
+
+                    // The textures were uploaded to the old renderer.
+                    self.replay_textures = true;
+                    render_state
+                }
+            }
+
+            State::Active(_) => unreachable!("window was detached above"),
+        };
+
+        let mut active = Active {
+            surface,
+            native_window,
+            render_state,
+            size: [0, 0],
+            depth_view: None,
+            msaa_view: None,
+            capture: None,
+        };
+        active.configure(&self.options);
+
+        self.state = State::Active(Box::new(active));
+
+        Ok(())
+    }
+}
+
+impl Graphics for GraphicsContext {
+    type Renderer<'a> = Renderer<'a>;
+
+    fn renderer(&mut self) -> Option<Renderer<'_>> {
+        match &self.state {
+            State::Active(_) => Some(Renderer { context: self }),
+            _ => None,
+        }
+    }
+
+    fn detach_window(&mut self) {
+        self.state = match self.state.take() {
+            // Dropping the surface releases the window.
+            State::Active(active) => State::Suspended {
+                render_state: active.render_state,
+            },
+
+            // We have no window to remove, do nothing.
+            state => state,
+        };
+    }
+
+    fn gl(&self) -> Option<Arc<glow::Context>> {
+        None
+    }
+
+    fn wgpu_render_state(&self) -> Option<RenderState> {
+        match &self.state {
+            State::Active(active) => Some(active.render_state.clone()),
+            State::Suspended { render_state } => Some(render_state.clone()),
+            State::Uninitialized => None,
+        }
+    }
+}
+
+impl State {
+    fn take(&mut self) -> Self {
+        replace(self, Self::Uninitialized)
+    }
+}
+
+impl Active {
+    fn window_size(&self) -> [u32; 2] {
+        [
+            self.native_window.width().max(1) as _,
+            self.native_window.height().max(1) as _,
+        ]
+    }
+
+    /// Configure the surface for the current window size, and create the
+    /// depth and multisampling textures to match.
+    fn configure(&mut self, options: &GraphicsOptions) {
+        let render_state = &self.render_state;
+        let [width, height] = self.window_size();
+        let capabilities = self.surface.get_capabilities(&render_state.adapter);
+
+        // A premultiplied surface lets whatever is behind the window show
+        // through.
+        let alpha_mode = if options.transparent
+            && capabilities
+                .alpha_modes
+                .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+        {
+            wgpu::CompositeAlphaMode::PreMultiplied
+        } else {
+            capabilities.alpha_modes[0]
+        };
+
+        self.surface.configure(
+            &render_state.device,
+            &wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: render_state.target_format,
+                width,
+                height,
+                present_mode: wgpu::PresentMode::AutoVsync,
+                desired_maximum_frame_latency: 2,
+                alpha_mode,
+                view_formats: vec![render_state.target_format],
+            },
+        );
+
+        let sample_count = u32::from(options.multisampling).max(1);
+
+        let create_view = |label, format| {
+            render_state
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[format],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+
+        self.depth_view =
+            depth_format(options).map(|format| create_view("egui_depth_texture", format));
+        self.msaa_view = (sample_count > 1)
+            .then(|| create_view("egui_msaa_texture", render_state.target_format));
+        self.size = [width, height];
+    }
+
+    /// Draw a frame and present it, reading it back first if `screenshot` is
+    /// true.
+    fn paint(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+        screenshot: bool,
+    ) -> Result<Option<ColorImage>, wgpu::SurfaceError> {
+        let render_state = &self.render_state;
+
+        let mut encoder =
+            render_state
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("encoder"),
+                });
+
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: self.size,
+            pixels_per_point,
+        };
+
+        let user_command_buffers = {
+            let mut renderer = render_state.renderer.write();
+
+            for (id, image_delta) in &textures_delta.set {
+                renderer.update_texture(
+                    &render_state.device,
+                    &render_state.queue,
+                    *id,
+                    image_delta,
+                );
+            }
+
+            renderer.update_buffers(
+                &render_state.device,
+                &render_state.queue,
+                &mut encoder,
+                clipped_primitives,
+                &screen_descriptor,
+            )
+        };
+
+        let output_frame = self.surface.get_current_texture()?;
+
+        let mut capture = screenshot.then(|| {
+            let capture = self.capture.get_or_insert_with(|| {
+                CaptureState::new(&render_state.device, &output_frame.texture)
+            });
+            capture.update(&render_state.device, &output_frame.texture);
+            capture
+        });
+
+        {
+            let renderer = render_state.renderer.read();
+            let target_view = match &capture {
+                Some(capture) => &capture.texture,
+                None => &output_frame.texture,
+            }
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+            let (view, resolve_target) = match &self.msaa_view {
+                Some(msaa_view) => (msaa_view, Some(&target_view)),
+                None => (&target_view, None),
+            };
+
+            let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui_render"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: self.depth_view.as_ref().map(|view| {
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view,
+                        depth_ops: Some(wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(1.0),
+                            store: wgpu::StoreOp::Discard,
+                        }),
+                        stencil_ops: None,
+                    }
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            renderer.render(
+                &mut render_pass.forget_lifetime(),
+                clipped_primitives,
+                &screen_descriptor,
+            );
+        }
+
+        // Copies the captured frame both to the surface and to a buffer.
+        let capture_buffer = capture.as_mut().map(|capture| {
+            capture.copy_textures(&render_state.device, &output_frame, &mut encoder)
+        });
+
+        render_state
+            .queue
+            .submit(user_command_buffers.into_iter().chain([encoder.finish()]));
+
+        // Textures must only be freed after the frame using them has been
+        // submitted.
+        let mut renderer = render_state.renderer.write();
+        for id in &textures_delta.free {
+            renderer.free_texture(id);
+        }
+        drop(renderer);
+
+        let size = output_frame.texture.size();
+        let format = output_frame.texture.format();
+        output_frame.present();
+
+        Ok(capture_buffer
+            .and_then(|buffer| read_captured_frame(&render_state.device, &buffer, size, format)))
+    }
+}
+
+pub(crate) struct Renderer<'c> {
+    context: &'c mut GraphicsContext,
+}
+
+impl Renderer<'_> {
+    fn active(&self) -> &Active {
+        match &self.context.state {
+            State::Active(active) => active,
+            _ => unreachable!("renderer exists without an active window"),
+        }
+    }
+
+    fn active_mut(&mut self) -> &mut Active {
+        match &mut self.context.state {
+            State::Active(active) => active,
+            _ => unreachable!("renderer exists without an active window"),
+        }
+    }
+
+    /// Configure the surface for the current window size.
+    fn configure(&mut self) {
+        if let State::Active(active) = &mut self.context.state {
+            active.configure(&self.context.options);
+        }
+    }
+
+    /// Recreate the surface in the same window, and the renderer too if the
+    /// adapter can't draw to the new surface.
+    fn recreate(&mut self) -> Result<(), GraphicsError> {
+        let native_window = self.active().native_window.clone();
+        self.context.attach_window(native_window)
+    }
+
+    /// Take all textures that need to be uploaded because the renderer is new.
+    fn take_replay(&mut self) -> Option<TexturesDelta> {
+        take(&mut self.context.replay_textures).then(|| self.context.textures.replay())
+    }
+}
+
+impl platform::Renderer for Renderer<'_> {
+    fn handle_resize(&mut self) {
+        self.configure();
+    }
+
+    fn repaint(
+        &mut self,
+        full_output: &mut FullOutput,
+        clipped_primitives: &[ClippedPrimitive],
+        screenshot: bool,
+    ) -> Result<Option<ColorImage>, GraphicsError> {
+        // Resize events can arrive after the window has already changed size.
+        if self.active().size != self.active().window_size() {
+            self.configure();
+        }
+
+        let pixels_per_point = full_output.pixels_per_point;
+        self.context.textures.update(&full_output.textures_delta);
+
+        // After the renderer has been replaced, everything needs to be
+        // uploaded again, not just what changed this frame.
+        let replay = self.take_replay();
+        let textures_delta = replay.as_ref().unwrap_or(&full_output.textures_delta);
+
+        let e = match self.active_mut().paint(
+            pixels_per_point,
+            clipped_primitives,
+            textures_delta,
+            screenshot,
+        ) {
+            Ok(image) => return Ok(image),
+
+            // The next frame will be on time.
+            Err(wgpu::SurfaceError::Timeout) => {
+                log::warn!("timed out acquiring frame, skipping it");
+                return Ok(None);
+            }
+
+            Err(e @ (wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost)) => e,
+            Err(e) => return Err(GraphicsError::SwapBuffers(e.into())),
+        };
+
+        // Try once more with a fresh surface, which is needed after the window
+        // changed underneath us. Textures were already uploaded, unless the
+        // renderer had to be replaced too, but freeing them happens after the
+        // frame is drawn.
+        log::warn!("failed to acquire frame, recreating surface: {e}");
+        self.recreate()?;
+
+        let textures_delta = TexturesDelta {
+            set: self
+                .take_replay()
+                .map(|replay| replay.set)
+                .unwrap_or_default(),
+            free: full_output.textures_delta.free.clone(),
+        };
+        self.active_mut()
+            .paint(
+                pixels_per_point,
+                clipped_primitives,
+                &textures_delta,
+                screenshot,
+            )
+            .map_err(|e| GraphicsError::SwapBuffers(e.into()))
+    }
+
+    fn window_size(&self) -> [u32; 2] {
+        self.active().window_size()
+    }
+}
+
+/// Copy a frame captured with [`CaptureState`] back from the GPU, waiting for
+/// it to finish rendering.
+fn read_captured_frame(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    size: wgpu::Extent3d,
+    format: wgpu::TextureFormat,
+) -> Option<ColorImage> {
+    let to_rgba = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => [0, 1, 2, 3],
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => [2, 1, 0, 3],
+        _ => {
+            log::error!("cannot read back frames with surface format {format:?}");
+            return None;
+        }
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).ok();
+    });
+
+    if let Err(e) = device.poll(wgpu::PollType::wait_indefinitely()) {
+        log::error!("failed to wait for frame to be read back: {e}");
+        return None;
+    }
+
+    if let Err(e) = rx.try_recv().unwrap_or(Err(wgpu::BufferAsyncError)) {
+        log::error!("failed to read back frame: {e}");
+        return None;
+    }
+
+    // Rows in the buffer are padded to satisfy wgpu's copy alignment.
+    let row_bytes = size.width as usize * 4;
+    let padded_row_bytes = wgpu::util::align_to(row_bytes, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as _);
+
+    let pixels = slice
+        .get_mapped_range()
+        .chunks_exact(padded_row_bytes)
+        .flat_map(|row| row[..row_bytes].chunks_exact(4))
+        .map(|p| {
+            Color32::from_rgba_premultiplied(
+                p[to_rgba[0]],
+                p[to_rgba[1]],
+                p[to_rgba[2]],
+                p[to_rgba[3]],
+            )
+        })
+        .collect();
+    buffer.unmap();
+
+    Some(ColorImage::new(
+        [size.width as usize, size.height as usize],
+        pixels,
+    ))
+}
+
+fn create_surface(
+    instance: &wgpu::Instance,
+    native_window: &NativeWindow,
+) -> Result<wgpu::Surface<'static>, GraphicsError> {
+    let width = native_window.width();
+    let height = native_window.height();
+
+    if width <= 0 || height <= 0 {
+        return Err(GraphicsError::InvalidWindowSize { width, height });
+    }
+
+    // The surface must not outlive the window, which is guaranteed by storing
+    // them together in `Active`.
+    unsafe {
+        instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::RawHandle {
+            raw_display_handle: RawDisplayHandle::Android(AndroidDisplayHandle::new()),
+            raw_window_handle: RawWindowHandle::AndroidNdk(AndroidNdkWindowHandle::new(
+                native_window.ptr().cast(),
+            )),
+        })
+    }
+    .map_err(|e| GraphicsError::CreateSurface(e.into()))
+}
+
+fn create_render_state(
+    configuration: &WgpuConfiguration,
+    instance: &wgpu::Instance,
+    surface: &wgpu::Surface<'static>,
+    options: &GraphicsOptions,
+) -> Result<RenderState, GraphicsError> {
+    let renderer_options = RendererOptions {
+        msaa_samples: options.multisampling.into(),
+        depth_stencil_format: depth_format(options),
+        ..Default::default()
+    };
+
+    let mut render_state = pollster::block_on(RenderState::create(
+        configuration,
+        instance,
+        Some(surface),
+        renderer_options,
+    ))
+    .map_err(|e| GraphicsError::CreateContext(e.into()))?;
+
+    // egui picks a linear format by default, so switch to the sRGB variant if
+    // one was asked for and the surface supports it.
+    if options.srgb {
+        let srgb_format = render_state.target_format.add_srgb_suffix();
+        let capabilities = surface.get_capabilities(&render_state.adapter);
+
+        if capabilities.formats.contains(&srgb_format) {
+            render_state.target_format = srgb_format;
+            render_state.renderer = Arc::new(RwLock::new(EguiRenderer::new(
+                &render_state.device,
+                srgb_format,
+                renderer_options,
+            )));
+        } else {
+            log::warn!("surface does not support an sRGB format");
+        }
+    }
+
+    log::info!(
+        "using wgpu adapter: {}, format: {:?}",
+        egui_wgpu::adapter_info_summary(&render_state.adapter.get_info()),
+        render_state.target_format,
+    );
+
+    Ok(render_state)
+}
+
+fn depth_format(options: &GraphicsOptions) -> Option<wgpu::TextureFormat> {
+    if options.depth_bits == 0 && options.stencil_bits == 0 {
+        return None;
+    }
+
+    // Fall back to a format every device supports if the exact combination
+    // of bits has no equivalent.
+    egui_wgpu::depth_format_from_bits(options.depth_bits, options.stencil_bits)
+        .or(Some(wgpu::TextureFormat::Depth24PlusStencil8))
+}
@@ -0,0 +1,94 @@
+//! A loupe that shows the text around a dragged selection handle enlarged,
+//! since the finger dragging it covers the text being selected.
+
+use egui::{
+    emath::TSTransform,
+    epaint::{ClippedShape, RectShape},
+    pos2, vec2, Color32, Context, CornerRadius, Pos2, Rect, Shape, Stroke, StrokeKind, Vec2,
+};
+
+/// Size of the loupe, in points.
+const SIZE: Vec2 = vec2(100.0, 48.0);
+
+/// How much the content of the loupe is enlarged.
+const ZOOM: f32 = 1.25;
+
+/// Space between the loupe and the text cursor it magnifies, in points.
+const MARGIN: f32 = 8.0;
+
+pub(crate) struct Magnifier {
+    /// The point that is shown at the center of the loupe.
+    focus: Pos2,
+    rect: Rect,
+    fill: Color32,
+    stroke: Stroke,
+    corner_radius: CornerRadius,
+}
+
+impl Magnifier {
+    /// Place a magnifier above the given text cursor, which is magnified.
+    pub(crate) fn new(ctx: &Context, cursor_rect: Rect) -> Self {
+        let style = ctx.style();
+        let bounds = ctx.content_rect();
+        let focus = cursor_rect.center();
+
+        let center = pos2(
+            focus.x.clamp(
+                bounds.left() + SIZE.x / 2.0,
+                (bounds.right() - SIZE.x / 2.0).max(bounds.left() + SIZE.x / 2.0),
+            ),
+            (cursor_rect.top() - MARGIN - SIZE.y / 2.0).max(bounds.top() + SIZE.y / 2.0),
+        );
+
+        Self {
+            focus,
+            rect: Rect::from_center_size(center, SIZE),
+            fill: style.visuals.extreme_bg_color,
+            stroke: style.visuals.window_stroke,
+            corner_radius: style.visuals.menu_corner_radius,
+        }
+    }
+
+    /// Add the loupe on top of the other shapes, which must be in global
+    /// coordinates.
+    pub(crate) fn paint(&self, shapes: &mut Vec<ClippedShape>) {
+        let transform = TSTransform::new(
+            self.rect.center().to_vec2() - ZOOM * self.focus.to_vec2(),
+            ZOOM,
+        );
+        let source = transform.inverse() * self.rect;
+
+        let magnified: Vec<_> = shapes
+            .iter()
+            .filter(|clipped| {
+                // Custom painting can't be transformed, only moved.
+                !matches!(clipped.shape, Shape::Callback(_))
+                    && clipped.clip_rect.intersects(source)
+                    && clipped.shape.visual_bounding_rect().intersects(source)
+            })
+            .map(|clipped| {
+                let mut shape = clipped.shape.clone();
+                shape.transform(transform);
+                ClippedShape {
+                    clip_rect: (transform * clipped.clip_rect).intersect(self.rect),
+                    shape,
+                }
+            })
+            .collect();
+
+        shapes.push(ClippedShape {
+            clip_rect: self.rect,
+            shape: Shape::Rect(RectShape::filled(self.rect, self.corner_radius, self.fill)),
+        });
+        shapes.extend(magnified);
+        shapes.push(ClippedShape {
+            clip_rect: Rect::EVERYTHING,
+            shape: Shape::Rect(RectShape::stroke(
+                self.rect,
+                self.corner_radius,
+                self.stroke,
+                StrokeKind::Outside,
+            )),
+        });
+    }
+}
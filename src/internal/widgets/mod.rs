@@ -3,4 +3,6 @@
 //!
 //! None of these widgets are exposed to app developers directly.
 
-pub(crate) mod text_context_menu;
+pub(crate) mod magnifier;
+pub(crate) mod selection_handle;
+pub(crate) mod text_toolbar;
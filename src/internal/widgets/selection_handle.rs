@@ -0,0 +1,73 @@
+//! The teardrop shaped handles shown below both ends of a text selection, which
+//! can be dragged to change what is selected.
+
+use egui::{pos2, vec2, Color32, Painter, Pos2, Rect};
+
+/// Radius of the round part of a handle, in points.
+const RADIUS: f32 = 11.0;
+
+/// How far outside of its painted shape a handle can still be grabbed, in
+/// points.
+const TOUCH_SLOP: f32 = 12.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HandleSide {
+    /// The handle at the start of the selection, which points up and to the
+    /// right.
+    Start,
+
+    /// The handle at the end of the selection, which points up and to the
+    /// left.
+    End,
+}
+
+/// A selection handle attached to the text cursor at one end of a selection.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SelectionHandle {
+    pub(crate) side: HandleSide,
+
+    /// The text cursor at this end of the selection, in global coordinates.
+    pub(crate) cursor_rect: Rect,
+}
+
+impl SelectionHandle {
+    /// The point of the handle, which touches the bottom of the cursor.
+    fn tip(&self) -> Pos2 {
+        self.cursor_rect.center_bottom()
+    }
+
+    /// The area covered by the painted handle.
+    pub(crate) fn rect(&self) -> Rect {
+        let tip = self.tip();
+        match self.side {
+            HandleSide::Start => Rect::from_min_max(
+                pos2(tip.x - 2.0 * RADIUS, tip.y),
+                tip + vec2(0.0, 2.0 * RADIUS),
+            ),
+            HandleSide::End => Rect::from_min_size(tip, vec2(2.0 * RADIUS, 2.0 * RADIUS)),
+        }
+    }
+
+    /// The area in which touches grab the handle, which is larger than the
+    /// handle itself to make it easier to hit.
+    pub(crate) fn hit_rect(&self) -> Rect {
+        self.rect().expand(TOUCH_SLOP)
+    }
+
+    pub(crate) fn paint(&self, painter: &Painter, color: Color32) {
+        let tip = self.tip();
+        let (center, corner) = match self.side {
+            HandleSide::Start => (
+                tip + vec2(-RADIUS, RADIUS),
+                Rect::from_min_max(tip - vec2(RADIUS, 0.0), tip + vec2(0.0, RADIUS)),
+            ),
+            HandleSide::End => (
+                tip + vec2(RADIUS, RADIUS),
+                Rect::from_min_max(tip, tip + vec2(RADIUS, RADIUS)),
+            ),
+        };
+
+        painter.circle_filled(center, RADIUS, color);
+        painter.rect_filled(corner, 0.0, color);
+    }
+}
@@ -0,0 +1,138 @@
+//! A floating toolbar with clipboard actions that appears above selected text.
+
+use crate::internal::platform::input::TextAction;
+use egui::{
+    pos2, vec2, Align2, Color32, Galley, Painter, Pos2, Rect, Shape, Stroke, TextStyle, Vec2,
+};
+use std::sync::Arc;
+
+/// Space between the toolbar and the text it belongs to, in points.
+const MARGIN: f32 = 8.0;
+
+fn label(action: TextAction) -> &'static str {
+    match action {
+        TextAction::Cut => "Cut",
+        TextAction::Copy => "Copy",
+        TextAction::Paste => "Paste",
+        TextAction::SelectAll => "Select all",
+    }
+}
+
+/// A laid out toolbar. The toolbar isn't interactive by itself, as egui would
+/// take focus away from the text field when it is tapped, so taps have to be
+/// checked against [`TextToolbar::action_at`] instead.
+pub(crate) struct TextToolbar {
+    rect: Rect,
+    buttons: Vec<(TextAction, Rect, Arc<Galley>)>,
+}
+
+impl TextToolbar {
+    /// Lay out a toolbar with the given actions, above `target` if there is
+    /// room within `bounds`, or below it otherwise.
+    pub(crate) fn new(
+        painter: &Painter,
+        actions: &[TextAction],
+        target: Rect,
+        bounds: Rect,
+    ) -> Self {
+        let style = painter.ctx().style();
+        let font_id = TextStyle::Button.resolve(&style);
+        let padding = style.spacing.button_padding * 2.0;
+        let text_color = style.visuals.text_color();
+
+        let galleys: Vec<_> = actions
+            .iter()
+            .map(|action| {
+                painter.layout_no_wrap(label(*action).into(), font_id.clone(), text_color)
+            })
+            .collect();
+
+        let height = galleys
+            .iter()
+            .map(|galley| galley.size().y)
+            .fold(0.0, f32::max)
+            + 2.0 * padding.y;
+        let width = galleys
+            .iter()
+            .map(|galley| galley.size().x + 2.0 * padding.x)
+            .sum::<f32>();
+        let size = vec2(width, height);
+
+        let above = target.top() - MARGIN - height;
+        let top = if above >= bounds.top() {
+            above
+        } else {
+            target.bottom() + MARGIN
+        };
+        let left = (target.center().x - width / 2.0)
+            .min(bounds.right() - width)
+            .max(bounds.left());
+        let rect = Rect::from_min_size(pos2(left, top), size);
+
+        let mut x = rect.left();
+        let buttons = actions
+            .iter()
+            .zip(galleys)
+            .map(|(&action, galley)| {
+                let button_width = galley.size().x + 2.0 * padding.x;
+                let button_rect =
+                    Rect::from_min_size(pos2(x, rect.top()), vec2(button_width, height));
+                x += button_width;
+                (action, button_rect, galley)
+            })
+            .collect();
+
+        Self { rect, buttons }
+    }
+
+    pub(crate) fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    #[cfg(test)]
+    pub(crate) fn button_rect(&self, action: TextAction) -> Option<Rect> {
+        self.buttons
+            .iter()
+            .find(|(button, _, _)| *button == action)
+            .map(|(_, rect, _)| *rect)
+    }
+
+    /// Get the action of the button at the given position, if any.
+    pub(crate) fn action_at(&self, pos: Pos2) -> Option<TextAction> {
+        self.buttons
+            .iter()
+            .find(|(_, rect, _)| rect.contains(pos))
+            .map(|(action, _, _)| *action)
+    }
+
+    pub(crate) fn paint(&self, painter: &Painter) {
+        let visuals = &painter.ctx().style().visuals;
+        let corner_radius = visuals.menu_corner_radius;
+
+        painter.add(visuals.popup_shadow.as_shape(self.rect, corner_radius));
+        painter.rect(
+            self.rect,
+            corner_radius,
+            visuals.window_fill,
+            visuals.window_stroke,
+            egui::StrokeKind::Outside,
+        );
+
+        for (index, (_, rect, galley)) in self.buttons.iter().enumerate() {
+            if index > 0 {
+                painter.add(Shape::line_segment(
+                    [
+                        rect.left_top() + Vec2::Y * 6.0,
+                        rect.left_bottom() - Vec2::Y * 6.0,
+                    ],
+                    Stroke::new(1.0, visuals.weak_text_color().gamma_multiply(0.5)),
+                ));
+            }
+
+            let pos = Align2::CENTER_CENTER
+                .align_size_within_rect(galley.size(), *rect)
+                .min;
+            painter.galley(pos, galley.clone(), Color32::PLACEHOLDER);
+        }
+    }
+}
@@ -1,6 +1,31 @@
 mod activity;
+mod gamepad;
+mod graphics;
+mod insets;
 mod internal;
+mod open_url;
+mod share;
+mod storage;
+mod stylus;
+mod system_bars;
+mod text_input;
 
 pub mod entrypoint;
+pub mod headless;
 pub use activity::Activity;
 pub use egui;
+pub use egui_glow;
+#[cfg(feature = "wgpu")]
+pub use egui_wgpu;
+pub use gamepad::{gamepads, GamepadButton, GamepadState};
+pub use graphics::{GraphicsError, GraphicsOptions};
+pub use insets::{keyboard_height, safe_area, window_insets, SoftInputAdjust, WindowInsets};
+pub use open_url::OpenUrlError;
+pub use share::{share, share_with_result, ShareContent, ShareResult};
+pub use storage::{get_value, set_value, Storage};
+pub use stylus::{stylus_samples, StylusSample, ToolType};
+pub use system_bars::{set_system_bars, system_bars, SystemBars};
+pub use text_input::{
+    ime_action, set_text_input_options, text_input_options, ImeAction, InputType, TextInputOptions,
+    TextSelectionMenu,
+};
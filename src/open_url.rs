@@ -0,0 +1,157 @@
+use egui::Color32;
+use std::{error::Error, fmt};
+
+type Source = Box<dyn Error + Send + Sync>;
+
+/// Why a URL opened with [`egui::Context::open_url`] could not be opened.
+///
+/// These are passed to
+/// [`Activity::on_open_url_error`](crate::Activity::on_open_url_error).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OpenUrlError {
+    /// No installed app can open the URL.
+    NoHandler,
+
+    /// The URL could not be parsed, such as an `intent:` URL with invalid
+    /// syntax.
+    InvalidUrl(Source),
+
+    /// Starting the app that opens the URL failed.
+    Launch(Source),
+}
+
+impl fmt::Display for OpenUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoHandler => write!(f, "no app can open the URL"),
+            Self::InvalidUrl(e) => write!(f, "invalid URL: {e}"),
+            Self::Launch(e) => write!(f, "failed to start app for URL: {e}"),
+        }
+    }
+}
+
+impl Error for OpenUrlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::InvalidUrl(e) | Self::Launch(e) => Some(e.as_ref()),
+            Self::NoHandler => None,
+        }
+    }
+}
+
+/// What a URL is opened with, decided by its scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum UrlTarget {
+    /// The intent an `intent:` URL describes.
+    Intent,
+
+    /// An email app, to write to a `mailto:` address.
+    Email,
+
+    /// The dialer, with a `tel:` number.
+    Dialer,
+
+    /// A web page, in a Custom Tab with the given toolbar color if there is
+    /// one, or otherwise in the browser.
+    Web { custom_tab: Option<Color32> },
+
+    /// Whichever app handles the URL's scheme.
+    Other,
+}
+
+/// Decide what to open a URL with. Only web pages are opened in a Custom Tab,
+/// and nothing can open a URL without a valid scheme.
+pub(crate) fn url_target(
+    url: &str,
+    custom_tab: Option<Color32>,
+) -> Result<UrlTarget, OpenUrlError> {
+    let Some((scheme, _)) = url.split_once(':').filter(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+    }) else {
+        return Err(OpenUrlError::NoHandler);
+    };
+
+    Ok(match scheme.to_ascii_lowercase().as_str() {
+        "intent" => UrlTarget::Intent,
+        "mailto" => UrlTarget::Email,
+        "tel" => UrlTarget::Dialer,
+        "http" | "https" => UrlTarget::Web { custom_tab },
+        _ => UrlTarget::Other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::testing::*;
+
+    #[test]
+    fn target_from_scheme() {
+        let color = Some(Color32::RED);
+        let urls = [
+            ("https://example.com", UrlTarget::Web { custom_tab: color }),
+            ("HTTP://example.com", UrlTarget::Web { custom_tab: color }),
+            ("mailto:someone@example.com", UrlTarget::Email),
+            ("tel:+123456789", UrlTarget::Dialer),
+            ("intent://scan/#Intent;scheme=zxing;end", UrlTarget::Intent),
+            ("geo:0,0?q=coffee", UrlTarget::Other),
+            ("com.example.app+x://callback", UrlTarget::Other),
+        ];
+
+        for (url, target) in urls {
+            assert_eq!(url_target(url, color).ok(), Some(target), "{url}");
+        }
+    }
+
+    #[test]
+    fn no_handler_without_scheme() {
+        for url in ["example.com", "/docs", ":path", "1http://example.com", ""] {
+            assert!(
+                matches!(url_target(url, None), Err(OpenUrlError::NoHandler)),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn open_url_in_custom_tab_or_browser() {
+        let errors = Log::default();
+        let runner = launch(
+            TestActivity::default()
+                .with_update({
+                    let mut urls = vec![
+                        egui::OpenUrl::same_tab("https://example.com"),
+                        egui::OpenUrl::new_tab("https://example.com/docs"),
+                        egui::OpenUrl::same_tab("example.com"),
+                    ];
+                    move |ctx| {
+                        for url in urls.drain(..) {
+                            ctx.open_url(url);
+                        }
+                    }
+                })
+                .with_custom_tabs()
+                .with_open_url_errors(record(&errors)),
+        );
+
+        // Only the link opened in the same tab stays within the app, with a
+        // toolbar matching the UI.
+        let panel_fill = runner.context().style().visuals.panel_fill;
+        assert_eq!(
+            runner.custom_tab_urls(),
+            [("https://example.com".to_owned(), panel_fill)]
+        );
+
+        assert_eq!(
+            *errors.borrow(),
+            [(
+                "example.com".to_owned(),
+                "no app can open the URL".to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use egui::{Context, Id};
+use std::sync::{Arc, Mutex};
+
+/// Something to share with another app through the Android share sheet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShareContent {
+    /// Plain text.
+    Text(String),
+
+    /// A link, which the share sheet shows a preview of.
+    Url(String),
+
+    /// The contents of a file, such as an image the app generated.
+    ///
+    /// The file is written to the app's cache directory and shared through
+    /// the app's `FileProvider`, which has to be set up as described in the
+    /// README.
+    File {
+        /// File name shown to the receiving app, such as `chart.png`.
+        name: String,
+
+        /// MIME type of the file, such as `image/png`.
+        mime_type: String,
+
+        bytes: Vec<u8>,
+    },
+}
+
+/// What happened to a share sheet opened with [`share_with_result`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShareResult {
+    /// The user picked an app to share with.
+    Shared,
+
+    /// The share sheet was dismissed without picking an app.
+    Dismissed,
+
+    /// The share sheet was closed, but whether an app was picked is unknown,
+    /// because the activity doesn't report it. See [`share_with_result`].
+    Closed,
+
+    /// The share sheet could not be opened.
+    Failed(String),
+}
+
+/// Open the share sheet to share something with another app.
+///
+/// The share sheet is opened after the current frame.
+pub fn share(ctx: &Context, content: ShareContent) {
+    request(ctx, content, None);
+}
+
+/// Open the share sheet like [`share`], and call `on_result` once it is
+/// closed.
+///
+/// Android only reports whether an app was picked from the share sheet to a
+/// `BroadcastReceiver`, so this needs the glue code in
+/// `examples/java/MainActivity.java`, including a
+/// `private native void onShareTargetChosen()` method. Without it, the result
+/// is always [`ShareResult::Closed`] or [`ShareResult::Failed`].
+///
+/// The report can arrive after the app regains focus, so the result may be
+/// given up to half a second after the share sheet is closed.
+pub fn share_with_result(
+    ctx: &Context,
+    content: ShareContent,
+    on_result: impl FnOnce(ShareResult) + Send + 'static,
+) {
+    request(ctx, content, Some(Box::new(on_result)));
+}
+
+/// Called with the result of a share.
+pub(crate) type ShareCallback = Box<dyn FnOnce(ShareResult) + Send>;
+
+/// A share requested by the app that has not been started yet.
+pub(crate) struct ShareRequest {
+    pub(crate) content: ShareContent,
+    pub(crate) on_result: Option<ShareCallback>,
+}
+
+/// Shares requested during the current pass. Wrapped so that it can be kept
+/// in egui's temporary data, which has to be cloneable.
+#[derive(Clone, Default)]
+struct ShareRequests(Arc<Mutex<Vec<ShareRequest>>>);
+
+fn request(ctx: &Context, content: ShareContent, on_result: Option<ShareCallback>) {
+    let requests = ctx.data_mut(|data| {
+        data.get_temp_mut_or_default::<ShareRequests>(Id::NULL)
+            .clone()
+    });
+    requests
+        .0
+        .lock()
+        .unwrap()
+        .push(ShareRequest { content, on_result });
+}
+
+/// Take the shares requested since this was last called.
+pub(crate) fn take_share_requests(ctx: &Context) -> Vec<ShareRequest> {
+    ctx.data(|data| data.get_temp::<ShareRequests>(Id::NULL))
+        .map(|requests| std::mem::take(&mut *requests.0.lock().unwrap()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::LifecycleEvent;
+    use crate::internal::testing::*;
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    #[test]
+    fn share_reports_result_once_closed() {
+        let url = ShareContent::Url("https://example.com".into());
+        let file = ShareContent::File {
+            name: "chart.png".into(),
+            mime_type: "image/png".into(),
+            bytes: vec![1, 2, 3],
+        };
+        let share = Rc::new(RefCell::new(Some(url)));
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        let mut runner = launch(TestActivity::default().with_update({
+            let (share, results) = (share.clone(), results.clone());
+            move |ctx| {
+                if let Some(content) = share.take() {
+                    let results = results.clone();
+                    crate::share_with_result(ctx, content, move |result| {
+                        results.lock().unwrap().push(result);
+                    });
+                }
+            }
+        }));
+
+        // The share sheet takes focus from the window, and gives it back once
+        // it is closed.
+        runner.push_event(LifecycleEvent::LostFocus);
+        runner.run_once();
+        assert!(results.lock().unwrap().is_empty());
+        runner.push_event(LifecycleEvent::GainedFocus);
+        runner.run_once();
+        assert!(results.lock().unwrap().is_empty());
+        runner.advance_time(Duration::from_secs(1));
+        runner.run_once();
+        assert_eq!(*results.lock().unwrap(), [ShareResult::Dismissed]);
+
+        // Whether an app was picked can be reported after focus returns.
+        *share.borrow_mut() = Some(file);
+        runner.push_event(LifecycleEvent::RedrawNeeded);
+        runner.run_once();
+        runner.push_event(LifecycleEvent::LostFocus);
+        runner.run_once();
+        runner.push_event(LifecycleEvent::GainedFocus);
+        runner.run_once();
+        runner.choose_share_target();
+        runner.run_once();
+        assert_eq!(
+            *results.lock().unwrap(),
+            [ShareResult::Dismissed, ShareResult::Shared]
+        );
+    }
+}
@@ -0,0 +1,95 @@
+/// A key-value store for persisting app data across launches.
+///
+/// This has the same methods as
+/// [`eframe::Storage`](https://docs.rs/eframe/latest/eframe/trait.Storage.html),
+/// but is a trait of its own, as depending on eframe would also pull in winit.
+/// Values are serialized the same way as eframe does with [`get_value`] and
+/// [`set_value`], so persistence code can be shared between an app's desktop
+/// and Android builds by switching which crate these come from.
+pub trait Storage {
+    /// Get the value for the given key.
+    fn get_string(&self, key: &str) -> Option<String>;
+
+    /// Set the value for the given key.
+    fn set_string(&mut self, key: &str, value: String);
+
+    /// Write any changes to disk.
+    fn flush(&mut self);
+}
+
+/// Get and deserialize the value for the given key, like `eframe::get_value`.
+pub fn get_value<T: serde::de::DeserializeOwned>(storage: &dyn Storage, key: &str) -> Option<T> {
+    let value = storage.get_string(key)?;
+    match ron::from_str(&value) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            log::warn!("failed to deserialize stored value for {key:?}: {e}");
+            None
+        }
+    }
+}
+
+/// Serialize and set the value for the given key, like `eframe::set_value`.
+pub fn set_value<T: serde::Serialize>(storage: &mut dyn Storage, key: &str, value: &T) {
+    match ron::to_string(value) {
+        Ok(value) => storage.set_string(key, value),
+        Err(e) => log::warn!("failed to serialize value for {key:?}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{HeadlessRunner, LifecycleEvent};
+    use crate::internal::testing::*;
+    use std::collections::HashMap;
+
+    impl Storage for HashMap<String, String> {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.insert(key.to_owned(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let mut storage = HashMap::new();
+        set_value(&mut storage, "point", &(1, "two".to_owned()));
+
+        assert_eq!(storage["point"], "(1,\"two\")");
+        assert_eq!(get_value(&storage, "point"), Some((1, "two".to_owned())));
+        assert_eq!(get_value::<u32>(&storage, "missing"), None);
+    }
+
+    #[test]
+    fn invalid_value_is_ignored() {
+        let mut storage = HashMap::new();
+        storage.set_string("count", "not a number".to_owned());
+
+        assert_eq!(get_value::<u32>(&storage, "count"), None);
+    }
+
+    #[test]
+    fn persists_storage_across_runs() {
+        let data_dir =
+            std::env::temp_dir().join(format!("egui-android-storage-test-{}", std::process::id()));
+
+        let mut runner = HeadlessRunner::<Counter>::with_data_dir(&data_dir);
+        runner.push_event(LifecycleEvent::Start);
+        runner.run_once();
+        runner.activity_mut().count = 5;
+        runner.push_event(LifecycleEvent::Pause);
+        runner.run_once();
+
+        let runner = HeadlessRunner::<Counter>::with_data_dir(&data_dir);
+        let count = runner.activity().count;
+        std::fs::remove_dir_all(&data_dir).unwrap();
+
+        assert_eq!(count, 5);
+    }
+}
@@ -0,0 +1,129 @@
+use egui::{Context, Id, Pos2, TouchId, TouchPhase};
+use std::sync::Arc;
+
+/// What a pointer is, such as a finger or a stylus.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToolType {
+    #[default]
+    Unknown,
+    Finger,
+    Stylus,
+    Mouse,
+    Eraser,
+    Palm,
+}
+
+/// A sample of a finger, stylus or eraser touching or hovering over the
+/// screen, with the details that egui's touch events leave out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StylusSample {
+    /// Identifies the pointer for as long as it touches or hovers over the
+    /// screen, the same as in egui's [`egui::Event::Touch`] events.
+    pub id: TouchId,
+
+    pub tool_type: ToolType,
+
+    /// Whether the pointer started touching or hovering, moved, or stopped.
+    pub phase: TouchPhase,
+
+    /// Whether the pointer is hovering above the screen instead of touching
+    /// it.
+    pub hovering: bool,
+
+    /// Position in points.
+    pub pos: Pos2,
+
+    /// How hard the pointer presses on the screen, usually from 0 to 1.
+    pub pressure: f32,
+
+    /// Angle of a stylus away from perpendicular to the screen, in radians. 0
+    /// is upright, and π/2 is flat against the screen.
+    pub tilt: f32,
+
+    /// Direction a stylus points in, in radians. 0 points to the top of the
+    /// screen, and positive angles are clockwise, up to π.
+    pub orientation: f32,
+
+    /// Distance of a hovering stylus from the screen, in units that depend on
+    /// the device, or 0 if it is touching the screen or the device can't tell.
+    pub distance: f32,
+}
+
+/// Get the samples of fingers, styluses and erasers received for this pass,
+/// oldest first.
+///
+/// These include every sample received since the previous pass, not just the
+/// latest one, so drawing them all gives smoother strokes than egui's pointer
+/// position. Android doesn't provide the samples it batches into a single
+/// event yet, so those are still missing.
+/// Knowing which tool touched the screen also makes it possible to ignore a
+/// palm resting on it, or to erase with the back of a stylus.
+pub fn stylus_samples(ctx: &Context) -> Vec<StylusSample> {
+    ctx.data(|data| data.get_temp::<StylusSamples>(Id::NULL))
+        .map(|samples| samples.0.to_vec())
+        .unwrap_or_default()
+}
+
+/// Samples received for a pass, kept in egui's temporary data.
+#[derive(Clone, Default)]
+pub(crate) struct StylusSamples(Arc<[StylusSample]>);
+
+impl StylusSamples {
+    /// Make the samples available to [`stylus_samples`] during the next pass,
+    /// replacing the ones from the previous pass.
+    pub(crate) fn store(samples: Vec<StylusSample>, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, Self(samples.into())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{InputEvent, MotionAction};
+    use crate::internal::testing::*;
+    use egui::pos2;
+
+    #[test]
+    fn stylus_samples_include_hover_and_tilt() {
+        let samples: Log<StylusSample> = Log::default();
+        let mut runner = launch(
+            TestActivity::default().with_update(shared(&samples, |samples, ctx| {
+                samples.borrow_mut().extend(crate::stylus_samples(ctx))
+            })),
+        );
+
+        let stylus = |action, tilt, distance| {
+            let InputEvent::Motion(mut event) = touch(action, 10.0, 20.0) else {
+                unreachable!()
+            };
+            event.pointers[0].tool_type = ToolType::Stylus;
+            event.pointers[0].tilt = tilt;
+            event.pointers[0].distance = distance;
+            InputEvent::Motion(event)
+        };
+        runner.push_input(stylus(MotionAction::HoverEnter, 0.0, 2.0));
+        runner.push_input(stylus(MotionAction::HoverExit, 0.0, 1.0));
+        runner.push_input(stylus(MotionAction::Down, 0.5, 0.0));
+        runner.run_once();
+        runner.push_input(stylus(MotionAction::Up, 0.5, 0.0));
+        runner.run_once();
+
+        let samples = samples.borrow();
+        let phases: Vec<_> = samples
+            .iter()
+            .map(|sample| (sample.phase, sample.hovering, sample.tilt, sample.distance))
+            .collect();
+        assert_eq!(
+            phases,
+            [
+                (egui::TouchPhase::Start, true, 0.0, 2.0),
+                (egui::TouchPhase::End, true, 0.0, 1.0),
+                (egui::TouchPhase::Start, false, 0.5, 0.0),
+                (egui::TouchPhase::End, false, 0.5, 0.0),
+            ]
+        );
+        assert!(samples
+            .iter()
+            .all(|sample| sample.tool_type == ToolType::Stylus && sample.pos == pos2(10.0, 20.0)));
+    }
+}
@@ -0,0 +1,83 @@
+use egui::{Context, Id, Theme};
+
+/// How the status bar and navigation bar are shown.
+///
+/// Set with [`set_system_bars`]. Until it is called, the bars are left as the
+/// app's Android theme configures them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SystemBars {
+    /// Draw the UI behind the system bars, which are made transparent.
+    ///
+    /// Use [`safe_area`](crate::safe_area) to keep content clear of them.
+    pub edge_to_edge: bool,
+
+    /// Hide the system bars. The user can still reveal them for a moment by
+    /// swiping in from the edge of the screen.
+    pub hidden: bool,
+
+    /// The theme the bars should suit: icons are drawn dark for
+    /// [`Theme::Light`] and light for [`Theme::Dark`]. If `None`, this follows
+    /// the current egui theme.
+    pub theme: Option<Theme>,
+}
+
+/// Get the system bar settings most recently given to [`set_system_bars`].
+pub fn system_bars(ctx: &Context) -> SystemBars {
+    ctx.data(|data| data.get_temp(Id::NULL)).unwrap_or_default()
+}
+
+/// Change how the status bar and navigation bar are shown.
+///
+/// The settings are applied after the current frame, and again whenever the
+/// device configuration or the egui theme changes.
+pub fn set_system_bars(ctx: &Context, system_bars: SystemBars) {
+    ctx.data_mut(|data| data.insert_temp(Id::NULL, system_bars));
+}
+
+/// Get the system bar settings, if the app has set any.
+pub(crate) fn requested_system_bars(ctx: &Context) -> Option<SystemBars> {
+    ctx.data(|data| data.get_temp(Id::NULL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::LifecycleEvent;
+    use crate::internal::testing::*;
+    use egui::Theme;
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn system_bars_follow_theme() {
+        let system_bars = SystemBars {
+            edge_to_edge: true,
+            ..SystemBars::default()
+        };
+        let set_system_bars = Rc::new(Cell::new(false));
+
+        let mut runner = launch(TestActivity::default().with_update(shared(
+            &set_system_bars,
+            move |set_system_bars, ctx| {
+                if set_system_bars.get() {
+                    crate::set_system_bars(ctx, system_bars);
+                }
+            },
+        )));
+        assert_eq!(runner.system_bars(), None);
+
+        set_system_bars.set(true);
+        runner.push_event(LifecycleEvent::ConfigChanged {
+            density: None,
+            theme: Some(Theme::Dark),
+        });
+        runner.run_once();
+        assert_eq!(runner.system_bars(), Some((system_bars, Theme::Dark)));
+
+        runner.push_event(LifecycleEvent::ConfigChanged {
+            density: None,
+            theme: Some(Theme::Light),
+        });
+        runner.run_once();
+        assert_eq!(runner.system_bars(), Some((system_bars, Theme::Light)));
+    }
+}
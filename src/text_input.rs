@@ -0,0 +1,337 @@
+use egui::{Context, Id};
+
+/// How the soft keyboard is set up while a text field has focus.
+///
+/// Set for a text field with [`set_text_input_options`]. Fields without
+/// options get the default, a general text keyboard.
+///
+/// Only a `GameActivity` can set up its keyboard, so these options are
+/// ignored unless the `game-activity` feature is enabled. In a
+/// `NativeActivity` the enter key still reports [`ime_action`], but keeps its
+/// usual label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextInputOptions {
+    /// The kind of text the field accepts, which decides the keyboard layout.
+    pub input_type: InputType,
+
+    /// What the keyboard's enter key does, and how it is labelled.
+    pub ime_action: ImeAction,
+
+    /// Let the keyboard suggest and make corrections. Ignored for passwords,
+    /// which never get suggestions.
+    pub autocorrect: bool,
+}
+
+impl Default for TextInputOptions {
+    fn default() -> Self {
+        Self {
+            input_type: InputType::default(),
+            ime_action: ImeAction::default(),
+            autocorrect: true,
+        }
+    }
+}
+
+/// The kind of text a text field accepts, like Android's `inputType`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputType {
+    /// Any single line of text.
+    #[default]
+    Text,
+
+    /// Text that may span multiple lines.
+    Multiline,
+
+    /// Whole numbers.
+    Number,
+
+    /// Numbers that may have a decimal point.
+    Decimal,
+
+    /// A phone number.
+    Phone,
+
+    /// An email address.
+    Email,
+
+    /// A URL or other URI.
+    Uri,
+
+    /// A password, which the keyboard does not learn from.
+    Password,
+
+    /// A password made up of digits, such as a PIN.
+    NumberPassword,
+}
+
+/// The action performed by the soft keyboard's enter key, like Android's
+/// `imeOptions`.
+///
+/// When the key is pressed, the action is reported by [`ime_action`]. `Next`
+/// and `Previous` also move focus to the next or previous widget, and the
+/// other actions are also sent to egui as an enter key press.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImeAction {
+    /// Let the keyboard choose, usually `Done` for single line fields.
+    #[default]
+    Unspecified,
+
+    /// A plain enter key, which starts a new line in multiline fields.
+    None,
+
+    Go,
+    Search,
+    Send,
+    Next,
+    Previous,
+    Done,
+}
+
+/// The menu of clipboard actions shown for selected text, chosen with
+/// [`Activity::text_selection_menu`](crate::Activity::text_selection_menu).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextSelectionMenu {
+    /// A toolbar drawn with egui, in the style of the app.
+    #[default]
+    Egui,
+
+    /// Android's own floating toolbar, which also lists actions offered by
+    /// other apps, such as translating or sharing the selected text.
+    ///
+    /// The toolbar is an `ActionMode`, which has to be started from Java, so
+    /// the activity needs the glue code in `examples/java/MainActivity.java`,
+    /// including a `private native void onTextAction(int itemId)` method.
+    /// Without it, the egui toolbar is shown instead.
+    System,
+}
+
+impl TextSelectionMenu {
+    pub(crate) fn store(self, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, self));
+    }
+
+    pub(crate) fn load(ctx: &Context) -> Self {
+        ctx.data(|data| data.get_temp(Id::NULL)).unwrap_or_default()
+    }
+}
+
+/// Set how the soft keyboard is set up while the text field with the given ID
+/// has focus.
+///
+/// ```ignore
+/// let response = ui.text_edit_singleline(&mut query);
+/// egui_android::set_text_input_options(
+///     ui.ctx(),
+///     response.id,
+///     TextInputOptions {
+///         ime_action: ImeAction::Search,
+///         ..Default::default()
+///     },
+/// );
+/// ```
+pub fn set_text_input_options(ctx: &Context, id: Id, options: TextInputOptions) {
+    ctx.data_mut(|data| data.insert_temp(id, options));
+}
+
+/// Get the options given to [`set_text_input_options`] for a text field.
+pub fn text_input_options(ctx: &Context, id: Id) -> TextInputOptions {
+    ctx.data(|data| data.get_temp(id)).unwrap_or_default()
+}
+
+/// Get the action the soft keyboard's enter key performed on the text field
+/// with the given ID during this pass, if it was pressed.
+pub fn ime_action(ctx: &Context, id: Id) -> Option<ImeAction> {
+    ctx.data(|data| data.get_temp::<TriggeredImeAction>(Id::NULL))
+        .filter(|triggered| triggered.id == id)
+        .map(|triggered| triggered.action)
+}
+
+/// An IME action performed on a text field.
+#[derive(Clone, Copy)]
+pub(crate) struct TriggeredImeAction {
+    pub(crate) id: Id,
+    pub(crate) action: ImeAction,
+}
+
+impl TriggeredImeAction {
+    /// Make the action available to [`ime_action`] during the next pass.
+    pub(crate) fn store(self, ctx: &Context) {
+        ctx.data_mut(|data| data.insert_temp(Id::NULL, self));
+    }
+
+    pub(crate) fn clear(ctx: &Context) {
+        ctx.data_mut(|data| data.remove::<Self>(Id::NULL));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{
+        InputEvent, KeyAction, KeyEvent, MotionAction, TextAction, TextInputState, TextSpan,
+    };
+    use crate::internal::testing::*;
+    use egui::{vec2, Key, Modifiers};
+    use std::{rc::Rc, time::Duration};
+
+    #[test]
+    fn syncs_text_with_soft_keyboard() {
+        let field = Rc::new(TextField::new("a "));
+        let mut runner =
+            launch(TestActivity::default().with_update(shared(&field, TextField::show)));
+        runner.run_once();
+
+        let state =
+            |text: &str, selection: usize, compose_region: Option<(usize, usize)>| TextInputState {
+                text: text.into(),
+                selection: TextSpan {
+                    start: selection,
+                    end: selection,
+                },
+                compose_region: compose_region.map(|(start, end)| TextSpan { start, end }),
+            };
+        // egui doesn't report the text of a field that was focused without
+        // being clicked.
+        assert_eq!(runner.text_input_state(), Some(TextInputState::default()));
+
+        let ime = runner.take_frames().pop().unwrap().platform_output.ime;
+        let pos = ime.unwrap().rect.right_center();
+        runner.push_input(touch(MotionAction::Down, pos.x, pos.y));
+        runner.run_once();
+        runner.push_input(touch(MotionAction::Up, pos.x, pos.y));
+        runner.run_once();
+
+        let initial = state("a ", 2, None);
+        assert_eq!(runner.text_input_state(), Some(initial.clone()));
+
+        // Composing and committing text is not echoed back to the keyboard.
+        for (text, selection, compose_region) in [
+            ("a wo", 4, Some((2, 4))),
+            ("a wörl", 6, Some((2, 6))),
+            ("a world ", 8, None),
+        ] {
+            runner.push_input(InputEvent::Text(state(text, selection, compose_region)));
+            runner.run_once();
+            assert_eq!(field.text(), text);
+        }
+        assert_eq!(runner.text_input_state(), Some(initial));
+
+        // Text typed on the egui side is sent to the keyboard.
+        runner.push_input(InputEvent::Key(KeyEvent {
+            device_id: 0,
+            action: KeyAction::Down,
+            key: None,
+            physical_key: None,
+            text: Some('!'),
+            gamepad_button: None,
+            repeat_count: 0,
+            modifiers: Modifiers::NONE,
+        }));
+        runner.run_once();
+        assert_eq!(runner.text_input_state(), Some(state("a world !", 9, None)));
+    }
+
+    #[test]
+    fn text_field_configures_soft_keyboard() {
+        let options = TextInputOptions {
+            input_type: InputType::Email,
+            ime_action: ImeAction::Search,
+            autocorrect: false,
+        };
+        let field = Rc::new(TextField {
+            options: Some(options),
+            ..TextField::default()
+        });
+        let mut runner =
+            launch(TestActivity::default().with_update(shared(&field, TextField::show)));
+        runner.run_once();
+        assert_eq!(runner.text_input_options(), options);
+
+        for action in [KeyAction::Down, KeyAction::Up] {
+            runner.push_input(InputEvent::Key(KeyEvent {
+                device_id: 0,
+                action,
+                key: Some(Key::Enter),
+                physical_key: Some(Key::Enter),
+                text: Some('\n'),
+                gamepad_button: None,
+                repeat_count: 0,
+                modifiers: Modifiers::NONE,
+            }));
+        }
+        runner.run_once();
+        assert_eq!(*field.ime_actions.borrow(), [ImeAction::Search]);
+    }
+
+    #[test]
+    fn long_press_selects_word_and_shows_toolbar() {
+        let field = Rc::new(TextField::new("hello world"));
+        let mut runner =
+            launch(TestActivity::default().with_update(shared(&field, TextField::show)));
+        runner.run_once();
+
+        let ime = runner.take_frames().pop().unwrap().platform_output.ime;
+        let pos = ime.unwrap().rect.left_center() + vec2(55.0, 0.0);
+        runner.push_input(touch(MotionAction::Down, pos.x, pos.y));
+        runner.run_once();
+        runner.advance_time(Duration::from_millis(600));
+        runner.run_once();
+        runner.push_input(touch(MotionAction::Up, pos.x, pos.y));
+        runner.run_once();
+
+        let selection = runner.text_input_state().unwrap().selection;
+        assert_eq!((selection.start, selection.end), (6, 11));
+
+        // Tap the toolbar's cut button.
+        let cut = crate::internal::plugins::text_toolbar_button(runner.context(), TextAction::Cut)
+            .expect("toolbar is shown");
+        runner.push_input(touch(MotionAction::Down, cut.x, cut.y));
+        runner.run_once();
+        runner.push_input(touch(MotionAction::Up, cut.x, cut.y));
+        runner.run_once();
+        runner.run_once();
+
+        assert_eq!(field.text(), "hello ");
+        assert_eq!(runner.clipboard_text(), "world");
+    }
+
+    #[test]
+    fn long_press_shows_system_text_action_menu() {
+        let field = Rc::new(TextField::new("hello world"));
+        let mut runner = launch(
+            TestActivity::default()
+                .with_update(shared(&field, TextField::show))
+                .with_text_selection_menu(TextSelectionMenu::System),
+        );
+        runner.run_once();
+
+        let ime = runner.take_frames().pop().unwrap().platform_output.ime;
+        let pos = ime.unwrap().rect.left_center() + vec2(55.0, 0.0);
+        runner.push_input(touch(MotionAction::Down, pos.x, pos.y));
+        runner.run_once();
+        runner.advance_time(Duration::from_millis(600));
+        runner.run_once();
+        runner.push_input(touch(MotionAction::Up, pos.x, pos.y));
+        runner.run_once();
+        runner.run_once();
+
+        let menu = runner.text_action_menu().expect("menu is shown");
+        assert_eq!(
+            menu.actions,
+            [
+                TextAction::Cut,
+                TextAction::Copy,
+                TextAction::Paste,
+                TextAction::SelectAll
+            ]
+        );
+        assert_eq!(menu.selected_text.as_deref(), Some("world"));
+
+        runner.push_input(InputEvent::TextAction(Some(TextAction::Copy)));
+        runner.run_once();
+        runner.run_once();
+
+        assert_eq!(runner.clipboard_text(), "world");
+        assert_eq!(runner.text_action_menu(), None);
+    }
+}